@@ -0,0 +1,82 @@
+//! Cost of entity range queries and AI updates at thousands of entities per map.
+//!
+//! Run with `cargo bench -p game-module --bench spatial`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use game_module::entity::{prefabs, EntityManager, Transform};
+use game_module::map::Vec2;
+
+const MAP_SIZE: f64 = 200.0;
+
+/// Deterministic pseudo-random positions spread over the map
+fn scatter(index: u64) -> Vec2 {
+    let hash = index
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        .rotate_left(17)
+        .wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    Vec2 {
+        x: (hash & 0xffff) as f64 / 65_536.0 * MAP_SIZE,
+        y: ((hash >> 16) & 0xffff) as f64 / 65_536.0 * MAP_SIZE,
+    }
+}
+
+/// One player for every twenty monsters
+fn populate(entities: u64) -> EntityManager {
+    let mut manager = EntityManager::new();
+    for i in 0..entities {
+        if i % 20 == 0 {
+            manager.spawn(prefabs::player(scatter(i)));
+        } else {
+            manager.spawn(prefabs::monster(scatter(i)));
+        }
+    }
+    manager
+}
+
+/// What range queries cost without the spatial index
+fn linear_range(manager: &EntityManager, position: &Vec2, range: f64) -> Vec<u64> {
+    manager
+        .store::<Transform>()
+        .iter()
+        .filter(|(_, transform)| transform.distance_to(position) <= range)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+fn range_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("range_query");
+    for entities in [1_000u64, 5_000, 10_000] {
+        let manager = populate(entities);
+        let center = Vec2 { x: 100.0, y: 100.0 };
+
+        group.bench_with_input(BenchmarkId::new("grid", entities), &manager, |b, m| {
+            b.iter(|| m.get_entities_in_range(black_box(&center), 8.0))
+        });
+        group.bench_with_input(BenchmarkId::new("linear", entities), &manager, |b, m| {
+            b.iter(|| linear_range(m, black_box(&center), 8.0))
+        });
+        group.bench_with_input(BenchmarkId::new("nearest", entities), &manager, |b, m| {
+            b.iter(|| m.nearest_entities(black_box(&center), 1, 8.0, |_| true))
+        });
+    }
+    group.finish();
+}
+
+fn ai_updates(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_entities");
+    group.sample_size(20);
+    for entities in [1_000u64, 5_000, 10_000] {
+        group.bench_function(BenchmarkId::from_parameter(entities), |b| {
+            let mut manager = populate(entities);
+            let mut time = 0.0;
+            b.iter(|| {
+                time += 0.05;
+                manager.update_entities(0.05, time);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, range_queries, ai_updates);
+criterion_main!(benches);
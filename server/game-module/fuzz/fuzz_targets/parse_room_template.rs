@@ -0,0 +1,16 @@
+#![no_main]
+
+use game_module::map_generator::room_manager::{RoomManager, RoomType};
+use libfuzzer_sys::fuzz_target;
+
+// Run with `cargo +nightly fuzz run parse_room_template` from server/game-module
+fuzz_target!(|text: &str| {
+    if let Ok(parsed) = RoomManager::parse_template_text("fuzz", RoomType::Combat, false, text) {
+        // Everything the parser reports must lie inside the room it parsed
+        let inside = |x: usize, y: usize| x < parsed.width && y < parsed.height;
+        assert!(parsed.tiles.len() == parsed.height);
+        assert!(parsed.connections.iter().all(|p| inside(p.x, p.y)));
+        assert!(parsed.spawn_points.iter().all(|p| inside(p.x, p.y)));
+        assert!(parsed.entrances.iter().all(|p| inside(p.x, p.y)));
+    }
+});
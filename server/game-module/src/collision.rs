@@ -0,0 +1,153 @@
+//! Swept movement of round entities through a map's walls.
+//!
+//! Tiles are unit squares centred on their coordinates, so tile (3, 4) covers
+//! x in [2.5, 3.5] and y in [3.5, 4.5].
+
+use crate::map::{Map, Vec2};
+
+/// Longest distance a sweep moves in one step, so small entities never skip over a wall
+const MAX_STEP: f64 = 0.25;
+
+/// Bisection rounds used to slide flush against an obstacle
+const CONTACT_ITERATIONS: u32 = 8;
+
+/// Circles smaller than this are treated as this size, so points still collide
+const MIN_RADIUS: f64 = 1e-3;
+
+/// Whether a circle overlaps a wall tile or sticks out of the map
+pub fn circle_hits_wall(map: &Map, center: &Vec2, radius: f64) -> bool {
+    let radius = radius.max(MIN_RADIUS);
+    let min_x = (center.x - radius + 0.5).floor() as i64;
+    let max_x = (center.x + radius + 0.5).floor() as i64;
+    let min_y = (center.y - radius + 0.5).floor() as i64;
+    let max_y = (center.y + radius + 0.5).floor() as i64;
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            if x >= 0 && y >= 0 && map.is_walkable(x as usize, y as usize) {
+                continue;
+            }
+            // Closest point of the tile to the circle's centre
+            let closest_x = center.x.clamp(x as f64 - 0.5, x as f64 + 0.5);
+            let closest_y = center.y.clamp(y as f64 - 0.5, y as f64 + 0.5);
+            let dx = center.x - closest_x;
+            let dy = center.y - closest_y;
+            if dx * dx + dy * dy < radius * radius {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Move from `from` towards `to` in small steps, stopping at obstacles and sliding along
+/// them. `blocked(current, next)` decides whether a step may be taken.
+pub fn sweep(from: &Vec2, to: &Vec2, blocked: impl Fn(&Vec2, &Vec2) -> bool) -> Vec2 {
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return from.clone();
+    }
+
+    let steps = (length / MAX_STEP).ceil();
+    let (step_x, step_y) = (dx / steps, dy / steps);
+    let mut position = from.clone();
+
+    for _ in 0..steps as u64 {
+        // Each axis on its own, so a blocked axis still lets the other one slide
+        let moved_x = advance(&mut position, step_x, 0.0, &blocked);
+        let moved_y = advance(&mut position, 0.0, step_y, &blocked);
+        if !moved_x && !moved_y {
+            break;
+        }
+    }
+    position
+}
+
+/// Move by (dx, dy), or as far as possible towards it. Returns whether the position changed.
+fn advance(position: &mut Vec2, dx: f64, dy: f64, blocked: &impl Fn(&Vec2, &Vec2) -> bool) -> bool {
+    if dx == 0.0 && dy == 0.0 {
+        return false;
+    }
+    let offset = |fraction: f64| Vec2 {
+        x: position.x + dx * fraction,
+        y: position.y + dy * fraction,
+    };
+
+    let full = offset(1.0);
+    if !blocked(position, &full) {
+        *position = full;
+        return true;
+    }
+
+    // Find how close the obstacle can be approached
+    let (mut free, mut hit) = (0.0, 1.0);
+    for _ in 0..CONTACT_ITERATIONS {
+        let middle = (free + hit) / 2.0;
+        if blocked(position, &offset(middle)) {
+            hit = middle;
+        } else {
+            free = middle;
+        }
+    }
+    if free == 0.0 {
+        return false;
+    }
+    *position = offset(free);
+    true
+}
+
+/// Sweep a circle of `radius` through the walls of `map`. An entity that is already stuck in
+/// a wall may move freely until it is out.
+pub fn sweep_circle(map: &Map, from: &Vec2, to: &Vec2, radius: f64) -> Vec2 {
+    sweep(from, to, |current, next| {
+        circle_hits_wall(map, next, radius) && !circle_hits_wall(map, current, radius)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::tests::map_from;
+
+    fn at(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    #[test]
+    fn test_walls_stop_movement() {
+        let map = map_from(&[
+            "#######", //
+            "#..#..#", //
+            "#..#..#", //
+            "#######",
+        ]);
+
+        // Far targets behind a wall no longer teleport across it
+        let end = sweep_circle(&map, &at(1.0, 1.0), &at(5.0, 1.0), 0.4);
+        assert!((end.x - 2.1).abs() < 0.01, "Stopped at {:?}", end);
+        assert!((end.y - 1.0).abs() < 1e-9);
+        assert!(!circle_hits_wall(&map, &end, 0.4));
+
+        // Points stop at the wall too
+        let end = sweep_circle(&map, &at(1.0, 1.0), &at(5.0, 1.0), 0.0);
+        assert!(end.x < 2.5 && end.x > 2.45);
+    }
+
+    #[test]
+    fn test_sliding_along_walls() {
+        let map = map_from(&[
+            "######", //
+            "#....#", //
+            "#....#", //
+            "######",
+        ]);
+
+        // Moving diagonally into the top wall keeps the sideways part of the motion
+        let end = sweep_circle(&map, &at(1.0, 2.0), &at(4.0, -1.0), 0.4);
+        assert!((end.y - 0.9).abs() < 0.01, "Stopped at {:?}", end);
+        assert!((end.x - 4.0).abs() < 0.11, "Stopped at {:?}", end);
+        assert!(!circle_hits_wall(&map, &end, 0.4));
+    }
+}
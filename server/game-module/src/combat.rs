@@ -0,0 +1,261 @@
+//! Combat resolution: hit, miss and critical rolls, damage types and mitigation.
+
+use spacetimedb::rand::rngs::StdRng;
+use spacetimedb::rand::{Rng, SeedableRng};
+
+/// Resistance rating that halves damage of its type
+pub const MITIGATION_SCALE: f64 = 50.0;
+
+/// Most of a hit any resistance can absorb
+pub const MAX_MITIGATION: f64 = 0.9;
+
+/// Attacks always keep at least this chance to hit, however evasive the target
+pub const MIN_HIT_CHANCE: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Ice,
+    Poison,
+}
+
+/// Resistance rating per damage type; negative ratings make the damage hurt more
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Resistances {
+    pub physical: i32,
+    pub fire: i32,
+    pub ice: i32,
+    pub poison: i32,
+}
+
+impl Resistances {
+    pub fn get(&self, damage_type: DamageType) -> i32 {
+        match damage_type {
+            DamageType::Physical => self.physical,
+            DamageType::Fire => self.fire,
+            DamageType::Ice => self.ice,
+            DamageType::Poison => self.poison,
+        }
+    }
+}
+
+/// Fraction of the damage a resistance rating absorbs. Each point is worth less than the
+/// one before; negative ratings give a negative fraction, down to -1 (double damage).
+pub fn mitigation(rating: i32) -> f64 {
+    let rating = rating as f64;
+    if rating >= 0.0 {
+        (rating / (rating + MITIGATION_SCALE)).min(MAX_MITIGATION)
+    } else {
+        rating / (-rating + MITIGATION_SCALE)
+    }
+}
+
+/// Damage left after a resistance rating. Any damage at all hurts a little, however well
+/// protected the target is.
+pub fn mitigate(damage: u32, rating: i32) -> u32 {
+    let factor = 1.0 - mitigation(rating);
+    ((damage as f64 * factor).round() as u32).max(damage.min(1))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitOutcome {
+    Miss,
+    Hit,
+    Critical,
+}
+
+/// Everything about one swing that the roll needs
+#[derive(Clone, Debug)]
+pub struct Strike {
+    pub damage: u32,
+    pub damage_type: DamageType,
+    pub accuracy: f64,        // Chance to hit a target without evasion
+    pub crit_chance: f64,     // Chance that a hit is critical
+    pub crit_multiplier: f64, // Damage multiplier of critical hits
+}
+
+/// How an attack turned out, for clients to display
+#[derive(Clone, Debug, PartialEq)]
+pub struct DamageBreakdown {
+    pub outcome: HitOutcome,
+    pub damage_type: DamageType,
+    pub raw_damage: u32, // After the critical multiplier, before resistances
+    pub mitigated_damage: i64, // Absorbed by resistance; negative when a weakness added damage
+    pub damage_dealt: u32,
+}
+
+/// Seeded source of combat rolls, so fights can be replayed
+#[derive(Debug)]
+pub struct Dice {
+    rng: StdRng,
+}
+
+impl Default for Dice {
+    fn default() -> Self {
+        Self::seeded(0)
+    }
+}
+
+impl Dice {
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// True with probability `chance`
+    pub fn chance(&mut self, chance: f64) -> bool {
+        self.rng.gen::<f64>() < chance
+    }
+
+    /// Whole number from `min` to `max`, both included
+    pub fn between(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            return min;
+        }
+        self.rng.gen_range(min..=max)
+    }
+}
+
+/// Roll one attack against a target with the given evasion and resistances
+pub fn resolve(
+    strike: &Strike,
+    evasion: f64,
+    resistances: &Resistances,
+    dice: &mut Dice,
+) -> DamageBreakdown {
+    let hit_chance = (strike.accuracy - evasion).clamp(MIN_HIT_CHANCE, 1.0);
+    if !dice.chance(hit_chance) {
+        return DamageBreakdown {
+            outcome: HitOutcome::Miss,
+            damage_type: strike.damage_type,
+            raw_damage: 0,
+            mitigated_damage: 0,
+            damage_dealt: 0,
+        };
+    }
+
+    let (outcome, raw_damage) = if dice.chance(strike.crit_chance) {
+        let damage = (strike.damage as f64 * strike.crit_multiplier).round() as u32;
+        (HitOutcome::Critical, damage)
+    } else {
+        (HitOutcome::Hit, strike.damage)
+    };
+
+    let damage_dealt = mitigate(raw_damage, resistances.get(strike.damage_type));
+
+    DamageBreakdown {
+        outcome,
+        damage_type: strike.damage_type,
+        raw_damage,
+        mitigated_damage: raw_damage as i64 - damage_dealt as i64,
+        damage_dealt,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strike(damage: u32, damage_type: DamageType) -> Strike {
+        Strike {
+            damage,
+            damage_type,
+            accuracy: 1.0,
+            crit_chance: 0.0,
+            crit_multiplier: 2.0,
+        }
+    }
+
+    #[test]
+    fn test_mitigation_curve() {
+        assert_eq!(mitigation(0), 0.0);
+        assert_eq!(mitigation(50), 0.5);
+        assert!(mitigation(100) > mitigation(50) && mitigation(100) < 0.7);
+        assert_eq!(mitigation(100_000), MAX_MITIGATION);
+        assert_eq!(mitigation(-50), -0.5);
+        assert!(mitigation(-100_000) >= -1.0);
+    }
+
+    #[test]
+    fn test_resistances_are_per_type() {
+        let resistances = Resistances {
+            fire: 50,
+            ice: -50,
+            ..Default::default()
+        };
+        let mut dice = Dice::seeded(1);
+
+        let fire = resolve(&strike(20, DamageType::Fire), 0.0, &resistances, &mut dice);
+        assert_eq!(fire.damage_dealt, 10);
+        assert_eq!(fire.mitigated_damage, 10);
+        let ice = resolve(&strike(20, DamageType::Ice), 0.0, &resistances, &mut dice);
+        assert_eq!(ice.damage_dealt, 30);
+        let poison = resolve(
+            &strike(20, DamageType::Poison),
+            0.0,
+            &resistances,
+            &mut dice,
+        );
+        assert_eq!(poison.damage_dealt, 20);
+
+        // Weak hits against heavy armour still do something
+        let armored = Resistances {
+            physical: 1000,
+            ..Default::default()
+        };
+        let weak = resolve(&strike(3, DamageType::Physical), 0.0, &armored, &mut dice);
+        assert_eq!(weak.damage_dealt, 1);
+    }
+
+    #[test]
+    fn test_negative_resistance_reports_extra_damage() {
+        let resistances = Resistances {
+            poison: -100,
+            ..Default::default()
+        };
+        let mut dice = Dice::seeded(1);
+
+        let bite = resolve(
+            &strike(20, DamageType::Poison),
+            0.0,
+            &resistances,
+            &mut dice,
+        );
+        assert!(bite.damage_dealt > bite.raw_damage);
+        assert_eq!(
+            bite.mitigated_damage,
+            bite.raw_damage as i64 - bite.damage_dealt as i64
+        );
+        assert!(bite.mitigated_damage < 0);
+    }
+
+    #[test]
+    fn test_rolls_are_seeded() {
+        let swing = Strike {
+            accuracy: 0.8,
+            crit_chance: 0.25,
+            ..strike(10, DamageType::Physical)
+        };
+        let roll_all = |seed| {
+            let mut dice = Dice::seeded(seed);
+            (0..2000)
+                .map(|_| resolve(&swing, 0.1, &Resistances::default(), &mut dice))
+                .collect::<Vec<_>>()
+        };
+
+        let rolls = roll_all(7);
+        assert_eq!(rolls, roll_all(7));
+        assert_ne!(rolls, roll_all(8));
+
+        let count = |outcome| rolls.iter().filter(|r| r.outcome == outcome).count();
+        // 70% hit chance after evasion, a quarter of the hits critical
+        assert!((1250..1550).contains(&(count(HitOutcome::Hit) + count(HitOutcome::Critical))));
+        assert!((250..450).contains(&count(HitOutcome::Critical)));
+        assert!(rolls
+            .iter()
+            .filter(|r| r.outcome == HitOutcome::Critical)
+            .all(|r| r.damage_dealt == 20));
+    }
+}
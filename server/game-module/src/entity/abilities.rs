@@ -0,0 +1,818 @@
+//! Ability definitions and how they are cast.
+//!
+//! Abilities cost mana or energy, may take a while to cast, cool down after use and hit
+//! one entity, an area or the caster. Entities refer to them by name, like behaviours.
+
+use super::components::*;
+use super::effects::{EffectKind, StatusEffect, StatusEffects};
+use super::{prefabs, AttackResult, CombatEvent, EntityManager};
+use crate::combat::{self, DamageType, HitOutcome, Strike};
+use crate::map::Vec2;
+
+/// Mana regained per second
+pub const MANA_REGEN: f64 = 2.0;
+
+/// Energy regained per second
+pub const ENERGY_REGEN: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Mana,
+    Energy,
+}
+
+/// Which entities an ability reaches
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AbilityShape {
+    Single, // The targeted entity
+    Cone {
+        half_angle: f64,
+    }, // Radians either side of the aim, out to the ability's range
+    Circle {
+        radius: f64,
+    }, // Around the aimed point, which must be within range
+    Line {
+        width: f64,
+    }, // Straight from the caster towards the aim, out to the range
+    Projectile {
+        speed: f64,
+        radius: f64,
+        pierce: u32,
+    }, // Flies towards the aim, out to the range
+    SelfCast, // Only the caster
+}
+
+/// A status effect an ability leaves on what it hits
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EffectSpec {
+    pub kind: EffectKind,
+    pub magnitude: f64,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ability {
+    pub name: &'static str,
+    pub shape: AbilityShape,
+    pub resource: ResourceKind,
+    pub cost: f64,
+    pub cast_time: f64, // Seconds before the ability goes off; 0 is instant
+    pub cooldown: f64,
+    pub range: f64,
+    pub damage: u32,
+    pub damage_type: DamageType,
+    pub healing: u32, // Restored to the caster
+    pub effects: &'static [EffectSpec],
+}
+
+pub const POWER_STRIKE: Ability = Ability {
+    name: "power_strike",
+    shape: AbilityShape::Single,
+    resource: ResourceKind::Energy,
+    cost: 20.0,
+    cast_time: 0.0,
+    cooldown: 4.0,
+    range: 1.5,
+    damage: 25,
+    damage_type: DamageType::Physical,
+    healing: 0,
+    effects: &[],
+};
+
+pub const SHIELD_BASH: Ability = Ability {
+    name: "shield_bash",
+    shape: AbilityShape::Single,
+    resource: ResourceKind::Energy,
+    cost: 25.0,
+    cast_time: 0.0,
+    cooldown: 10.0,
+    range: 1.5,
+    damage: 8,
+    damage_type: DamageType::Physical,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Stun,
+        magnitude: 0.0,
+        duration: 1.5,
+    }],
+};
+
+pub const FIREBALL: Ability = Ability {
+    name: "fireball",
+    shape: AbilityShape::Circle { radius: 1.5 },
+    resource: ResourceKind::Mana,
+    cost: 25.0,
+    cast_time: 1.0,
+    cooldown: 6.0,
+    range: 8.0,
+    damage: 20,
+    damage_type: DamageType::Fire,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Burn,
+        magnitude: 3.0,
+        duration: 3.0,
+    }],
+};
+
+pub const FROST_CONE: Ability = Ability {
+    name: "frost_cone",
+    shape: AbilityShape::Cone {
+        half_angle: std::f64::consts::FRAC_PI_4,
+    },
+    resource: ResourceKind::Mana,
+    cost: 20.0,
+    cast_time: 0.0,
+    cooldown: 8.0,
+    range: 4.0,
+    damage: 12,
+    damage_type: DamageType::Ice,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Slow,
+        magnitude: 0.5,
+        duration: 3.0,
+    }],
+};
+
+pub const PIERCING_SHOT: Ability = Ability {
+    name: "piercing_shot",
+    shape: AbilityShape::Line { width: 0.6 },
+    resource: ResourceKind::Energy,
+    cost: 25.0,
+    cast_time: 0.5,
+    cooldown: 5.0,
+    range: 8.0,
+    damage: 18,
+    damage_type: DamageType::Physical,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Bleed,
+        magnitude: 2.0,
+        duration: 4.0,
+    }],
+};
+
+pub const ARROW: Ability = Ability {
+    name: "arrow",
+    shape: AbilityShape::Projectile {
+        speed: 12.0,
+        radius: 0.1,
+        pierce: 0,
+    },
+    resource: ResourceKind::Energy,
+    cost: 10.0,
+    cast_time: 0.0,
+    cooldown: 1.0,
+    range: 10.0,
+    damage: 12,
+    damage_type: DamageType::Physical,
+    healing: 0,
+    effects: &[],
+};
+
+pub const FIRE_BOLT: Ability = Ability {
+    name: "fire_bolt",
+    shape: AbilityShape::Projectile {
+        speed: 8.0,
+        radius: 0.3,
+        pierce: 2,
+    },
+    resource: ResourceKind::Mana,
+    cost: 15.0,
+    cast_time: 0.5,
+    cooldown: 3.0,
+    range: 10.0,
+    damage: 15,
+    damage_type: DamageType::Fire,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Burn,
+        magnitude: 2.0,
+        duration: 3.0,
+    }],
+};
+
+pub const ENTANGLE: Ability = Ability {
+    name: "entangle",
+    shape: AbilityShape::Circle { radius: 2.0 },
+    resource: ResourceKind::Mana,
+    cost: 30.0,
+    cast_time: 0.5,
+    cooldown: 12.0,
+    range: 6.0,
+    damage: 0,
+    damage_type: DamageType::Poison,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Root,
+        magnitude: 0.0,
+        duration: 2.5,
+    }],
+};
+
+pub const HEAL: Ability = Ability {
+    name: "heal",
+    shape: AbilityShape::SelfCast,
+    resource: ResourceKind::Mana,
+    cost: 30.0,
+    cast_time: 1.5,
+    cooldown: 10.0,
+    range: 0.0,
+    damage: 0,
+    damage_type: DamageType::Physical,
+    healing: 30,
+    effects: &[EffectSpec {
+        kind: EffectKind::Regeneration,
+        magnitude: 3.0,
+        duration: 5.0,
+    }],
+};
+
+pub const SPRINT: Ability = Ability {
+    name: "sprint",
+    shape: AbilityShape::SelfCast,
+    resource: ResourceKind::Energy,
+    cost: 30.0,
+    cast_time: 0.0,
+    cooldown: 15.0,
+    range: 0.0,
+    damage: 0,
+    damage_type: DamageType::Physical,
+    healing: 0,
+    effects: &[EffectSpec {
+        kind: EffectKind::Haste,
+        magnitude: 0.5,
+        duration: 4.0,
+    }],
+};
+
+pub const ALL_ABILITIES: &[Ability] = &[
+    POWER_STRIKE,
+    SHIELD_BASH,
+    FIREBALL,
+    FROST_CONE,
+    PIERCING_SHOT,
+    ARROW,
+    FIRE_BOLT,
+    ENTANGLE,
+    HEAL,
+    SPRINT,
+];
+
+/// Abilities new players start with
+pub const STARTING_ABILITIES: &[&str] = &["power_strike", "arrow", "fireball", "heal", "sprint"];
+
+/// Find an ability by name
+pub fn find_ability(name: &str) -> Option<&'static Ability> {
+    ALL_ABILITIES.iter().find(|ability| ability.name == name)
+}
+
+/// Whether a body of `radius` at `position` is caught by an area shape cast from `origin`
+/// towards `aim` with the given range
+pub fn in_area(
+    shape: AbilityShape,
+    range: f64,
+    origin: &Vec2,
+    aim: &Vec2,
+    position: &Vec2,
+    radius: f64,
+) -> bool {
+    let (dx, dy) = (position.x - origin.x, position.y - origin.y);
+    let (aim_x, aim_y) = (aim.x - origin.x, aim.y - origin.y);
+    let aim_length = (aim_x * aim_x + aim_y * aim_y).sqrt();
+    match shape {
+        AbilityShape::Circle { radius: area } => {
+            let (cx, cy) = (position.x - aim.x, position.y - aim.y);
+            (cx * cx + cy * cy).sqrt() <= area + radius
+        }
+        AbilityShape::Cone { half_angle } => {
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance > range + radius || aim_length == 0.0 {
+                return false;
+            }
+            // Bodies right next to the caster are caught whichever way it faces
+            if distance <= radius {
+                return true;
+            }
+            let cos = (dx * aim_x + dy * aim_y) / (distance * aim_length);
+            cos.clamp(-1.0, 1.0).acos() <= half_angle
+        }
+        AbilityShape::Line { width } => {
+            if aim_length == 0.0 {
+                return false;
+            }
+            let along = (dx * aim_x + dy * aim_y) / aim_length;
+            let across = (dx * aim_y - dy * aim_x).abs() / aim_length;
+            along >= -radius && along <= range + radius && across <= width / 2.0 + radius
+        }
+        AbilityShape::Single | AbilityShape::Projectile { .. } | AbilityShape::SelfCast => false,
+    }
+}
+
+/// What using an ability led to
+#[derive(Clone, Debug, PartialEq)]
+pub enum AbilityOutcome {
+    Casting { completes_at: f64 },
+    Resolved { hit_entity_ids: Vec<u64> }, // Entities damaged or affected, caster included
+    Launched { projectile_id: u64 },
+}
+
+fn distance(a: &Vec2, b: &Vec2) -> f64 {
+    let dx = a.x - b.x;
+    let dy = a.y - b.y;
+    (dx * dx + dy * dy).sqrt()
+}
+
+impl EntityManager {
+    /// Use one of the caster's abilities. Checks range and line of sight, pays the cost and
+    /// starts the cooldown; abilities with a cast time go off once the cast completes.
+    pub fn use_ability(
+        &mut self,
+        caster_id: u64,
+        name: &str,
+        target: AbilityTarget,
+        current_time: f64,
+    ) -> Result<AbilityOutcome, String> {
+        let ability = find_ability(name).ok_or("Unknown ability")?;
+        let abilities = self
+            .get::<Abilities>(caster_id)
+            .ok_or("Caster has no abilities")?;
+        if !abilities.knows(ability.name) {
+            return Err("Ability not known".to_string());
+        }
+        if !abilities.is_ready(ability.name, current_time) {
+            return Err("Ability is on cooldown".to_string());
+        }
+        if !self.is_alive(caster_id) {
+            return Err("Caster is dead".to_string());
+        }
+        if self
+            .get::<StatusEffects>(caster_id)
+            .is_some_and(|effects| !effects.can_act())
+        {
+            return Err("Caster is stunned".to_string());
+        }
+        if self.has::<Casting>(caster_id) {
+            return Err("Already casting".to_string());
+        }
+        self.aim(caster_id, ability, &target)?;
+
+        let resources = self
+            .get_mut::<Resources>(caster_id)
+            .ok_or("Caster has no resources")?;
+        let pool = match ability.resource {
+            ResourceKind::Mana => &mut resources.mana,
+            ResourceKind::Energy => &mut resources.energy,
+        };
+        if *pool < ability.cost {
+            return Err(match ability.resource {
+                ResourceKind::Mana => "Not enough mana".to_string(),
+                ResourceKind::Energy => "Not enough energy".to_string(),
+            });
+        }
+        *pool -= ability.cost;
+        if let Some(abilities) = self.get_mut::<Abilities>(caster_id) {
+            abilities
+                .ready_at
+                .insert(ability.name, current_time + ability.cooldown);
+        }
+
+        if ability.cast_time > 0.0 {
+            let completes_at = current_time + ability.cast_time;
+            self.insert(
+                caster_id,
+                Casting {
+                    ability: ability.name,
+                    target,
+                    completes_at,
+                },
+            );
+            return Ok(AbilityOutcome::Casting { completes_at });
+        }
+        self.resolve_ability(caster_id, ability, &target, current_time)
+    }
+
+    /// Check that the target is valid, in range and in sight, and return the point aimed at
+    fn aim(
+        &self,
+        caster_id: u64,
+        ability: &Ability,
+        target: &AbilityTarget,
+    ) -> Result<Vec2, String> {
+        let origin = self.position(caster_id).ok_or("Caster not found")?.clone();
+        let point = match (ability.shape, target) {
+            (AbilityShape::SelfCast, _) => return Ok(origin),
+            (_, AbilityTarget::Caster) => return Err("Ability needs a target".to_string()),
+            (AbilityShape::Single, AbilityTarget::Point(_)) => {
+                return Err("Ability needs a target entity".to_string())
+            }
+            (_, AbilityTarget::Entity(target_id)) => {
+                if *target_id == caster_id {
+                    return Err("Cannot target yourself".to_string());
+                }
+                if !self.is_alive(*target_id) {
+                    return Err("Target is already dead".to_string());
+                }
+                // Like area abilities, targeted attacks only ever reach hostile entities
+                if ability.shape == AbilityShape::Single
+                    && ability.damage > 0
+                    && !self.is_hostile(caster_id, *target_id)
+                {
+                    return Err("Target is not hostile".to_string());
+                }
+                self.position(*target_id).ok_or("Target not found")?.clone()
+            }
+            (_, AbilityTarget::Point(point)) => point.clone(),
+        };
+
+        // Cones, lines and projectiles only need a direction; everything else has to be reached
+        match ability.shape {
+            AbilityShape::Cone { .. }
+            | AbilityShape::Line { .. }
+            | AbilityShape::Projectile { .. } => {
+                if distance(&origin, &point) == 0.0 {
+                    return Err("Cannot aim at yourself".to_string());
+                }
+            }
+            _ => {
+                if distance(&origin, &point) > ability.range {
+                    return Err("Target is out of range".to_string());
+                }
+                if !self.clear_line(&origin, &point) {
+                    return Err("Target is not in line of sight".to_string());
+                }
+            }
+        }
+        Ok(point)
+    }
+
+    /// Apply an ability's damage, healing and effects, or launch its projectile
+    pub(super) fn resolve_ability(
+        &mut self,
+        caster_id: u64,
+        ability: &'static Ability,
+        target: &AbilityTarget,
+        current_time: f64,
+    ) -> Result<AbilityOutcome, String> {
+        let aim = self.aim(caster_id, ability, target)?;
+        let origin = self.position(caster_id).ok_or("Caster not found")?.clone();
+
+        let targets: Vec<u64> = match (ability.shape, target) {
+            (AbilityShape::SelfCast, _) => {
+                if let Some(health) = self.get_mut::<Health>(caster_id) {
+                    health.heal(ability.healing);
+                }
+                self.apply_ability_effects(caster_id, caster_id, ability, current_time);
+                return Ok(AbilityOutcome::Resolved {
+                    hit_entity_ids: vec![caster_id],
+                });
+            }
+            (AbilityShape::Projectile { .. }, _) => {
+                let projectile_id = self.launch(caster_id, ability, &origin, &aim, current_time);
+                self.insert(caster_id, EntityState::Attacking);
+                return Ok(AbilityOutcome::Launched { projectile_id });
+            }
+            (AbilityShape::Single, AbilityTarget::Entity(target_id)) => vec![*target_id],
+            _ => self.entities_in_area(caster_id, ability, &origin, &aim),
+        };
+
+        let mut hit_entity_ids = Vec::new();
+        for target_id in targets {
+            if self.hit_with_ability(caster_id, target_id, ability, current_time) {
+                hit_entity_ids.push(target_id);
+            }
+        }
+        self.insert(caster_id, EntityState::Attacking);
+        Ok(AbilityOutcome::Resolved { hit_entity_ids })
+    }
+
+    /// Spawn the projectile of an ability, flying from `origin` towards `aim`
+    fn launch(
+        &mut self,
+        caster_id: u64,
+        ability: &'static Ability,
+        origin: &Vec2,
+        aim: &Vec2,
+        current_time: f64,
+    ) -> u64 {
+        let AbilityShape::Projectile {
+            speed,
+            radius,
+            pierce,
+        } = ability.shape
+        else {
+            unreachable!("only projectile abilities are launched");
+        };
+        let length = distance(origin, aim);
+        let velocity = Vec2 {
+            x: (aim.x - origin.x) / length * speed,
+            y: (aim.y - origin.y) / length * speed,
+        };
+        let projectile = Projectile {
+            ability: ability.name,
+            faction: self
+                .get::<Faction>(caster_id)
+                .copied()
+                .unwrap_or(Faction::Neutral),
+            velocity,
+            pierce,
+            hit_entity_ids: Vec::new(),
+        };
+        let expires_at = current_time + ability.range / speed;
+        self.spawn(prefabs::projectile(
+            origin.clone(),
+            caster_id,
+            projectile,
+            radius,
+            expires_at,
+        ))
+    }
+
+    /// Roll an ability's damage against one entity and leave its effects if it landed.
+    /// Returns whether the target was reached.
+    pub(super) fn hit_with_ability(
+        &mut self,
+        attacker_id: u64,
+        target_id: u64,
+        ability: &Ability,
+        current_time: f64,
+    ) -> bool {
+        if !self.has::<Health>(target_id) {
+            return false;
+        }
+        if ability.damage == 0 {
+            self.apply_ability_effects(attacker_id, target_id, ability, current_time);
+            return true;
+        }
+
+        let combat = self.effective_combat(attacker_id).unwrap_or_default();
+        let strike = Strike {
+            damage: ability.damage,
+            damage_type: ability.damage_type,
+            ..combat.strike()
+        };
+        let defense = self.effective_defense(target_id);
+        let breakdown = combat::resolve(
+            &strike,
+            defense.evasion,
+            &defense.resistances,
+            &mut self.dice,
+        );
+        let landed = breakdown.outcome != HitOutcome::Miss;
+        let target_died = self
+            .get_mut::<Health>(target_id)
+            .is_some_and(|health| health.take_damage(breakdown.damage_dealt));
+        if target_died {
+            self.insert(target_id, EntityState::Dead);
+        } else if landed {
+            self.apply_ability_effects(attacker_id, target_id, ability, current_time);
+        }
+        let Some(target_position) = self.position(target_id).cloned() else {
+            return landed;
+        };
+        self.record_combat_event(CombatEvent {
+            attacker_id,
+            target_id,
+            result: AttackResult {
+                breakdown,
+                target_died,
+                target_position,
+            },
+        });
+        landed
+    }
+
+    /// Whether two entities are of hostile factions; entities without one are neutral
+    fn is_hostile(&self, id: u64, other_id: u64) -> bool {
+        let faction = |id| self.get::<Faction>(id).copied().unwrap_or(Faction::Neutral);
+        faction(id).is_hostile_to(faction(other_id))
+    }
+
+    /// Living, hostile entities caught in an area ability, that the blast can reach
+    fn entities_in_area(
+        &self,
+        caster_id: u64,
+        ability: &Ability,
+        origin: &Vec2,
+        aim: &Vec2,
+    ) -> Vec<u64> {
+        let (center, extent) = match ability.shape {
+            AbilityShape::Circle { radius } => (aim.clone(), radius),
+            _ => (origin.clone(), ability.range),
+        };
+        self.get_entities_in_range(&center, extent + MAX_COLLIDER_RADIUS)
+            .into_iter()
+            .filter(|&id| id != caster_id && self.is_alive(id) && self.is_hostile(caster_id, id))
+            .filter(|&id| {
+                let Some(position) = self.position(id) else {
+                    return false;
+                };
+                let radius = self
+                    .get::<Collider>(id)
+                    .map_or(0.0, |collider| collider.radius.min(MAX_COLLIDER_RADIUS));
+                in_area(ability.shape, ability.range, origin, aim, position, radius)
+                    && self.clear_line(&center, position)
+            })
+            .collect()
+    }
+
+    fn apply_ability_effects(
+        &mut self,
+        caster_id: u64,
+        target_id: u64,
+        ability: &Ability,
+        current_time: f64,
+    ) {
+        for spec in ability.effects {
+            let effect = StatusEffect::new(
+                spec.kind,
+                Some(caster_id),
+                spec.magnitude,
+                spec.duration,
+                current_time,
+            );
+            let _ = self.apply_effect(target_id, effect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::prefabs;
+    use crate::pathfinding::tests::map_from;
+
+    fn at(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    fn caster(manager: &mut EntityManager, position: Vec2) -> u64 {
+        let id = manager.spawn(prefabs::player(position));
+        manager.insert(
+            id,
+            Abilities::new(&["power_strike", "fireball", "heal", "frost_cone"]),
+        );
+        let combat = manager.get_mut::<Combat>(id).unwrap();
+        combat.accuracy = 1.0;
+        combat.crit_chance = 0.0;
+        id
+    }
+
+    #[test]
+    fn test_area_shapes() {
+        let origin = at(0.0, 0.0);
+        let east = at(1.0, 0.0);
+        let cone = AbilityShape::Cone {
+            half_angle: std::f64::consts::FRAC_PI_4,
+        };
+        assert!(in_area(cone, 4.0, &origin, &east, &at(3.0, 1.0), 0.0));
+        assert!(!in_area(cone, 4.0, &origin, &east, &at(1.0, 2.0), 0.0));
+        assert!(!in_area(cone, 4.0, &origin, &east, &at(-2.0, 0.0), 0.0));
+        assert!(!in_area(cone, 4.0, &origin, &east, &at(5.0, 0.0), 0.4));
+
+        let line = AbilityShape::Line { width: 0.6 };
+        assert!(in_area(line, 8.0, &origin, &east, &at(7.0, 0.5), 0.4));
+        assert!(!in_area(line, 8.0, &origin, &east, &at(7.0, 1.0), 0.4));
+        assert!(!in_area(line, 8.0, &origin, &east, &at(-1.0, 0.0), 0.4));
+
+        let circle = AbilityShape::Circle { radius: 1.5 };
+        assert!(in_area(
+            circle,
+            8.0,
+            &origin,
+            &at(5.0, 5.0),
+            &at(6.0, 6.0),
+            0.0
+        ));
+        assert!(!in_area(
+            circle,
+            8.0,
+            &origin,
+            &at(5.0, 5.0),
+            &at(7.0, 7.0),
+            0.0
+        ));
+    }
+
+    #[test]
+    fn test_cooldowns_and_costs() {
+        let mut manager = EntityManager::new();
+        let player = caster(&mut manager, at(0.0, 0.0));
+        let monster = manager.spawn(prefabs::monster(at(1.0, 0.0)));
+
+        let outcome = manager
+            .use_ability(player, "power_strike", AbilityTarget::Entity(monster), 10.0)
+            .unwrap();
+        assert_eq!(
+            outcome,
+            AbilityOutcome::Resolved {
+                hit_entity_ids: vec![monster]
+            }
+        );
+        assert_eq!(manager.get::<Health>(monster).unwrap().current, 25);
+        assert_eq!(manager.get::<Resources>(player).unwrap().energy, 80.0);
+        assert_eq!(
+            manager.use_ability(player, "power_strike", AbilityTarget::Entity(monster), 12.0),
+            Err("Ability is on cooldown".to_string())
+        );
+        assert_eq!(
+            manager.use_ability(player, "sprint", AbilityTarget::Caster, 12.0),
+            Err("Ability not known".to_string())
+        );
+
+        manager.get_mut::<Resources>(player).unwrap().energy = 10.0;
+        assert_eq!(
+            manager.use_ability(player, "power_strike", AbilityTarget::Entity(monster), 14.0),
+            Err("Not enough energy".to_string())
+        );
+        manager.update_entities(1.0, 15.0);
+        assert_eq!(manager.get::<Resources>(player).unwrap().energy, 20.0);
+    }
+
+    #[test]
+    fn test_targeted_attacks_only_hit_hostile_entities() {
+        let mut manager = EntityManager::new();
+        let player = caster(&mut manager, at(0.0, 0.0));
+        let other_player = manager.spawn(prefabs::player(at(1.0, 0.0)));
+
+        assert_eq!(
+            manager.use_ability(
+                player,
+                "power_strike",
+                AbilityTarget::Entity(other_player),
+                10.0
+            ),
+            Err("Target is not hostile".to_string())
+        );
+        assert_eq!(manager.get::<Health>(other_player).unwrap().current, 100);
+        assert_eq!(manager.get::<Resources>(player).unwrap().energy, 100.0);
+    }
+
+    #[test]
+    fn test_range_and_line_of_sight() {
+        let mut manager = EntityManager::new();
+        let player = caster(&mut manager, at(1.0, 1.0));
+        let monster = manager.spawn(prefabs::monster(at(5.0, 1.0)));
+        manager.remove_component::<Ai>(monster);
+        let ally = manager.spawn(prefabs::player(at(4.0, 2.0)));
+        manager.set_navigation(
+            map_from(&[
+                "#######", //
+                "#..#..#", //
+                "#.....#", //
+                "#######",
+            ]),
+            Default::default(),
+            Default::default(),
+        );
+
+        assert_eq!(
+            manager.use_ability(player, "power_strike", AbilityTarget::Entity(monster), 10.0),
+            Err("Target is out of range".to_string())
+        );
+        assert_eq!(
+            manager.use_ability(player, "fireball", AbilityTarget::Point(at(5.0, 1.0)), 10.0),
+            Err("Target is not in line of sight".to_string())
+        );
+        // Failed attempts cost nothing
+        assert_eq!(manager.get::<Resources>(player).unwrap().mana, 100.0);
+
+        // Around the wall the fireball goes off after its cast time
+        let outcome = manager
+            .use_ability(player, "fireball", AbilityTarget::Point(at(5.0, 2.0)), 10.0)
+            .unwrap();
+        assert_eq!(outcome, AbilityOutcome::Casting { completes_at: 11.0 });
+        manager.update_entities(0.0, 10.5);
+        assert_eq!(manager.get::<Health>(monster).unwrap().current, 50);
+        manager.update_entities(0.0, 11.0);
+        assert_eq!(manager.get::<Health>(monster).unwrap().current, 30);
+        assert_eq!(manager.get::<Health>(ally).unwrap().current, 100);
+        assert!(manager
+            .get::<StatusEffects>(monster)
+            .is_some_and(|effects| effects.has(EffectKind::Burn)));
+    }
+
+    #[test]
+    fn test_moving_interrupts_casting() {
+        let mut manager = EntityManager::new();
+        let player = caster(&mut manager, at(0.0, 0.0));
+        manager.get_mut::<Health>(player).unwrap().current = 50;
+
+        manager
+            .use_ability(player, "heal", AbilityTarget::Caster, 10.0)
+            .unwrap();
+        manager.move_entity(player, at(1.0, 0.0), None).unwrap();
+        manager.update_entities(0.0, 12.0);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 50);
+
+        manager
+            .use_ability(player, "heal", AbilityTarget::Caster, 20.0)
+            .unwrap();
+        manager.update_entities(0.0, 21.5);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 80);
+        assert!(manager
+            .get::<StatusEffects>(player)
+            .is_some_and(|effects| effects.has(EffectKind::Regeneration)));
+    }
+}
@@ -0,0 +1,332 @@
+//! Behaviour definitions for AI-driven entities.
+//!
+//! A behaviour is a state machine over `AiMode` described by a few parameters. Entities
+//! refer to their behaviour by name, so new monster kinds only need a new definition here.
+
+use super::components::AiMode;
+use crate::map::Map;
+use crate::pathfinding::Tile;
+use std::collections::{HashSet, VecDeque};
+
+/// Targets are followed this many times further than they are noticed
+pub const PURSUIT_FACTOR: f64 = 2.0;
+
+/// Distance from home at which a returning entity counts as back
+pub const HOME_RADIUS: f64 = 0.5;
+
+/// Rooms larger than this are patrolled only around home
+const MAX_ROOM_TILES: usize = 256;
+
+const TILE_FLOOR: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Behavior {
+    pub name: &'static str,
+    pub detection_range: f64, // How far hostile entities are noticed, in tiles
+    pub patrols: bool,        // Walk around the room while there is nothing to fight
+    pub flee_health: f64,     // Run away below this fraction of max health; 0 never flees
+    pub leash_range: f64,     // Give up and walk home beyond this distance from home
+    pub help_range: f64,      // Allies this close join in when a fight starts; 0 fights alone
+}
+
+/// What the entity knows when deciding what to do next
+#[derive(Debug, Clone)]
+pub struct Situation {
+    pub target_distance: Option<f64>,
+    pub attack_range: f64,
+    pub health_fraction: f64,
+    pub distance_from_home: f64,
+    pub can_patrol: bool,
+}
+
+impl Behavior {
+    /// The mode to switch to from `current`
+    pub fn next_mode(&self, current: AiMode, situation: &Situation) -> AiMode {
+        // Leashed entities ignore everything until they are home
+        if current == AiMode::Return && situation.distance_from_home > HOME_RADIUS {
+            return AiMode::Return;
+        }
+
+        match situation.target_distance {
+            Some(_) if situation.health_fraction < self.flee_health => AiMode::Flee,
+            Some(_) if situation.distance_from_home > self.leash_range => AiMode::Return,
+            Some(distance) if distance <= situation.attack_range => AiMode::Attack,
+            Some(_) => AiMode::Chase,
+            None if self.patrols && situation.can_patrol => AiMode::Patrol,
+            None if situation.distance_from_home > HOME_RADIUS && self.leash_range.is_finite() => {
+                AiMode::Return
+            }
+            None => AiMode::Idle,
+        }
+    }
+}
+
+/// Charges whatever it sees and gives up when dragged too far from home
+pub const AGGRESSIVE: Behavior = Behavior {
+    name: "aggressive",
+    detection_range: 8.0,
+    patrols: false,
+    flee_health: 0.0,
+    leash_range: 16.0,
+    help_range: 0.0,
+};
+
+/// Walks around its room and alerts the other guards
+pub const GUARD: Behavior = Behavior {
+    name: "guard",
+    detection_range: 6.0,
+    patrols: true,
+    flee_health: 0.0,
+    leash_range: 10.0,
+    help_range: 8.0,
+};
+
+/// Runs away once it is badly hurt
+pub const COWARD: Behavior = Behavior {
+    name: "coward",
+    detection_range: 8.0,
+    patrols: false,
+    flee_health: 0.3,
+    leash_range: 16.0,
+    help_range: 0.0,
+};
+
+/// Hunts together with the rest of its pack
+pub const PACK: Behavior = Behavior {
+    name: "pack",
+    detection_range: 8.0,
+    patrols: true,
+    flee_health: 0.15,
+    leash_range: 20.0,
+    help_range: 10.0,
+};
+
+/// Only reacts to whatever steps right onto it (traps, mimics, ...)
+pub const AMBUSH: Behavior = Behavior {
+    name: "ambush",
+    detection_range: 0.5,
+    patrols: false,
+    flee_health: 0.0,
+    leash_range: f64::INFINITY,
+    help_range: 0.0,
+};
+
+pub const ALL_BEHAVIORS: &[Behavior] = &[AGGRESSIVE, GUARD, COWARD, PACK, AMBUSH];
+
+/// Find a behaviour by name
+pub fn find_behavior(name: &str) -> Option<&'static Behavior> {
+    ALL_BEHAVIORS.iter().find(|behavior| behavior.name == name)
+}
+
+/// Patrol waypoints around `home`: the corners of the room it stands in, clockwise from the
+/// top left. A room is the floor reachable from `home` without going through a door.
+pub fn room_waypoints(map: &Map, home: Tile) -> Vec<Tile> {
+    if map.get_tile(home.0, home.1) != Some(TILE_FLOOR) {
+        return Vec::new();
+    }
+
+    let mut room = HashSet::from([home]);
+    let mut queue = VecDeque::from([home]);
+    while let Some((x, y)) = queue.pop_front() {
+        if room.len() >= MAX_ROOM_TILES {
+            break;
+        }
+        let neighbors = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for next in neighbors {
+            if map.get_tile(next.0, next.1) == Some(TILE_FLOOR) && room.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let key = |score: fn(i64, i64) -> i64| {
+        *room
+            .iter()
+            .max_by_key(|&&(x, y)| (score(x as i64, y as i64), x, y))
+            .expect("the room contains home")
+    };
+    let corners = [
+        key(|x, y| -x - y), // Top left
+        key(|x, y| x - y),  // Top right
+        key(|x, y| x + y),  // Bottom right
+        key(|x, y| y - x),  // Bottom left
+    ];
+
+    let mut waypoints: Vec<Tile> = Vec::new();
+    for corner in corners {
+        if !waypoints.contains(&corner) {
+            waypoints.push(corner);
+        }
+    }
+    if waypoints.len() < 2 {
+        waypoints.clear();
+    }
+    waypoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{prefabs, Ai, EntityManager, Health};
+    use crate::map::Vec2;
+    use crate::pathfinding::tests::map_from;
+
+    fn at(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    fn room() -> Map {
+        map_from(&[
+            "##############", //
+            "#.....D......#", //
+            "#.....#......#", //
+            "#.....#......#", //
+            "##############",
+        ])
+    }
+
+    fn mode(manager: &EntityManager, id: u64) -> AiMode {
+        manager.get::<Ai>(id).unwrap().mode
+    }
+
+    #[test]
+    fn test_mode_transitions() {
+        let situation = Situation {
+            target_distance: Some(5.0),
+            attack_range: 1.5,
+            health_fraction: 1.0,
+            distance_from_home: 0.0,
+            can_patrol: true,
+        };
+        assert_eq!(
+            AGGRESSIVE.next_mode(AiMode::Idle, &situation),
+            AiMode::Chase
+        );
+
+        let close = Situation {
+            target_distance: Some(1.0),
+            ..situation.clone()
+        };
+        assert_eq!(AGGRESSIVE.next_mode(AiMode::Chase, &close), AiMode::Attack);
+
+        let hurt = Situation {
+            health_fraction: 0.2,
+            ..situation.clone()
+        };
+        assert_eq!(COWARD.next_mode(AiMode::Chase, &hurt), AiMode::Flee);
+        assert_eq!(AGGRESSIVE.next_mode(AiMode::Chase, &hurt), AiMode::Chase);
+
+        let far_from_home = Situation {
+            distance_from_home: 20.0,
+            ..situation.clone()
+        };
+        assert_eq!(
+            AGGRESSIVE.next_mode(AiMode::Chase, &far_from_home),
+            AiMode::Return
+        );
+        // Still walking home even though the target is right there
+        let on_the_way = Situation {
+            distance_from_home: 3.0,
+            ..close.clone()
+        };
+        assert_eq!(
+            AGGRESSIVE.next_mode(AiMode::Return, &on_the_way),
+            AiMode::Return
+        );
+
+        let nothing = Situation {
+            target_distance: None,
+            ..situation
+        };
+        assert_eq!(GUARD.next_mode(AiMode::Chase, &nothing), AiMode::Patrol);
+        assert_eq!(AGGRESSIVE.next_mode(AiMode::Chase, &nothing), AiMode::Idle);
+    }
+
+    #[test]
+    fn test_room_waypoints_stop_at_doors() {
+        let map = room();
+        assert_eq!(
+            room_waypoints(&map, (2, 2)),
+            vec![(1, 1), (5, 1), (5, 3), (1, 3)]
+        );
+        assert_eq!(
+            room_waypoints(&map, (10, 2)),
+            vec![(7, 1), (12, 1), (12, 3), (7, 3)]
+        );
+        assert!(room_waypoints(&map, (0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_guards_patrol_their_room() {
+        let mut manager = EntityManager::new();
+        let guard = manager.spawn(prefabs::archetype(&prefabs::SKELETON_GUARD, at(2.0, 2.0)));
+        manager.set_navigation(room(), Default::default(), Default::default());
+
+        let mut visited = HashSet::new();
+        for step in 1..=40 {
+            manager.update_entities(0.5, step as f64);
+            let position = manager.position(guard).unwrap();
+            visited.insert((position.x.round() as usize, position.y.round() as usize));
+        }
+        assert_eq!(mode(&manager, guard), AiMode::Patrol);
+        for corner in room_waypoints(&room(), (2, 2)) {
+            assert!(visited.contains(&corner), "Never went to {:?}", corner);
+        }
+        assert!(visited.iter().all(|&(x, _)| x < 6), "Left the room");
+    }
+
+    #[test]
+    fn test_guards_call_for_help() {
+        let mut manager = EntityManager::new();
+        let guard = manager.spawn(prefabs::archetype(&prefabs::SKELETON_GUARD, at(2.0, 2.0)));
+        // Behind the wall, this one can't see the player
+        let other_guard = manager.spawn(prefabs::archetype(&prefabs::SKELETON_GUARD, at(9.0, 3.0)));
+        let player = manager.spawn(prefabs::player(at(4.0, 3.0)));
+        manager.set_navigation(room(), Default::default(), Default::default());
+        assert!(!manager.can_see(other_guard, player));
+
+        manager.update_entities(0.05, 1.0);
+        for id in [guard, other_guard] {
+            assert_eq!(mode(&manager, id), AiMode::Chase);
+            assert_eq!(
+                manager.get::<Ai>(id).unwrap().target_entity_id,
+                Some(player)
+            );
+        }
+    }
+
+    #[test]
+    fn test_hurt_cowards_flee() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(3.0, 2.0)));
+        let rat = manager.spawn(prefabs::archetype(&prefabs::RAT, at(4.0, 2.0)));
+        manager.set_navigation(room(), Default::default(), Default::default());
+
+        manager.update_entities(0.5, 1.0);
+        assert_eq!(mode(&manager, rat), AiMode::Attack);
+
+        manager.get_mut::<Health>(rat).unwrap().current = 2;
+        manager.update_entities(0.5, 2.0);
+        assert_eq!(mode(&manager, rat), AiMode::Flee);
+        assert!(manager.distance(rat, player).unwrap() > 1.0);
+    }
+
+    #[test]
+    fn test_leashed_monsters_walk_home() {
+        let mut manager = EntityManager::new();
+        let monster = manager.spawn(prefabs::monster(at(10.0, 2.0)));
+        let player = manager.spawn(prefabs::player(at(8.0, 2.0)));
+        manager.set_navigation(room(), Default::default(), Default::default());
+        manager.get_mut::<Ai>(monster).unwrap().home = at(30.0, 2.0);
+
+        manager.update_entities(0.5, 1.0);
+        assert_eq!(mode(&manager, monster), AiMode::Return);
+        assert_eq!(manager.get::<Ai>(monster).unwrap().target_entity_id, None);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 100);
+    }
+}
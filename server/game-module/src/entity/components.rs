@@ -0,0 +1,407 @@
+use super::items::{EquipSlot, INVENTORY_SLOTS};
+use super::quests::QuestProgress;
+use crate::combat::{DamageType, Resistances, Strike};
+use crate::map::Vec2;
+use std::collections::BTreeMap;
+
+/// Where an entity is and which way it faces.
+/// Move entities with `EntityManager::set_position` so the spatial index follows them.
+#[derive(Clone, Debug)]
+pub struct Transform {
+    pub position: Vec2,
+    pub direction: f64, // Radians (0 = east, π/2 = north)
+}
+
+impl Transform {
+    pub fn at(position: Vec2) -> Self {
+        Self {
+            position,
+            direction: 0.0,
+        }
+    }
+
+    pub fn distance_to(&self, position: &Vec2) -> f64 {
+        let dx = self.position.x - position.x;
+        let dy = self.position.y - position.y;
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+/// Hit points; entities without health cannot be damaged
+#[derive(Clone, Debug, PartialEq)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.current > 0
+    }
+
+    /// Take damage and return true if this killed the entity
+    pub fn take_damage(&mut self, damage: u32) -> bool {
+        let was_alive = self.is_alive();
+        self.current = self.current.saturating_sub(damage);
+        was_alive && !self.is_alive()
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+/// Ability to attack
+#[derive(Clone, Debug, PartialEq)]
+pub struct Combat {
+    pub attack_damage: u32,
+    pub damage_type: DamageType,
+    pub accuracy: f64,        // Chance to hit a target without evasion
+    pub crit_chance: f64,     // Chance that a hit is critical
+    pub crit_multiplier: f64, // Damage multiplier of critical hits
+    pub attack_range: f64,
+    pub attack_cooldown: f64,
+    pub last_attack_time: f64,
+}
+
+impl Default for Combat {
+    fn default() -> Self {
+        Self {
+            attack_damage: 10,
+            damage_type: DamageType::Physical,
+            accuracy: 0.95,
+            crit_chance: 0.05,
+            crit_multiplier: 1.5,
+            attack_range: 1.0,
+            attack_cooldown: 1.0,
+            last_attack_time: 0.0,
+        }
+    }
+}
+
+impl Combat {
+    pub fn can_attack(&self, current_time: f64) -> bool {
+        current_time - self.last_attack_time >= self.attack_cooldown
+    }
+
+    /// The roll parameters of one attack
+    pub fn strike(&self) -> Strike {
+        Strike {
+            damage: self.attack_damage,
+            damage_type: self.damage_type,
+            accuracy: self.accuracy,
+            crit_chance: self.crit_chance,
+            crit_multiplier: self.crit_multiplier,
+        }
+    }
+}
+
+/// Ability to avoid and soak up damage; entities without it take every hit in full
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Defense {
+    pub evasion: f64, // Subtracted from the attacker's chance to hit
+    pub resistances: Resistances,
+}
+
+/// Largest collider radius entity blocking looks for around a moving entity
+pub const MAX_COLLIDER_RADIUS: f64 = 1.0;
+
+/// Round body that walls stop. Bodies that block entities also stop each other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collider {
+    pub radius: f64, // In tiles, at most `MAX_COLLIDER_RADIUS`
+    pub blocks_entities: bool,
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Self {
+            radius: 0.4,
+            blocks_entities: true,
+        }
+    }
+}
+
+/// Movement speed in tiles per second
+#[derive(Clone, Debug, PartialEq)]
+pub struct Movement {
+    pub speed: f64,
+}
+
+/// How well an entity notices its surroundings
+#[derive(Clone, Debug, PartialEq)]
+pub struct Senses {
+    pub perception: f64, // Scales the radius at which secret passages are noticed
+}
+
+impl Default for Senses {
+    fn default() -> Self {
+        Self { perception: 1.0 }
+    }
+}
+
+/// Mana and energy that abilities are paid with
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resources {
+    pub mana: f64,
+    pub max_mana: f64,
+    pub energy: f64,
+    pub max_energy: f64,
+}
+
+impl Resources {
+    pub fn new(max_mana: f64, max_energy: f64) -> Self {
+        Self {
+            mana: max_mana,
+            max_mana,
+            energy: max_energy,
+            max_energy,
+        }
+    }
+}
+
+/// Abilities in `abilities::ALL_ABILITIES` an entity can use, and when each is ready again
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Abilities {
+    pub known: Vec<&'static str>,              // Ability names
+    pub ready_at: BTreeMap<&'static str, f64>, // Abilities still cooling down
+}
+
+impl Abilities {
+    pub fn new(known: &[&'static str]) -> Self {
+        Self {
+            known: known.to_vec(),
+            ready_at: BTreeMap::new(),
+        }
+    }
+
+    pub fn knows(&self, ability: &str) -> bool {
+        self.known.contains(&ability)
+    }
+
+    pub fn is_ready(&self, ability: &str, current_time: f64) -> bool {
+        self.ready_at
+            .get(ability)
+            .is_none_or(|&ready_at| ready_at <= current_time)
+    }
+}
+
+/// Where an ability is aimed
+#[derive(Clone, Debug)]
+pub enum AbilityTarget {
+    Caster,
+    Entity(u64),
+    Point(Vec2),
+}
+
+/// An ability being cast, resolved once `completes_at` has passed. Moving or being
+/// stunned interrupts it.
+#[derive(Clone, Debug)]
+pub struct Casting {
+    pub ability: &'static str,
+    pub target: AbilityTarget,
+    pub completes_at: f64,
+}
+
+/// A pile of identical items from `items::ALL_ITEMS`, carried or lying on the ground
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemStack {
+    pub item: &'static str, // Item ID
+    pub count: u32,
+}
+
+/// Item slots of an entity, each holding one stack of at most the item's stack size
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: vec![None; INVENTORY_SLOTS],
+        }
+    }
+}
+
+/// Items worn by an entity; their modifiers count towards its effective stats
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Equipment {
+    pub slots: BTreeMap<EquipSlot, &'static str>, // Item IDs
+}
+
+/// What an entity is currently doing
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntityState {
+    Idle,
+    Moving,
+    Attacking,
+    Dead,
+    Interacting,
+}
+
+/// Which side an entity fights for; AI only hunts entities of other, non-neutral factions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Faction {
+    Players,
+    Monsters,
+    Neutral,
+}
+
+impl Faction {
+    pub fn is_hostile_to(self, other: Faction) -> bool {
+        self != other && self != Faction::Neutral && other != Faction::Neutral
+    }
+}
+
+/// What an AI-driven entity is currently up to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AiMode {
+    Idle,
+    Patrol,
+    Chase,
+    Attack,
+    Flee,
+    Return, // Walking back home after being leashed
+}
+
+/// Acts on its own following one of the behaviours in `behavior::ALL_BEHAVIORS`
+#[derive(Clone, Debug)]
+pub struct Ai {
+    pub behavior: &'static str, // Behaviour name
+    pub mode: AiMode,
+    pub target_entity_id: Option<u64>,
+    pub home: Vec2,              // Where the entity returns to when leashed
+    pub patrol_route: Vec<Vec2>, // Filled in from the room around home on the first patrol
+    pub patrol_index: usize,     // Waypoint currently walked to
+}
+
+impl Ai {
+    pub fn new(behavior: &'static str, home: Vec2) -> Self {
+        Self {
+            behavior,
+            mode: AiMode::Idle,
+            target_entity_id: None,
+            home,
+            patrol_route: Vec::new(),
+            patrol_index: 0,
+        }
+    }
+}
+
+/// Flies in a straight line until it hits a wall, has no pierce left or expires.
+/// Hits deal the damage and effects of the ability that launched it.
+#[derive(Clone, Debug)]
+pub struct Projectile {
+    pub ability: &'static str, // Name of an ability in `abilities::ALL_ABILITIES`
+    pub faction: Faction,      // Side of the shooter; only entities hostile to it are hit
+    pub velocity: Vec2,        // Tiles per second
+    pub pierce: u32,           // Entities it can still pass through after hitting one
+    pub hit_entity_ids: Vec<u64>, // Never hit twice
+}
+
+/// Rolled on `loot::ALL_LOOT_TABLES` when the entity dies
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loot {
+    pub table: &'static str, // Loot table name
+}
+
+/// The monster archetype an entity was spawned from
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonsterKind {
+    pub archetype: &'static str, // Name of an archetype in `prefabs::ALL_ARCHETYPES`
+}
+
+/// Experience an entity has gathered and the level it reached with it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Experience {
+    pub level: u32,
+    pub xp: u64, // Total gathered; not reset on level up
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self { level: 1, xp: 0 }
+    }
+}
+
+/// Experience shared out among whoever damaged the entity when it dies
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExperienceReward {
+    pub xp: u64,
+}
+
+/// Damage taken from each attacker so far, to split the `ExperienceReward` by
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DamageTaken {
+    pub by: BTreeMap<u64, u32>, // Attacker entity ID -> damage dealt
+}
+
+/// Gold an entity carries
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wallet {
+    pub gold: u64,
+}
+
+/// Sells the wares of a shop and buys items for their value
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vendor {
+    pub shop: &'static str, // Name of a shop in `shops::ALL_SHOPS`
+    pub stock: Vec<u32>,    // Left of each of the shop's wares
+    pub restock_at: f64,
+}
+
+/// The entity that created this one (summoner, shooter, ...)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Owner {
+    pub entity_id: u64,
+}
+
+/// Despawn the entity once `expires_at` has passed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lifetime {
+    pub expires_at: f64,
+}
+
+/// Can be picked up into an inventory
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pickup;
+
+/// Can be talked to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Talkable {
+    pub dialogue: &'static str, // Name of a dialogue in `dialogue::ALL_DIALOGUES`
+}
+
+/// A conversation the entity is having with a `Talkable` one
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conversation {
+    pub npc_id: u64,
+    pub dialogue: &'static str,
+    pub node: &'static str, // Node of the dialogue the NPC is waiting for an answer at
+    pub shop_open: bool,    // Whether the NPC showed its wares
+}
+
+/// Quests the entity took on
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuestLog {
+    pub quests: BTreeMap<&'static str, QuestProgress>, // Quest ID -> progress
+}
+
+/// Stays when it dies, waiting to be brought back with `EntityManager::respawn`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Respawns;
+
+/// A respawning entity that died and has not been brought back yet
+#[derive(Clone, Debug, PartialEq)]
+pub struct Death {
+    pub died_at: f64,
+}
+
+/// Marks where its owner died, until they respawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gravestone;
@@ -0,0 +1,105 @@
+//! Death and respawn of entities that are brought back instead of removed, like players.
+
+use super::components::*;
+use super::progression::xp_for_level;
+use super::{prefabs, EntityManager, StatusEffects};
+use crate::map::Vec2;
+
+/// Share of the experience between the current and the next level lost on death.
+/// Dying never costs a level.
+pub const DEATH_XP_PENALTY: f64 = 0.1;
+
+impl EntityManager {
+    /// Take the death penalty of a respawning entity that just died and leave a gravestone
+    /// where it fell. Returns the gravestone ID.
+    pub(super) fn die(&mut self, id: u64, current_time: f64) -> Option<u64> {
+        if let Some(experience) = self.get_mut::<Experience>(id) {
+            let floor = xp_for_level(experience.level);
+            let step = xp_for_level(experience.level + 1) - floor;
+            let penalty = (step as f64 * DEATH_XP_PENALTY) as u64;
+            experience.xp = experience.xp.saturating_sub(penalty).max(floor);
+        }
+        self.remove_component::<StatusEffects>(id);
+        self.remove_component::<Casting>(id);
+        self.insert(id, EntityState::Dead);
+        self.insert(
+            id,
+            Death {
+                died_at: current_time,
+            },
+        );
+
+        let position = self.position(id)?.clone();
+        Some(self.spawn(prefabs::gravestone(position, id)))
+    }
+
+    /// Bring a dead entity back at a position with full health and resources, removing its
+    /// gravestones. Returns the IDs of the removed gravestones.
+    pub fn respawn(&mut self, id: u64, position: Vec2) -> Result<Vec<u64>, String> {
+        if self.remove_component::<Death>(id).is_none() {
+            return Err("Entity is not dead".to_string());
+        }
+        if let Some(health) = self.get_mut::<Health>(id) {
+            health.current = health.max;
+        }
+        if let Some(resources) = self.get_mut::<Resources>(id) {
+            resources.mana = resources.max_mana;
+            resources.energy = resources.max_energy;
+        }
+        self.insert(id, EntityState::Idle);
+        self.set_position(id, position);
+
+        let gravestone_ids: Vec<u64> = self
+            .store::<Gravestone>()
+            .ids()
+            .into_iter()
+            .filter(|&gravestone_id| {
+                self.get::<Owner>(gravestone_id)
+                    .is_some_and(|owner| owner.entity_id == id)
+            })
+            .collect();
+        for &gravestone_id in &gravestone_ids {
+            self.remove_entity(gravestone_id);
+        }
+        Ok(gravestone_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_players_die_and_respawn() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(Vec2 { x: 3.0, y: 3.0 }));
+        manager.award_experience(player, 150);
+        manager.get_mut::<Health>(player).unwrap().current = 0;
+        assert!(manager.respawn(player, Vec2 { x: 0.0, y: 0.0 }).is_err());
+
+        // A tenth of the 200 experience from level 2 to 3, only once
+        manager.cleanup_dead_entities(5.0);
+        manager.cleanup_dead_entities(6.0);
+        assert_eq!(manager.get::<Death>(player), Some(&Death { died_at: 5.0 }));
+        assert_eq!(manager.get::<Experience>(player).unwrap().xp, 130);
+        let gravestones = manager.store::<Gravestone>().ids();
+        assert_eq!(gravestones.len(), 1);
+        assert_eq!(
+            manager.position(gravestones[0]).map(|position| position.x),
+            Some(3.0)
+        );
+
+        // Never below the start of the current level
+        manager.get_mut::<Experience>(player).unwrap().xp = 105;
+        manager.die(player, 7.0);
+        assert_eq!(manager.get::<Experience>(player).unwrap().xp, 100);
+
+        let removed = manager.respawn(player, Vec2 { x: 1.0, y: 1.0 }).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(manager.store::<Gravestone>().is_empty());
+        assert!(manager.is_alive(player));
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 110);
+        assert_eq!(manager.get::<EntityState>(player), Some(&EntityState::Idle));
+        assert!(!manager.has::<Death>(player));
+    }
+}
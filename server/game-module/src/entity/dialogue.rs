@@ -0,0 +1,579 @@
+//! Dialogue: conversation graphs NPCs walk players through.
+//!
+//! A dialogue is a set of nodes, each a line the NPC says and the options the player can
+//! answer with. Options only show when their conditions hold, run their actions when chosen
+//! and lead to another node or end the conversation.
+
+use super::components::*;
+use super::quests::{find_quest, QuestEvent, QuestStatus};
+use super::EntityManager;
+
+/// How close an entity must stay to an NPC to talk to it
+pub const TALK_RANGE: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DialogueCondition {
+    MinLevel(u32),
+    HasItem { item: &'static str, count: u32 },
+    QuestNotStarted(&'static str),
+    QuestActive(&'static str), // Taken on, with objectives left to meet
+    QuestReady(&'static str),  // Every objective met, waiting to be handed in
+    QuestCompleted(&'static str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DialogueAction {
+    GiveItem { item: &'static str, count: u32 },
+    TakeItem { item: &'static str, count: u32 },
+    StartQuest(&'static str),
+    CompleteQuest(&'static str), // Hand in a quest and pay out its reward
+    OpenShop,                    // The NPC's wares, for as long as the conversation lasts
+}
+
+#[derive(Debug, Clone)]
+pub struct DialogueOption {
+    pub text: &'static str,
+    pub conditions: &'static [DialogueCondition],
+    pub actions: &'static [DialogueAction],
+    pub next: Option<&'static str>, // Node to go to; `None` ends the conversation
+}
+
+#[derive(Debug, Clone)]
+pub struct DialogueNode {
+    pub id: &'static str,
+    pub text: &'static str,
+    pub options: &'static [DialogueOption],
+}
+
+#[derive(Debug, Clone)]
+pub struct Dialogue {
+    pub name: &'static str,
+    pub start: &'static str, // Node every conversation starts at
+    pub nodes: &'static [DialogueNode],
+}
+
+impl Dialogue {
+    pub fn node(&self, id: &str) -> Option<&'static DialogueNode> {
+        let nodes: &'static [DialogueNode] = self.nodes;
+        nodes.iter().find(|node| node.id == id)
+    }
+}
+
+const GOODBYE: DialogueOption = DialogueOption {
+    text: "Goodbye.",
+    conditions: &[],
+    actions: &[],
+    next: None,
+};
+
+pub const VILLAGER: Dialogue = Dialogue {
+    name: "villager",
+    start: "greeting",
+    nodes: &[
+        DialogueNode {
+            id: "greeting",
+            text: "Welcome, traveller. Mind the portal, it leads straight into the dungeon.",
+            options: &[
+                DialogueOption {
+                    text: "What is down there?",
+                    conditions: &[],
+                    actions: &[],
+                    next: Some("dungeon"),
+                },
+                DialogueOption {
+                    text: "Who should I talk to around here?",
+                    conditions: &[DialogueCondition::QuestNotStarted("introductions")],
+                    actions: &[],
+                    next: Some("elder"),
+                },
+                DialogueOption {
+                    text: "I met the elder.",
+                    conditions: &[DialogueCondition::QuestReady("introductions")],
+                    actions: &[DialogueAction::CompleteQuest("introductions")],
+                    next: Some("welcome"),
+                },
+                GOODBYE,
+            ],
+        },
+        DialogueNode {
+            id: "dungeon",
+            text: "Rats, wolves and worse. The old guards never stopped keeping watch.",
+            options: &[GOODBYE],
+        },
+        DialogueNode {
+            id: "elder",
+            text: "The elder, of course. Go and introduce yourself, nothing here escapes them.",
+            options: &[
+                DialogueOption {
+                    text: "I will.",
+                    conditions: &[],
+                    actions: &[DialogueAction::StartQuest("introductions")],
+                    next: None,
+                },
+                GOODBYE,
+            ],
+        },
+        DialogueNode {
+            id: "welcome",
+            text: "Then you are one of us now. Here, for the road.",
+            options: &[GOODBYE],
+        },
+    ],
+};
+
+pub const ELDER: Dialogue = Dialogue {
+    name: "elder",
+    start: "greeting",
+    nodes: &[
+        DialogueNode {
+            id: "greeting",
+            text: "Ah, a new face. The town could use someone handy with a blade.",
+            options: &[
+                DialogueOption {
+                    text: "Do you need help?",
+                    conditions: &[DialogueCondition::QuestNotStarted("rat_problem")],
+                    actions: &[],
+                    next: Some("rats"),
+                },
+                DialogueOption {
+                    text: "About the rats...",
+                    conditions: &[DialogueCondition::QuestActive("rat_problem")],
+                    actions: &[],
+                    next: Some("rats_reminder"),
+                },
+                DialogueOption {
+                    text: "The rats are dealt with.",
+                    conditions: &[DialogueCondition::QuestReady("rat_problem")],
+                    actions: &[DialogueAction::CompleteQuest("rat_problem")],
+                    next: Some("reward"),
+                },
+                DialogueOption {
+                    text: "Is there more work?",
+                    conditions: &[
+                        DialogueCondition::QuestCompleted("rat_problem"),
+                        DialogueCondition::QuestNotStarted("bones_of_the_deep"),
+                        DialogueCondition::MinLevel(2),
+                    ],
+                    actions: &[],
+                    next: Some("bones"),
+                },
+                DialogueOption {
+                    text: "The skeletons are at rest.",
+                    conditions: &[DialogueCondition::QuestReady("bones_of_the_deep")],
+                    actions: &[DialogueAction::CompleteQuest("bones_of_the_deep")],
+                    next: Some("reward"),
+                },
+                DialogueOption {
+                    text: "Anything else down there?",
+                    conditions: &[
+                        DialogueCondition::QuestCompleted("bones_of_the_deep"),
+                        DialogueCondition::QuestNotStarted("secrets_below"),
+                    ],
+                    actions: &[],
+                    next: Some("secrets"),
+                },
+                DialogueOption {
+                    text: "The dungeon is clear.",
+                    conditions: &[DialogueCondition::QuestReady("secrets_below")],
+                    actions: &[DialogueAction::CompleteQuest("secrets_below")],
+                    next: Some("reward"),
+                },
+                DialogueOption {
+                    text: "I brought bones from the dungeon.",
+                    conditions: &[DialogueCondition::HasItem {
+                        item: "bone",
+                        count: 5,
+                    }],
+                    actions: &[
+                        DialogueAction::TakeItem {
+                            item: "bone",
+                            count: 5,
+                        },
+                        DialogueAction::GiveItem {
+                            item: "health_potion",
+                            count: 1,
+                        },
+                    ],
+                    next: Some("bones_traded"),
+                },
+                GOODBYE,
+            ],
+        },
+        DialogueNode {
+            id: "rats",
+            text: "Rats keep crawling out of the dungeon into our stores. Could you thin them out?",
+            options: &[
+                DialogueOption {
+                    text: "I'll take care of it.",
+                    conditions: &[],
+                    actions: &[DialogueAction::StartQuest("rat_problem")],
+                    next: Some("thanks"),
+                },
+                DialogueOption {
+                    text: "Not now.",
+                    conditions: &[],
+                    actions: &[],
+                    next: None,
+                },
+            ],
+        },
+        DialogueNode {
+            id: "rats_reminder",
+            text: "The rats are still about. The dungeon portal is by the square.",
+            options: &[GOODBYE],
+        },
+        DialogueNode {
+            id: "bones",
+            text: "The skeletons below were our guards once. Put them to rest.",
+            options: &[
+                DialogueOption {
+                    text: "Consider it done.",
+                    conditions: &[],
+                    actions: &[DialogueAction::StartQuest("bones_of_the_deep")],
+                    next: Some("thanks"),
+                },
+                GOODBYE,
+            ],
+        },
+        DialogueNode {
+            id: "secrets",
+            text: "They say the builders hid levers in the walls. Find one, and clear the halls for good.",
+            options: &[
+                DialogueOption {
+                    text: "I'll go and look.",
+                    conditions: &[],
+                    actions: &[DialogueAction::StartQuest("secrets_below")],
+                    next: Some("thanks"),
+                },
+                GOODBYE,
+            ],
+        },
+        DialogueNode {
+            id: "reward",
+            text: "Well done. Take this, you have earned it.",
+            options: &[GOODBYE],
+        },
+        DialogueNode {
+            id: "bones_traded",
+            text: "Proper burial for them, and a potion for your trouble.",
+            options: &[GOODBYE],
+        },
+        DialogueNode {
+            id: "thanks",
+            text: "Thank you. Come back when it is done.",
+            options: &[GOODBYE],
+        },
+    ],
+};
+
+pub const MERCHANT: Dialogue = Dialogue {
+    name: "merchant",
+    start: "greeting",
+    nodes: &[
+        DialogueNode {
+            id: "greeting",
+            text: "Potions, blades, trinkets. Everything an adventurer needs!",
+            options: &[
+                DialogueOption {
+                    text: "Show me your wares.",
+                    conditions: &[],
+                    actions: &[DialogueAction::OpenShop],
+                    next: Some("browsing"),
+                },
+                GOODBYE,
+            ],
+        },
+        DialogueNode {
+            id: "browsing",
+            text: "Take your time.",
+            options: &[DialogueOption {
+                text: "That's all.",
+                conditions: &[],
+                actions: &[],
+                next: None,
+            }],
+        },
+    ],
+};
+
+pub const ALL_DIALOGUES: &[Dialogue] = &[VILLAGER, ELDER, MERCHANT];
+
+/// Find a dialogue by name
+pub fn find_dialogue(name: &str) -> Option<&'static Dialogue> {
+    ALL_DIALOGUES.iter().find(|dialogue| dialogue.name == name)
+}
+
+impl EntityManager {
+    /// Whether a condition holds for an entity
+    pub fn meets(&self, id: u64, condition: &DialogueCondition) -> bool {
+        match *condition {
+            DialogueCondition::MinLevel(level) => self
+                .get::<Experience>(id)
+                .is_some_and(|experience| experience.level >= level),
+            DialogueCondition::HasItem { item, count } => self
+                .get::<Inventory>(id)
+                .is_some_and(|inventory| inventory.count(item) >= count),
+            DialogueCondition::QuestNotStarted(quest) => self.quest_status(id, quest).is_none(),
+            DialogueCondition::QuestActive(quest) => {
+                self.quest_status(id, quest) == Some(QuestStatus::Active)
+                    && !self.quest_ready(id, quest)
+            }
+            DialogueCondition::QuestReady(quest) => self.quest_ready(id, quest),
+            DialogueCondition::QuestCompleted(quest) => {
+                self.quest_status(id, quest) == Some(QuestStatus::Completed)
+            }
+        }
+    }
+
+    /// Start talking to an NPC at the start of its dialogue, leaving any other conversation
+    pub(super) fn start_conversation(
+        &mut self,
+        id: u64,
+        npc_id: u64,
+    ) -> Result<&'static DialogueNode, String> {
+        let talkable = self.get::<Talkable>(npc_id).ok_or("Cannot talk to that")?;
+        let dialogue = find_dialogue(talkable.dialogue).ok_or("Unknown dialogue")?;
+        let node = dialogue
+            .node(dialogue.start)
+            .ok_or("Unknown dialogue node")?;
+        self.record_quest_event(id, QuestEvent::TalkedTo(dialogue.name));
+        self.insert(
+            id,
+            Conversation {
+                npc_id,
+                dialogue: dialogue.name,
+                node: node.id,
+                shop_open: false,
+            },
+        );
+        Ok(node)
+    }
+
+    /// The node an entity's conversation is at
+    pub fn dialogue_node(&self, id: u64) -> Option<&'static DialogueNode> {
+        let conversation = self.get::<Conversation>(id)?;
+        find_dialogue(conversation.dialogue)?.node(conversation.node)
+    }
+
+    /// Indices of the options of the current node whose conditions the entity meets
+    pub fn dialogue_options(&self, id: u64) -> Vec<usize> {
+        let Some(node) = self.dialogue_node(id) else {
+            return Vec::new();
+        };
+        node.options
+            .iter()
+            .enumerate()
+            .filter(|(_, option)| {
+                option
+                    .conditions
+                    .iter()
+                    .all(|condition| self.meets(id, condition))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Answer with an option of the current node and run its actions. Nothing changes if an
+    /// action cannot be carried out. Returns the next node, or `None` if the conversation
+    /// ended.
+    pub fn choose_dialogue_option(
+        &mut self,
+        id: u64,
+        option: usize,
+    ) -> Result<Option<&'static DialogueNode>, String> {
+        let conversation = self
+            .get::<Conversation>(id)
+            .cloned()
+            .ok_or("Not in a conversation")?;
+        if let Err(error) =
+            self.check_reach(id, conversation.npc_id, TALK_RANGE, "Too far from NPC")
+        {
+            self.end_conversation(id);
+            return Err(error);
+        }
+        let dialogue = find_dialogue(conversation.dialogue).ok_or("Unknown dialogue")?;
+        let node = dialogue
+            .node(conversation.node)
+            .ok_or("Unknown dialogue node")?;
+        if !self.dialogue_options(id).contains(&option) {
+            return Err("Option not available".to_string());
+        }
+        let option = &node.options[option];
+
+        // Carry out the actions on copies, so a failing one leaves everything untouched
+        let mut inventory = self.get::<Inventory>(id).cloned();
+        let mut quests = self.get::<QuestLog>(id).cloned();
+        let mut shop_open = conversation.shop_open;
+        let mut xp = 0;
+        let mut gold = 0;
+        for action in option.actions {
+            match *action {
+                DialogueAction::GiveItem { item, count } => {
+                    let inventory = inventory.as_mut().ok_or("Entity has no inventory")?;
+                    if inventory.add(item, count) > 0 {
+                        return Err("Inventory is full".to_string());
+                    }
+                }
+                DialogueAction::TakeItem { item, count } => {
+                    let inventory = inventory.as_mut().ok_or("Entity has no inventory")?;
+                    if !inventory.remove(item, count) {
+                        return Err("Missing items".to_string());
+                    }
+                }
+                DialogueAction::StartQuest(quest) => {
+                    let quest = find_quest(quest).ok_or("Unknown quest")?;
+                    quests
+                        .as_mut()
+                        .ok_or("Entity cannot take quests")?
+                        .start(quest)?;
+                }
+                DialogueAction::CompleteQuest(quest) => {
+                    let quest = find_quest(quest).ok_or("Unknown quest")?;
+                    quests
+                        .as_mut()
+                        .ok_or("Entity cannot take quests")?
+                        .complete(quest)?;
+                    let inventory = inventory.as_mut().ok_or("Entity has no inventory")?;
+                    for &(item, count) in quest.reward.items {
+                        if inventory.add(item, count) > 0 {
+                            return Err("Inventory is full".to_string());
+                        }
+                    }
+                    xp += quest.reward.xp;
+                    gold += quest.reward.gold;
+                }
+                DialogueAction::OpenShop => shop_open = true,
+            }
+        }
+        if let Some(inventory) = inventory {
+            self.insert(id, inventory);
+        }
+        if let Some(quests) = quests {
+            self.insert(id, quests);
+        }
+        if let Some(wallet) = self.get_mut::<Wallet>(id) {
+            wallet.gold += gold;
+        }
+        self.award_experience(id, xp);
+
+        let Some(next) = option.next.and_then(|next| dialogue.node(next)) else {
+            self.end_conversation(id);
+            return Ok(None);
+        };
+        self.insert(
+            id,
+            Conversation {
+                node: next.id,
+                shop_open,
+                ..conversation
+            },
+        );
+        Ok(Some(next))
+    }
+
+    pub fn end_conversation(&mut self, id: u64) {
+        self.remove_component::<Conversation>(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{prefabs, InteractionResult};
+    use crate::map::Vec2;
+
+    #[test]
+    fn test_dialogues_only_lead_to_nodes_that_exist() {
+        for dialogue in ALL_DIALOGUES {
+            assert!(dialogue.node(dialogue.start).is_some());
+            for node in dialogue.nodes {
+                for option in node.options {
+                    if let Some(next) = option.next {
+                        assert!(dialogue.node(next).is_some(), "{} -> {}", node.id, next);
+                    }
+                    for action in option.actions {
+                        if let DialogueAction::StartQuest(quest)
+                        | DialogueAction::CompleteQuest(quest) = action
+                        {
+                            assert!(find_quest(quest).is_some());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_conversation_follows_conditions_and_actions() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(Vec2 { x: 0.0, y: 0.0 }));
+        let elder = manager
+            .spawn(prefabs::npc(Vec2 { x: 1.0, y: 0.0 }).with(Talkable { dialogue: "elder" }));
+        assert!(manager.choose_dialogue_option(player, 0).is_err());
+
+        let result = manager.interact_entities(player, elder).unwrap();
+        assert!(matches!(
+            result,
+            InteractionResult::NPCInteraction { node } if node.id == "greeting"
+        ));
+        // Only the quest offer and goodbye: no bones, and the quest is not started
+        assert_eq!(manager.dialogue_options(player), vec![0, 8]);
+        assert!(manager.choose_dialogue_option(player, 1).is_err());
+
+        let node = manager.choose_dialogue_option(player, 0).unwrap().unwrap();
+        assert_eq!(node.id, "rats");
+        let node = manager.choose_dialogue_option(player, 0).unwrap().unwrap();
+        assert_eq!(node.id, "thanks");
+        assert_eq!(
+            manager.quest_status(player, "rat_problem"),
+            Some(QuestStatus::Active)
+        );
+        assert!(manager.choose_dialogue_option(player, 0).unwrap().is_none());
+        assert!(!manager.has::<Conversation>(player));
+
+        // Handing the quest in pays out its reward
+        for _ in 0..5 {
+            manager.record_quest_event(player, QuestEvent::Killed("rat"));
+        }
+        manager.interact_entities(player, elder).unwrap();
+        assert_eq!(manager.dialogue_options(player), vec![2, 8]);
+        let node = manager.choose_dialogue_option(player, 2).unwrap().unwrap();
+        assert_eq!(node.id, "reward");
+        assert_eq!(manager.get::<Experience>(player).unwrap().level, 2);
+        assert_eq!(
+            manager.quest_status(player, "rat_problem"),
+            Some(QuestStatus::Completed)
+        );
+        manager.end_conversation(player);
+        manager
+            .get_mut::<Inventory>(player)
+            .unwrap()
+            .remove("health_potion", 2);
+
+        // Trading bones runs both actions, or neither when the potion would not fit
+        manager.get_mut::<Inventory>(player).unwrap().add("bone", 6);
+        manager.interact_entities(player, elder).unwrap();
+        assert_eq!(manager.dialogue_options(player), vec![3, 7, 8]);
+        let inventory = manager.get_mut::<Inventory>(player).unwrap();
+        inventory.slots.truncate(1);
+        assert_eq!(
+            manager.choose_dialogue_option(player, 7).unwrap_err(),
+            "Inventory is full"
+        );
+        assert_eq!(manager.get::<Inventory>(player).unwrap().count("bone"), 6);
+        manager
+            .get_mut::<Inventory>(player)
+            .unwrap()
+            .slots
+            .push(None);
+        manager.choose_dialogue_option(player, 7).unwrap();
+        let inventory = manager.get::<Inventory>(player).unwrap();
+        assert_eq!(inventory.count("bone"), 1);
+        assert_eq!(inventory.count("health_potion"), 1);
+
+        // Walking away ends the conversation
+        manager.set_position(player, Vec2 { x: 5.0, y: 0.0 });
+        assert!(manager.choose_dialogue_option(player, 0).is_err());
+        assert!(!manager.has::<Conversation>(player));
+    }
+}
@@ -0,0 +1,238 @@
+//! Timed status effects: damage and healing over time, crowd control and speed changes.
+
+use crate::combat::{DamageBreakdown, DamageType, HitOutcome};
+
+/// Seconds between two ticks of damage or healing over time
+pub const EFFECT_TICK_INTERVAL: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EffectKind {
+    Poison,
+    Burn,
+    Bleed,
+    Stun,
+    Slow,
+    Root,
+    Regeneration,
+    Haste,
+}
+
+/// What happens when an effect is applied to an entity that already has it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stacking {
+    /// Keep one, with the strongest magnitude and the latest expiry
+    Replace,
+    /// Keep one, add a stack and refresh the duration
+    Intensify { max_stacks: u32 },
+    /// Keep every application, dropping the oldest when full
+    Independent { max_instances: u32 },
+}
+
+impl EffectKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            EffectKind::Poison => Stacking::Intensify { max_stacks: 5 },
+            EffectKind::Bleed => Stacking::Independent { max_instances: 3 },
+            _ => Stacking::Replace,
+        }
+    }
+
+    /// Damage type of damage over time; `None` for effects that don't hurt
+    pub fn damage_type(self) -> Option<DamageType> {
+        match self {
+            EffectKind::Poison => Some(DamageType::Poison),
+            EffectKind::Burn => Some(DamageType::Fire),
+            EffectKind::Bleed => Some(DamageType::Physical),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatusEffect {
+    pub kind: EffectKind,
+    pub source_entity_id: Option<u64>, // Who applied it, credited with its damage
+    pub magnitude: f64, // Damage or healing per tick and stack, or speed fraction for slow and haste
+    pub stacks: u32,
+    pub expires_at: f64,
+    pub next_tick_at: f64,
+}
+
+impl StatusEffect {
+    pub fn new(
+        kind: EffectKind,
+        source_entity_id: Option<u64>,
+        magnitude: f64,
+        duration: f64,
+        now: f64,
+    ) -> Self {
+        Self {
+            kind,
+            source_entity_id,
+            magnitude,
+            stacks: 1,
+            expires_at: now + duration,
+            next_tick_at: now + EFFECT_TICK_INTERVAL,
+        }
+    }
+}
+
+/// Effects currently affecting an entity
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatusEffects {
+    pub active: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    /// Add an effect following the stacking rule of its kind
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let existing = self.active.iter().position(|e| e.kind == effect.kind);
+        match (effect.kind.stacking(), existing) {
+            (Stacking::Replace, Some(index)) => {
+                let current = &mut self.active[index];
+                if effect.magnitude >= current.magnitude {
+                    current.magnitude = effect.magnitude;
+                    current.source_entity_id = effect.source_entity_id;
+                }
+                current.expires_at = current.expires_at.max(effect.expires_at);
+            }
+            (Stacking::Intensify { max_stacks }, Some(index)) => {
+                let current = &mut self.active[index];
+                current.stacks = (current.stacks + 1).min(max_stacks);
+                current.magnitude = current.magnitude.max(effect.magnitude);
+                current.source_entity_id = effect.source_entity_id;
+                current.expires_at = current.expires_at.max(effect.expires_at);
+            }
+            (Stacking::Independent { max_instances }, Some(_)) => {
+                let count = self.count(effect.kind);
+                if count >= max_instances as usize {
+                    self.active.remove(existing.unwrap());
+                }
+                self.active.push(effect);
+            }
+            (_, None) => self.active.push(effect),
+        }
+    }
+
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.active.iter().any(|effect| effect.kind == kind)
+    }
+
+    fn count(&self, kind: EffectKind) -> usize {
+        self.active.iter().filter(|e| e.kind == kind).count()
+    }
+
+    /// Stunned entities can neither move nor attack
+    pub fn can_act(&self) -> bool {
+        !self.has(EffectKind::Stun)
+    }
+
+    pub fn can_move(&self) -> bool {
+        self.can_act() && !self.has(EffectKind::Root)
+    }
+
+    /// Factor applied to movement speed by slows and hastes
+    pub fn speed_multiplier(&self) -> f64 {
+        if !self.can_move() {
+            return 0.0;
+        }
+        self.active
+            .iter()
+            .map(|effect| match effect.kind {
+                EffectKind::Slow => (1.0 - effect.magnitude).max(0.0),
+                EffectKind::Haste => 1.0 + effect.magnitude,
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    /// Drop the effects that ran out
+    pub fn remove_expired(&mut self, now: f64) {
+        self.active.retain(|effect| effect.expires_at > now);
+    }
+}
+
+/// The effect a landed attack leaves behind, depending on its damage type
+pub fn on_hit_effect(
+    breakdown: &DamageBreakdown,
+    source_entity_id: u64,
+    now: f64,
+) -> Option<StatusEffect> {
+    let damage = breakdown.damage_dealt as f64;
+    let (kind, magnitude, duration) = match (breakdown.outcome, breakdown.damage_type) {
+        (HitOutcome::Miss, _) => return None,
+        (_, DamageType::Poison) => (EffectKind::Poison, (damage / 5.0).max(1.0), 5.0),
+        (_, DamageType::Fire) => (EffectKind::Burn, (damage / 4.0).max(1.0), 3.0),
+        (_, DamageType::Ice) => (EffectKind::Slow, 0.3, 2.0),
+        (HitOutcome::Critical, DamageType::Physical) => {
+            (EffectKind::Bleed, (damage / 5.0).max(1.0), 4.0)
+        }
+        (HitOutcome::Hit, DamageType::Physical) => return None,
+    };
+    Some(StatusEffect::new(
+        kind,
+        Some(source_entity_id),
+        magnitude,
+        duration,
+        now,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: EffectKind, source: u64, magnitude: f64, duration: f64) -> StatusEffect {
+        StatusEffect::new(kind, Some(source), magnitude, duration, 0.0)
+    }
+
+    #[test]
+    fn test_stacking_rules() {
+        let mut effects = StatusEffects::default();
+
+        // Poison intensifies up to five stacks and is credited to the latest source
+        for source in 1..=7 {
+            effects.apply(effect(EffectKind::Poison, source, 2.0, 5.0));
+        }
+        assert_eq!(effects.active.len(), 1);
+        assert_eq!(effects.active[0].stacks, 5);
+        assert_eq!(effects.active[0].source_entity_id, Some(7));
+
+        // Bleeds stay separate, the oldest falls off
+        for source in 1..=4 {
+            effects.apply(effect(EffectKind::Bleed, source, 1.0, 4.0));
+        }
+        let bleeds: Vec<Option<u64>> = effects
+            .active
+            .iter()
+            .filter(|e| e.kind == EffectKind::Bleed)
+            .map(|e| e.source_entity_id)
+            .collect();
+        assert_eq!(bleeds, vec![Some(2), Some(3), Some(4)]);
+
+        // Only the strongest slow counts, and a weaker one still extends it
+        effects.apply(effect(EffectKind::Slow, 1, 0.5, 1.0));
+        effects.apply(effect(EffectKind::Slow, 2, 0.2, 3.0));
+        let slow = effects
+            .active
+            .iter()
+            .find(|e| e.kind == EffectKind::Slow)
+            .unwrap();
+        assert_eq!((slow.magnitude, slow.expires_at), (0.5, 3.0));
+    }
+
+    #[test]
+    fn test_movement_modifiers() {
+        let mut effects = StatusEffects::default();
+        effects.apply(effect(EffectKind::Slow, 1, 0.5, 2.0));
+        effects.apply(effect(EffectKind::Haste, 2, 0.5, 4.0));
+        assert_eq!(effects.speed_multiplier(), 0.75);
+
+        effects.apply(effect(EffectKind::Root, 1, 0.0, 1.0));
+        assert!(effects.can_act() && !effects.can_move());
+        assert_eq!(effects.speed_multiplier(), 0.0);
+
+        effects.remove_expired(2.0);
+        assert_eq!(effects.speed_multiplier(), 1.5);
+    }
+}
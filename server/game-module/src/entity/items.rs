@@ -0,0 +1,574 @@
+//! Item definitions, inventory stacks and equipment.
+//!
+//! Items are referred to by ID, like abilities and behaviours. Worn items add their stat
+//! modifiers to the entity's effective combat, defense and speed.
+
+use super::components::*;
+use super::EntityManager;
+use crate::combat::Resistances;
+
+/// Slots in a fresh inventory
+pub const INVENTORY_SLOTS: usize = 20;
+
+/// How close an entity must be to an item on the ground to pick it up
+pub const PICKUP_RANGE: f64 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Weapon,
+    Armor,
+    Trinket,
+    Consumable,
+    Material,
+}
+
+/// How hard an item is to come by; loot tables can roll any item of a tier
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+}
+
+/// Where a worn item sits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+    Trinket1,
+    Trinket2,
+}
+
+impl ItemKind {
+    /// Equipment slots items of this kind fit in, empty for items that cannot be worn
+    pub fn slots(self) -> &'static [EquipSlot] {
+        match self {
+            ItemKind::Weapon => &[EquipSlot::Weapon],
+            ItemKind::Armor => &[EquipSlot::Armor],
+            ItemKind::Trinket => &[EquipSlot::Trinket1, EquipSlot::Trinket2],
+            ItemKind::Consumable | ItemKind::Material => &[],
+        }
+    }
+}
+
+/// What wearing an item adds to the wearer's stats
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatModifiers {
+    pub attack_damage: i32,
+    pub accuracy: f64,
+    pub crit_chance: f64,
+    pub evasion: f64,
+    pub resistances: Resistances,
+    pub move_speed: f64, // Fraction of the base speed
+}
+
+impl StatModifiers {
+    pub fn add(&mut self, other: &StatModifiers) {
+        self.attack_damage += other.attack_damage;
+        self.accuracy += other.accuracy;
+        self.crit_chance += other.crit_chance;
+        self.evasion += other.evasion;
+        self.resistances.physical += other.resistances.physical;
+        self.resistances.fire += other.resistances.fire;
+        self.resistances.ice += other.resistances.ice;
+        self.resistances.poison += other.resistances.poison;
+        self.move_speed += other.move_speed;
+    }
+}
+
+const NO_MODIFIERS: StatModifiers = StatModifiers {
+    attack_damage: 0,
+    accuracy: 0.0,
+    crit_chance: 0.0,
+    evasion: 0.0,
+    resistances: Resistances {
+        physical: 0,
+        fire: 0,
+        ice: 0,
+        poison: 0,
+    },
+    move_speed: 0.0,
+};
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub id: &'static str,
+    pub name: &'static str, // Shown to players
+    pub kind: ItemKind,
+    pub rarity: Rarity,
+    pub stack_size: u32, // Most items one inventory slot holds
+    pub value: u64,      // Gold a vendor pays for one; vendors do not buy items worth nothing
+    pub modifiers: StatModifiers,
+}
+
+pub const RUSTY_SWORD: Item = Item {
+    id: "rusty_sword",
+    name: "Rusty Sword",
+    kind: ItemKind::Weapon,
+    rarity: Rarity::Common,
+    stack_size: 1,
+    value: 5,
+    modifiers: StatModifiers {
+        attack_damage: 4,
+        ..NO_MODIFIERS
+    },
+};
+
+pub const IRON_SWORD: Item = Item {
+    id: "iron_sword",
+    name: "Iron Sword",
+    kind: ItemKind::Weapon,
+    rarity: Rarity::Uncommon,
+    stack_size: 1,
+    value: 40,
+    modifiers: StatModifiers {
+        attack_damage: 8,
+        accuracy: 0.02,
+        ..NO_MODIFIERS
+    },
+};
+
+pub const WAR_AXE: Item = Item {
+    id: "war_axe",
+    name: "War Axe",
+    kind: ItemKind::Weapon,
+    rarity: Rarity::Rare,
+    stack_size: 1,
+    value: 60,
+    modifiers: StatModifiers {
+        attack_damage: 12,
+        accuracy: -0.05,
+        crit_chance: 0.05,
+        ..NO_MODIFIERS
+    },
+};
+
+pub const LEATHER_ARMOR: Item = Item {
+    id: "leather_armor",
+    name: "Leather Armor",
+    kind: ItemKind::Armor,
+    rarity: Rarity::Common,
+    stack_size: 1,
+    value: 15,
+    modifiers: StatModifiers {
+        evasion: 0.05,
+        resistances: Resistances {
+            physical: 10,
+            ..NO_MODIFIERS.resistances
+        },
+        ..NO_MODIFIERS
+    },
+};
+
+pub const CHAIN_MAIL: Item = Item {
+    id: "chain_mail",
+    name: "Chain Mail",
+    kind: ItemKind::Armor,
+    rarity: Rarity::Uncommon,
+    stack_size: 1,
+    value: 50,
+    modifiers: StatModifiers {
+        resistances: Resistances {
+            physical: 25,
+            ..NO_MODIFIERS.resistances
+        },
+        move_speed: -0.1,
+        ..NO_MODIFIERS
+    },
+};
+
+pub const LUCKY_CHARM: Item = Item {
+    id: "lucky_charm",
+    name: "Lucky Charm",
+    kind: ItemKind::Trinket,
+    rarity: Rarity::Uncommon,
+    stack_size: 1,
+    value: 30,
+    modifiers: StatModifiers {
+        crit_chance: 0.05,
+        ..NO_MODIFIERS
+    },
+};
+
+pub const RING_OF_SWIFTNESS: Item = Item {
+    id: "ring_of_swiftness",
+    name: "Ring of Swiftness",
+    kind: ItemKind::Trinket,
+    rarity: Rarity::Rare,
+    stack_size: 1,
+    value: 45,
+    modifiers: StatModifiers {
+        move_speed: 0.15,
+        ..NO_MODIFIERS
+    },
+};
+
+pub const AMULET_OF_WARDING: Item = Item {
+    id: "amulet_of_warding",
+    name: "Amulet of Warding",
+    kind: ItemKind::Trinket,
+    rarity: Rarity::Epic,
+    stack_size: 1,
+    value: 80,
+    modifiers: StatModifiers {
+        resistances: Resistances {
+            physical: 5,
+            fire: 15,
+            ice: 15,
+            poison: 15,
+        },
+        ..NO_MODIFIERS
+    },
+};
+
+pub const HEALTH_POTION: Item = Item {
+    id: "health_potion",
+    name: "Health Potion",
+    kind: ItemKind::Consumable,
+    rarity: Rarity::Common,
+    stack_size: 10,
+    value: 8,
+    modifiers: NO_MODIFIERS,
+};
+
+pub const MANA_POTION: Item = Item {
+    id: "mana_potion",
+    name: "Mana Potion",
+    kind: ItemKind::Consumable,
+    rarity: Rarity::Common,
+    stack_size: 10,
+    value: 8,
+    modifiers: NO_MODIFIERS,
+};
+
+pub const BONE: Item = Item {
+    id: "bone",
+    name: "Bone",
+    kind: ItemKind::Material,
+    rarity: Rarity::Common,
+    stack_size: 50,
+    value: 1,
+    modifiers: NO_MODIFIERS,
+};
+
+pub const IRON_ORE: Item = Item {
+    id: "iron_ore",
+    name: "Iron Ore",
+    kind: ItemKind::Material,
+    rarity: Rarity::Common,
+    stack_size: 50,
+    value: 3,
+    modifiers: NO_MODIFIERS,
+};
+
+pub const ALL_ITEMS: &[Item] = &[
+    RUSTY_SWORD,
+    IRON_SWORD,
+    WAR_AXE,
+    LEATHER_ARMOR,
+    CHAIN_MAIL,
+    LUCKY_CHARM,
+    RING_OF_SWIFTNESS,
+    AMULET_OF_WARDING,
+    HEALTH_POTION,
+    MANA_POTION,
+    BONE,
+    IRON_ORE,
+];
+
+/// Find an item by ID
+pub fn find_item(id: &str) -> Option<&'static Item> {
+    ALL_ITEMS.iter().find(|item| item.id == id)
+}
+
+/// Most of an item one slot holds; unknown items don't stack
+fn stack_size(id: &str) -> u32 {
+    find_item(id).map_or(1, |item| item.stack_size)
+}
+
+impl Inventory {
+    /// Put items in, topping up stacks of the same item before filling empty slots.
+    /// Returns how many did not fit.
+    pub fn add(&mut self, item: &'static str, count: u32) -> u32 {
+        let stack_size = stack_size(item);
+        let mut left = count;
+        for stack in self.slots.iter_mut().flatten() {
+            if stack.item == item && stack.count < stack_size {
+                let moved = left.min(stack_size - stack.count);
+                stack.count += moved;
+                left -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if left == 0 {
+                break;
+            }
+            let moved = left.min(stack_size);
+            *slot = Some(ItemStack { item, count: moved });
+            left -= moved;
+        }
+        left
+    }
+
+    /// How many of an item are carried across all slots
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Take `count` of an item out of whichever slots hold it, emptying the last slots first.
+    /// Takes nothing and returns false if fewer are carried.
+    pub fn remove(&mut self, item: &str, count: u32) -> bool {
+        if self.count(item) < count {
+            return false;
+        }
+        let mut left = count;
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) else {
+                continue;
+            };
+            let taken = left.min(stack.count);
+            stack.count -= taken;
+            left -= taken;
+            if stack.count == 0 {
+                *slot = None;
+            }
+        }
+        true
+    }
+
+    /// Take up to `count` items out of one slot
+    pub fn take(&mut self, index: usize, count: u32) -> Option<ItemStack> {
+        let slot = self.slots.get_mut(index)?;
+        let stack = slot.as_mut()?;
+        let taken = count.min(stack.count);
+        if taken == 0 {
+            return None;
+        }
+        stack.count -= taken;
+        let item = stack.item;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(ItemStack { item, count: taken })
+    }
+
+    /// Move a stack to another slot. It merges into a stack of the same item as far as the
+    /// stack size allows and swaps places with anything else.
+    pub fn move_stack(&mut self, from: usize, to: usize) -> Result<(), String> {
+        if from >= self.slots.len() || to >= self.slots.len() {
+            return Err("Invalid inventory slot".to_string());
+        }
+        let moving = self.slots[from].clone().ok_or("Inventory slot is empty")?;
+        if from == to {
+            return Ok(());
+        }
+        match &mut self.slots[to] {
+            Some(target) if target.item == moving.item => {
+                let moved = moving
+                    .count
+                    .min(stack_size(moving.item).saturating_sub(target.count));
+                target.count += moved;
+                self.take(from, moved);
+            }
+            _ => self.slots.swap(from, to),
+        }
+        Ok(())
+    }
+
+    /// Move part of a stack into an empty slot
+    pub fn split_stack(&mut self, from: usize, to: usize, count: u32) -> Result<(), String> {
+        if from >= self.slots.len() || to >= self.slots.len() {
+            return Err("Invalid inventory slot".to_string());
+        }
+        if self.slots[to].is_some() {
+            return Err("Target slot is not empty".to_string());
+        }
+        let stack = self.slots[from].as_ref().ok_or("Inventory slot is empty")?;
+        if count == 0 || count >= stack.count {
+            return Err("Cannot split off that many items".to_string());
+        }
+        self.slots[to] = self.take(from, count);
+        Ok(())
+    }
+}
+
+impl Equipment {
+    /// Sum of the modifiers of every worn item
+    pub fn modifiers(&self) -> StatModifiers {
+        let mut modifiers = StatModifiers::default();
+        for item in self.slots.values().filter_map(|id| find_item(id)) {
+            modifiers.add(&item.modifiers);
+        }
+        modifiers
+    }
+}
+
+impl EntityManager {
+    /// Wear the item in an inventory slot, in a free slot of its kind if there is one.
+    /// Whatever that slot held goes back into the inventory.
+    pub fn equip_item(&mut self, entity_id: u64, index: usize) -> Result<EquipSlot, String> {
+        let mut inventory = self
+            .get::<Inventory>(entity_id)
+            .ok_or("Entity has no inventory")?
+            .clone();
+        let stack = inventory
+            .slots
+            .get(index)
+            .ok_or("Invalid inventory slot")?
+            .as_ref()
+            .ok_or("Inventory slot is empty")?;
+        let item = find_item(stack.item).ok_or("Unknown item")?;
+        let slots = item.kind.slots();
+        if slots.is_empty() {
+            return Err("Item cannot be equipped".to_string());
+        }
+
+        let mut equipment = self
+            .get::<Equipment>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        let slot = slots
+            .iter()
+            .copied()
+            .find(|slot| !equipment.slots.contains_key(slot))
+            .unwrap_or(slots[0]);
+        inventory.take(index, 1);
+        if let Some(previous) = equipment.slots.insert(slot, item.id) {
+            // Swap places with the worn item when the slot just emptied
+            if inventory.slots[index].is_none() {
+                inventory.slots[index] = Some(ItemStack {
+                    item: previous,
+                    count: 1,
+                });
+            } else if inventory.add(previous, 1) > 0 {
+                return Err("Inventory is full".to_string());
+            }
+        }
+        self.insert(entity_id, inventory);
+        self.insert(entity_id, equipment);
+        Ok(slot)
+    }
+
+    /// Take off the item in an equipment slot and put it in the inventory
+    pub fn unequip_item(&mut self, entity_id: u64, slot: EquipSlot) -> Result<(), String> {
+        let mut inventory = self
+            .get::<Inventory>(entity_id)
+            .ok_or("Entity has no inventory")?
+            .clone();
+        let mut equipment = self
+            .get::<Equipment>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        let item = equipment
+            .slots
+            .remove(&slot)
+            .ok_or("Nothing equipped in that slot")?;
+        if inventory.add(item, 1) > 0 {
+            return Err("Inventory is full".to_string());
+        }
+        self.insert(entity_id, inventory);
+        self.insert(entity_id, equipment);
+        Ok(())
+    }
+
+    /// Modifiers of everything an entity wears
+    pub fn equipment_modifiers(&self, id: u64) -> StatModifiers {
+        self.get::<Equipment>(id)
+            .map(Equipment::modifiers)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::prefabs;
+    use crate::map::Vec2;
+
+    #[test]
+    fn test_stacks_fill_move_and_split() {
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.add("health_potion", 14), 0);
+        assert_eq!(inventory.add("bone", 3), 0);
+        let counts: Vec<u32> = inventory.slots.iter().flatten().map(|s| s.count).collect();
+        assert_eq!(counts, vec![10, 4, 3]);
+
+        // Merging tops up the target and leaves the rest behind
+        inventory.split_stack(0, 5, 3).unwrap();
+        inventory.move_stack(5, 1).unwrap();
+        assert_eq!(inventory.slots[1].as_ref().unwrap().count, 7);
+        assert!(inventory.slots[5].is_none());
+        inventory.move_stack(0, 1).unwrap();
+        assert_eq!(inventory.slots[0].as_ref().unwrap().count, 4);
+        assert_eq!(inventory.slots[1].as_ref().unwrap().count, 10);
+
+        // Different items swap places
+        inventory.move_stack(2, 0).unwrap();
+        assert_eq!(inventory.slots[0].as_ref().unwrap().item, "bone");
+        assert_eq!(inventory.count("health_potion"), 14);
+
+        assert!(inventory.split_stack(0, 1, 1).is_err());
+        assert!(inventory.split_stack(0, 3, 3).is_err());
+
+        // Removing never takes part of what was asked for, and empties the last stacks first
+        assert!(!inventory.remove("health_potion", 15));
+        assert!(inventory.remove("health_potion", 12));
+        assert_eq!(inventory.count("health_potion"), 2);
+        assert!(inventory.slots[2].is_none());
+
+        // Whatever doesn't fit is handed back
+        let mut inventory = Inventory {
+            slots: vec![None; 2],
+        };
+        assert_eq!(inventory.add("iron_sword", 3), 1);
+    }
+
+    #[test]
+    fn test_equipment_feeds_effective_stats() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(Vec2 { x: 0.0, y: 0.0 }));
+        let base_damage = manager.get::<Combat>(player).unwrap().attack_damage;
+        let inventory = manager.get_mut::<Inventory>(player).unwrap();
+        for item in [
+            "iron_sword",
+            "lucky_charm",
+            "ring_of_swiftness",
+            "amulet_of_warding",
+        ] {
+            inventory.add(item, 1);
+        }
+
+        assert_eq!(manager.equip_item(player, 0), Ok(EquipSlot::Weapon));
+        assert_eq!(manager.equip_item(player, 1), Ok(EquipSlot::Trinket1));
+        assert_eq!(manager.equip_item(player, 2), Ok(EquipSlot::Trinket2));
+        let combat = manager.effective_combat(player).unwrap();
+        assert_eq!(combat.attack_damage, base_damage + 8);
+        assert_eq!(manager.effective_speed(player), 2.0 * 1.15);
+
+        // Both trinket slots are taken, so the amulet replaces the first trinket
+        assert_eq!(manager.equip_item(player, 3), Ok(EquipSlot::Trinket1));
+        let inventory = manager.get::<Inventory>(player).unwrap();
+        assert_eq!(inventory.slots[3].as_ref().unwrap().item, "lucky_charm");
+        assert_eq!(manager.effective_defense(player).resistances.fire, 15);
+
+        assert_eq!(
+            manager.equip_item(player, 5),
+            Err("Inventory slot is empty".to_string())
+        );
+        manager.unequip_item(player, EquipSlot::Weapon).unwrap();
+        assert_eq!(
+            manager.effective_combat(player).unwrap().attack_damage,
+            base_damage
+        );
+        assert_eq!(
+            manager.unequip_item(player, EquipSlot::Weapon),
+            Err("Nothing equipped in that slot".to_string())
+        );
+    }
+}
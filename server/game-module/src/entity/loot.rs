@@ -0,0 +1,382 @@
+//! Loot tables: weighted, nested rolls for what monsters and treasure leave behind.
+//!
+//! Every roll of a table picks one entry by weight. An entry drops nothing, a number of
+//! one item, any item of a rarity tier, or rolls another table.
+
+use super::components::*;
+use super::items::{Rarity, ALL_ITEMS};
+use super::{prefabs, EntityManager};
+use crate::combat::Dice;
+use crate::map::Vec2;
+
+/// Seconds dropped loot stays on the ground
+pub const LOOT_DESPAWN_TIME: f64 = 120.0;
+
+/// Tables rolling tables stop this deep, so a table naming itself cannot loop forever
+const MAX_NESTING: u32 = 4;
+
+/// How far apart the stacks of one drop are spread around the death position
+const DROP_SPREAD: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LootDrop {
+    Nothing,
+    Item {
+        item: &'static str,
+        min: u32,
+        max: u32,
+    },
+    AnyOf(Rarity), // One of any item of the tier
+    Table(&'static str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LootEntry {
+    pub weight: u32,
+    pub drop: LootDrop,
+}
+
+#[derive(Debug, Clone)]
+pub struct LootTable {
+    pub name: &'static str,
+    pub rolls: u32,
+    pub entries: &'static [LootEntry],
+}
+
+pub const GEAR: LootTable = LootTable {
+    name: "gear",
+    rolls: 1,
+    entries: &[
+        LootEntry {
+            weight: 60,
+            drop: LootDrop::AnyOf(Rarity::Common),
+        },
+        LootEntry {
+            weight: 28,
+            drop: LootDrop::AnyOf(Rarity::Uncommon),
+        },
+        LootEntry {
+            weight: 10,
+            drop: LootDrop::AnyOf(Rarity::Rare),
+        },
+        LootEntry {
+            weight: 2,
+            drop: LootDrop::AnyOf(Rarity::Epic),
+        },
+    ],
+};
+
+pub const BRUTE_LOOT: LootTable = LootTable {
+    name: "brute",
+    rolls: 1,
+    entries: &[
+        LootEntry {
+            weight: 40,
+            drop: LootDrop::Nothing,
+        },
+        LootEntry {
+            weight: 30,
+            drop: LootDrop::Item {
+                item: "bone",
+                min: 1,
+                max: 3,
+            },
+        },
+        LootEntry {
+            weight: 15,
+            drop: LootDrop::Item {
+                item: "health_potion",
+                min: 1,
+                max: 1,
+            },
+        },
+        LootEntry {
+            weight: 15,
+            drop: LootDrop::Table("gear"),
+        },
+    ],
+};
+
+pub const SKELETON_GUARD_LOOT: LootTable = LootTable {
+    name: "skeleton_guard",
+    rolls: 2,
+    entries: &[
+        LootEntry {
+            weight: 30,
+            drop: LootDrop::Nothing,
+        },
+        LootEntry {
+            weight: 40,
+            drop: LootDrop::Item {
+                item: "bone",
+                min: 2,
+                max: 5,
+            },
+        },
+        LootEntry {
+            weight: 10,
+            drop: LootDrop::Item {
+                item: "iron_ore",
+                min: 1,
+                max: 2,
+            },
+        },
+        LootEntry {
+            weight: 20,
+            drop: LootDrop::Table("gear"),
+        },
+    ],
+};
+
+pub const RAT_LOOT: LootTable = LootTable {
+    name: "rat",
+    rolls: 1,
+    entries: &[
+        LootEntry {
+            weight: 70,
+            drop: LootDrop::Nothing,
+        },
+        LootEntry {
+            weight: 30,
+            drop: LootDrop::Item {
+                item: "bone",
+                min: 1,
+                max: 1,
+            },
+        },
+    ],
+};
+
+pub const WOLF_LOOT: LootTable = LootTable {
+    name: "wolf",
+    rolls: 1,
+    entries: &[
+        LootEntry {
+            weight: 50,
+            drop: LootDrop::Nothing,
+        },
+        LootEntry {
+            weight: 40,
+            drop: LootDrop::Item {
+                item: "bone",
+                min: 1,
+                max: 2,
+            },
+        },
+        LootEntry {
+            weight: 10,
+            drop: LootDrop::AnyOf(Rarity::Uncommon),
+        },
+    ],
+};
+
+pub const TREASURE_ROOM_LOOT: LootTable = LootTable {
+    name: "treasure_room",
+    rolls: 3,
+    entries: &[
+        LootEntry {
+            weight: 45,
+            drop: LootDrop::Table("gear"),
+        },
+        LootEntry {
+            weight: 20,
+            drop: LootDrop::Item {
+                item: "health_potion",
+                min: 1,
+                max: 3,
+            },
+        },
+        LootEntry {
+            weight: 20,
+            drop: LootDrop::Item {
+                item: "mana_potion",
+                min: 1,
+                max: 3,
+            },
+        },
+        LootEntry {
+            weight: 15,
+            drop: LootDrop::AnyOf(Rarity::Rare),
+        },
+    ],
+};
+
+pub const ALL_LOOT_TABLES: &[LootTable] = &[
+    GEAR,
+    BRUTE_LOOT,
+    SKELETON_GUARD_LOOT,
+    RAT_LOOT,
+    WOLF_LOOT,
+    TREASURE_ROOM_LOOT,
+];
+
+/// Find a loot table by name
+pub fn find_loot_table(name: &str) -> Option<&'static LootTable> {
+    ALL_LOOT_TABLES.iter().find(|table| table.name == name)
+}
+
+/// Roll a table, merging drops of the same item into one stack
+pub fn roll_loot(table: &LootTable, dice: &mut Dice) -> Vec<ItemStack> {
+    let mut drops = Vec::new();
+    roll_into(table, dice, 0, &mut drops);
+    drops
+}
+
+fn roll_into(table: &LootTable, dice: &mut Dice, depth: u32, drops: &mut Vec<ItemStack>) {
+    let total: u32 = table.entries.iter().map(|entry| entry.weight).sum();
+    if total == 0 {
+        return;
+    }
+    for _ in 0..table.rolls {
+        let mut pick = dice.between(0, total - 1);
+        let Some(entry) = table.entries.iter().find(|entry| {
+            if pick < entry.weight {
+                return true;
+            }
+            pick -= entry.weight;
+            false
+        }) else {
+            continue;
+        };
+
+        match entry.drop {
+            LootDrop::Nothing => {}
+            LootDrop::Item { item, min, max } => add_drop(drops, item, dice.between(min, max)),
+            LootDrop::AnyOf(rarity) => {
+                let tier: Vec<&'static str> = ALL_ITEMS
+                    .iter()
+                    .filter(|item| item.rarity == rarity)
+                    .map(|item| item.id)
+                    .collect();
+                if !tier.is_empty() {
+                    let index = dice.between(0, tier.len() as u32 - 1) as usize;
+                    add_drop(drops, tier[index], 1);
+                }
+            }
+            LootDrop::Table(name) if depth < MAX_NESTING => {
+                if let Some(nested) = find_loot_table(name) {
+                    roll_into(nested, dice, depth + 1, drops);
+                }
+            }
+            LootDrop::Table(_) => {}
+        }
+    }
+}
+
+fn add_drop(drops: &mut Vec<ItemStack>, item: &'static str, count: u32) {
+    if count == 0 {
+        return;
+    }
+    match drops.iter_mut().find(|stack| stack.item == item) {
+        Some(stack) => stack.count += count,
+        None => drops.push(ItemStack { item, count }),
+    }
+}
+
+impl EntityManager {
+    /// Roll a table with the manager's dice
+    pub fn roll_loot(&mut self, table: &LootTable) -> Vec<ItemStack> {
+        roll_loot(table, &mut self.dice)
+    }
+
+    /// Spawn the loot of an entity with a `Loot` component around where it stands, as item
+    /// entities that despawn after `LOOT_DESPAWN_TIME`. Returns the new item entity IDs.
+    pub fn drop_loot(&mut self, id: u64, current_time: f64) -> Vec<u64> {
+        let (Some(loot), Some(position)) = (self.get::<Loot>(id), self.position(id)) else {
+            return Vec::new();
+        };
+        let Some(table) = find_loot_table(loot.table) else {
+            return Vec::new();
+        };
+        let position = position.clone();
+
+        let drops = self.roll_loot(table);
+        let count = drops.len();
+        drops
+            .into_iter()
+            .enumerate()
+            .map(|(index, stack)| {
+                let spot = if count == 1 {
+                    position.clone()
+                } else {
+                    let angle = std::f64::consts::TAU * index as f64 / count as f64;
+                    Vec2 {
+                        x: position.x + DROP_SPREAD * angle.cos(),
+                        y: position.y + DROP_SPREAD * angle.sin(),
+                    }
+                };
+                self.spawn(prefabs::item(spot, stack).with(Lifetime {
+                    expires_at: current_time + LOOT_DESPAWN_TIME,
+                }))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::items::find_item;
+
+    #[test]
+    fn test_rolls_are_seeded_and_nested() {
+        let roll = |seed| {
+            let mut dice = Dice::seeded(seed);
+            (0..20)
+                .flat_map(|_| roll_loot(&TREASURE_ROOM_LOOT, &mut dice))
+                .collect::<Vec<ItemStack>>()
+        };
+        assert_eq!(roll(7), roll(7));
+
+        // Gear only comes in through the nested table and the rare tier
+        let drops = roll(7);
+        assert!(drops
+            .iter()
+            .any(|stack| find_item(stack.item).is_some_and(|item| !item.kind.slots().is_empty())));
+        for stack in &drops {
+            assert!(find_item(stack.item).is_some());
+        }
+
+        // Every table only names items and tables that exist
+        for table in ALL_LOOT_TABLES {
+            for entry in table.entries {
+                match entry.drop {
+                    LootDrop::Item { item, min, max } => {
+                        assert!(find_item(item).is_some() && min <= max)
+                    }
+                    LootDrop::Table(name) => assert!(find_loot_table(name).is_some()),
+                    LootDrop::Nothing | LootDrop::AnyOf(_) => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_monsters_drop_loot_on_death() {
+        let mut manager = EntityManager::new();
+        manager.set_seed(3);
+        let position = Vec2 { x: 4.0, y: 4.0 };
+        let mut dropped = 0;
+        for _ in 0..20 {
+            let monster = manager.spawn(prefabs::monster(position.clone()));
+            manager.get_mut::<Health>(monster).unwrap().current = 0;
+            let before = manager.store::<ItemStack>().len();
+            assert_eq!(manager.cleanup_dead_entities(10.0), vec![monster]);
+            dropped += manager.store::<ItemStack>().len() - before;
+        }
+        assert!(dropped > 0);
+        for (id, _) in manager.store::<ItemStack>().iter() {
+            let transform = manager.get::<Transform>(id).unwrap();
+            assert!(transform.distance_to(&position) <= DROP_SPREAD + 1e-9);
+            assert_eq!(
+                manager.get::<Lifetime>(id).unwrap().expires_at,
+                10.0 + LOOT_DESPAWN_TIME
+            );
+        }
+
+        // Summons fight for someone and leave nothing behind
+        let summon = manager.spawn(prefabs::summon(position, 1, Faction::Players, 60.0));
+        assert!(!manager.has::<Loot>(summon));
+        assert_eq!(manager.cleanup_expired_entities(200.0).len(), dropped + 1);
+    }
+}
@@ -0,0 +1,1173 @@
+pub mod abilities;
+pub mod behavior;
+pub mod components;
+pub mod death;
+pub mod dialogue;
+pub mod effects;
+pub mod items;
+pub mod loot;
+pub mod prefabs;
+pub mod progression;
+pub mod quests;
+pub mod shops;
+pub mod spatial;
+mod systems;
+
+pub use components::*;
+pub use dialogue::DialogueNode;
+pub use effects::{EffectKind, StatusEffect, StatusEffects};
+pub use progression::LevelUp;
+pub use quests::QuestEvent;
+pub use spatial::SpatialGrid;
+
+use crate::collision;
+use crate::combat::{self, DamageBreakdown, Dice};
+use crate::fov::has_line_of_sight;
+use crate::map::{Map, Vec2};
+use crate::pathfinding::{tile_of, PathCache, PathParams};
+use std::any::{Any, TypeId};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Sparse storage for one component type, keyed by entity ID
+#[derive(Debug)]
+pub struct ComponentStore<T> {
+    items: BTreeMap<u64, T>,
+}
+
+impl<T> Default for ComponentStore<T> {
+    fn default() -> Self {
+        Self {
+            items: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T> ComponentStore<T> {
+    pub fn get(&self, id: u64) -> Option<&T> {
+        self.items.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut T> {
+        self.items.get_mut(&id)
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.items.contains_key(&id)
+    }
+
+    /// Iterate over every entity that has this component, in ID order
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &T)> {
+        self.items.iter().map(|(&id, component)| (id, component))
+    }
+
+    pub fn ids(&self) -> Vec<u64> {
+        self.items.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    fn insert(&mut self, id: u64, component: T) {
+        self.items.insert(id, component);
+    }
+
+    fn remove(&mut self, id: u64) -> Option<T> {
+        self.items.remove(&id)
+    }
+}
+
+/// Data that can be attached to an entity
+pub trait Component: Sized + 'static {
+    fn store(manager: &EntityManager) -> &ComponentStore<Self>;
+    fn store_mut(manager: &mut EntityManager) -> &mut ComponentStore<Self>;
+}
+
+macro_rules! components {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        /// One sparse store per component type
+        #[derive(Default)]
+        struct Stores {
+            $($field: ComponentStore<$ty>,)*
+        }
+
+        impl Stores {
+            fn remove_all(&mut self, id: u64) {
+                $(self.$field.remove(id);)*
+            }
+
+            fn rekey(&mut self, from: u64, to: u64) {
+                $(if let Some(component) = self.$field.remove(from) {
+                    self.$field.insert(to, component);
+                })*
+            }
+        }
+
+        $(impl Component for $ty {
+            fn store(manager: &EntityManager) -> &ComponentStore<Self> {
+                &manager.stores.$field
+            }
+
+            fn store_mut(manager: &mut EntityManager) -> &mut ComponentStore<Self> {
+                &mut manager.stores.$field
+            }
+        })*
+    };
+}
+
+components! {
+    transforms: Transform,
+    colliders: Collider,
+    states: EntityState,
+    factions: Faction,
+    health: Health,
+    combat: Combat,
+    defenses: Defense,
+    movement: Movement,
+    senses: Senses,
+    resources: Resources,
+    abilities: Abilities,
+    castings: Casting,
+    inventories: Inventory,
+    equipment: Equipment,
+    item_stacks: ItemStack,
+    ai: Ai,
+    status_effects: StatusEffects,
+    projectiles: Projectile,
+    loot: Loot,
+    monster_kinds: MonsterKind,
+    experience: Experience,
+    experience_rewards: ExperienceReward,
+    damage_taken: DamageTaken,
+    owners: Owner,
+    lifetimes: Lifetime,
+    pickups: Pickup,
+    talkables: Talkable,
+    wallets: Wallet,
+    vendors: Vendor,
+    conversations: Conversation,
+    quest_logs: QuestLog,
+    respawns: Respawns,
+    deaths: Death,
+    gravestones: Gravestone,
+}
+
+type ComponentInsert = Box<dyn FnOnce(&mut EntityManager, u64)>;
+
+/// Collects the components of an entity before it is spawned
+#[derive(Default)]
+pub struct EntityBuilder {
+    inserts: Vec<ComponentInsert>,
+}
+
+impl EntityBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a component, replacing any earlier component of the same type
+    pub fn with<C: Component>(mut self, component: C) -> Self {
+        self.inserts
+            .push(Box::new(move |manager, id| manager.insert(id, component)));
+        self
+    }
+
+    /// Leave out a component an earlier `with` attached
+    pub fn without<C: Component>(mut self) -> Self {
+        self.inserts.push(Box::new(|manager, id| {
+            manager.remove_component::<C>(id);
+        }));
+        self
+    }
+}
+
+/// Entity manager for handling game logic
+#[derive(Default)]
+pub struct EntityManager {
+    entities: BTreeSet<u64>,
+    next_id: u64,
+    stores: Stores,
+    grid: SpatialGrid, // Index over `Transform` positions
+    navigation: Option<Navigation>,
+    dice: Dice,
+    combat_events: Vec<CombatEvent>,
+    level_ups: Vec<LevelUp>,
+}
+
+/// Map the entities walk on, so AI can path around walls
+pub struct Navigation {
+    pub map: Map,
+    pub params: PathParams,
+    pub paths: PathCache,
+}
+
+impl EntityManager {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Create a manager whose spatial index uses cells of `cell_size` tiles
+    pub fn with_cell_size(cell_size: f64) -> Self {
+        Self {
+            grid: SpatialGrid::new(cell_size),
+            ..Self::new()
+        }
+    }
+
+    /// Let AI walk on `map`, continuing the paths in `paths`. Without a map entities move in
+    /// straight lines.
+    pub fn set_navigation(&mut self, map: Map, params: PathParams, paths: PathCache) {
+        self.navigation = Some(Navigation { map, params, paths });
+    }
+
+    /// Take the path cache back out, e.g. to reuse it next tick
+    pub fn take_path_cache(&mut self) -> Option<PathCache> {
+        let mut paths = self.navigation.take()?.paths;
+        paths.retain(|id| self.contains(id));
+        Some(paths)
+    }
+
+    /// Seed the combat rolls, e.g. from the current time
+    pub fn set_seed(&mut self, seed: u64) {
+        self.dice = Dice::seeded(seed);
+    }
+
+    /// Take the attacks that happened since the last call, oldest first
+    pub fn take_combat_events(&mut self) -> Vec<CombatEvent> {
+        std::mem::take(&mut self.combat_events)
+    }
+
+    /// Keep an attack for the caller, crediting its damage toward the target's experience split
+    fn record_combat_event(&mut self, event: CombatEvent) {
+        self.record_damage(
+            event.attacker_id,
+            event.target_id,
+            event.result.breakdown.damage_dealt,
+        );
+        self.combat_events.push(event);
+    }
+
+    /// Spawn an entity with a fresh ID
+    pub fn spawn(&mut self, builder: EntityBuilder) -> u64 {
+        let id = self.next_id.max(1);
+        self.spawn_with_id(id, builder)
+    }
+
+    /// Hand out fresh IDs from `id` on, e.g. a range the database never assigns
+    pub fn set_next_id(&mut self, id: u64) {
+        self.next_id = id;
+    }
+
+    /// Spawn an entity with a known ID, e.g. one loaded from the database
+    pub fn spawn_with_id(&mut self, id: u64, builder: EntityBuilder) -> u64 {
+        self.entities.insert(id);
+        self.next_id = self.next_id.max(id + 1);
+        for insert in builder.inserts {
+            insert(self, id);
+        }
+        id
+    }
+
+    /// Remove an entity and all of its components
+    pub fn remove_entity(&mut self, id: u64) -> bool {
+        self.stores.remove_all(id);
+        self.grid.remove(id);
+        self.entities.remove(&id)
+    }
+
+    /// Give an entity a new ID, e.g. the one the database assigned to an entity spawned by
+    /// the game logic. Fails when the new ID is taken.
+    pub fn rekey_entity(&mut self, from: u64, to: u64) -> Result<(), String> {
+        if !self.contains(from) {
+            return Err("Entity not found".to_string());
+        }
+        if self.contains(to) {
+            return Err("Entity ID is already in use".to_string());
+        }
+        self.entities.remove(&from);
+        self.entities.insert(to);
+        self.next_id = self.next_id.max(to.saturating_add(1));
+        self.stores.rekey(from, to);
+        self.grid.remove(from);
+        if let Some(position) = self.position(to).cloned() {
+            self.grid.insert(to, &position);
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.entities.contains(&id)
+    }
+
+    /// IDs of every entity, in ascending order
+    pub fn entity_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.entities.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Storage of one component type, for systems that iterate over it
+    pub fn store<C: Component>(&self) -> &ComponentStore<C> {
+        C::store(self)
+    }
+
+    pub fn get<C: Component>(&self, id: u64) -> Option<&C> {
+        C::store(self).get(id)
+    }
+
+    pub fn get_mut<C: Component>(&mut self, id: u64) -> Option<&mut C> {
+        C::store_mut(self).get_mut(id)
+    }
+
+    pub fn has<C: Component>(&self, id: u64) -> bool {
+        C::store(self).contains(id)
+    }
+
+    /// Attach a component to an existing entity, replacing the previous one
+    pub fn insert<C: Component>(&mut self, id: u64, component: C) {
+        if !self.contains(id) {
+            return;
+        }
+        if let Some(transform) = (&component as &dyn Any).downcast_ref::<Transform>() {
+            self.grid.insert(id, &transform.position);
+        }
+        C::store_mut(self).insert(id, component);
+    }
+
+    pub fn remove_component<C: Component>(&mut self, id: u64) -> Option<C> {
+        let component = C::store_mut(self).remove(id);
+        if TypeId::of::<C>() == TypeId::of::<Transform>() {
+            self.grid.remove(id);
+        }
+        component
+    }
+
+    /// Move an entity without any checks, keeping the spatial index in sync
+    pub fn set_position(&mut self, id: u64, position: Vec2) {
+        if let Some(transform) = self.get_mut::<Transform>(id) {
+            transform.position = position.clone();
+            self.grid.insert(id, &position);
+        }
+    }
+
+    pub fn position(&self, id: u64) -> Option<&Vec2> {
+        self.get::<Transform>(id)
+            .map(|transform| &transform.position)
+    }
+
+    /// Entities without health cannot die and are always alive
+    pub fn is_alive(&self, id: u64) -> bool {
+        match self.get::<Health>(id) {
+            Some(health) => health.is_alive(),
+            None => self.contains(id),
+        }
+    }
+
+    /// Distance between two entities that both have a position
+    pub fn distance(&self, a: u64, b: u64) -> Option<f64> {
+        let b = self.position(b)?;
+        Some(self.get::<Transform>(a)?.distance_to(b))
+    }
+
+    /// Whether entity `a` can see entity `b`. Without a navigation map nothing blocks sight.
+    pub fn can_see(&self, a: u64, b: u64) -> bool {
+        let (Some(from), Some(to)) = (self.position(a), self.position(b)) else {
+            return false;
+        };
+        self.clear_line(from, to)
+    }
+
+    /// Whether nothing on the navigation map blocks the view between two points
+    fn clear_line(&self, from: &Vec2, to: &Vec2) -> bool {
+        let Some(navigation) = &self.navigation else {
+            return true;
+        };
+        match (tile_of(from), tile_of(to)) {
+            (Some(from), Some(to)) => has_line_of_sight(&navigation.map, from, to),
+            _ => false,
+        }
+    }
+
+    /// Movement speed after equipment, slows, hastes, stuns and roots
+    pub fn effective_speed(&self, id: u64) -> f64 {
+        let speed = self
+            .get::<Movement>(id)
+            .map_or(0.0, |movement| movement.speed);
+        let equipment = (1.0 + self.equipment_modifiers(id).move_speed).max(0.0);
+        let multiplier = self
+            .get::<StatusEffects>(id)
+            .map_or(1.0, |effects| effects.speed_multiplier());
+        speed * equipment * multiplier
+    }
+
+    /// Combat stats with the modifiers of worn equipment added
+    pub fn effective_combat(&self, id: u64) -> Option<Combat> {
+        let mut combat = self.get::<Combat>(id)?.clone();
+        let modifiers = self.equipment_modifiers(id);
+        combat.attack_damage = combat
+            .attack_damage
+            .saturating_add_signed(modifiers.attack_damage);
+        combat.accuracy += modifiers.accuracy;
+        combat.crit_chance += modifiers.crit_chance;
+        Some(combat)
+    }
+
+    /// Evasion and resistances with the modifiers of worn equipment added
+    pub fn effective_defense(&self, id: u64) -> Defense {
+        let mut defense = self.get::<Defense>(id).cloned().unwrap_or_default();
+        let modifiers = self.equipment_modifiers(id);
+        defense.evasion += modifiers.evasion;
+        defense.resistances.physical += modifiers.resistances.physical;
+        defense.resistances.fire += modifiers.resistances.fire;
+        defense.resistances.ice += modifiers.resistances.ice;
+        defense.resistances.poison += modifiers.resistances.poison;
+        defense
+    }
+
+    /// Put a status effect on a living entity, following the stacking rule of its kind
+    pub fn apply_effect(&mut self, target_id: u64, effect: StatusEffect) -> Result<(), String> {
+        if !self.contains(target_id) {
+            return Err("Target not found".to_string());
+        }
+        if !self.is_alive(target_id) {
+            return Err("Target is already dead".to_string());
+        }
+        match self.get_mut::<StatusEffects>(target_id) {
+            Some(effects) => effects.apply(effect),
+            None => {
+                let mut effects = StatusEffects::default();
+                effects.apply(effect);
+                self.insert(target_id, effects);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get entities within a certain range of a position
+    pub fn get_entities_in_range(&self, position: &Vec2, range: f64) -> Vec<u64> {
+        self.grid.query_radius(position, range)
+    }
+
+    /// Get entities inside a rectangle
+    pub fn get_entities_in_rect(&self, min: &Vec2, max: &Vec2) -> Vec<u64> {
+        self.grid.query_rect(min, max)
+    }
+
+    /// Up to `k` entities accepted by `filter`, nearest first, with their distances
+    pub fn nearest_entities(
+        &self,
+        position: &Vec2,
+        k: usize,
+        max_distance: f64,
+        filter: impl Fn(u64) -> bool,
+    ) -> Vec<(u64, f64)> {
+        self.grid.k_nearest(position, k, max_distance, filter)
+    }
+
+    /// Walk an entity towards `new_position`, stopping at walls and blocking entities and
+    /// sliding along them. Uses the navigation map when no map is given.
+    /// Returns where the entity ended up.
+    pub fn move_entity(
+        &mut self,
+        id: u64,
+        new_position: Vec2,
+        map: Option<&Map>,
+    ) -> Result<Vec2, String> {
+        if !self.has::<Transform>(id) {
+            return Err("Entity not found".to_string());
+        }
+        if !self.is_alive(id) {
+            return Err("Cannot move dead entity".to_string());
+        }
+        if let Some(effects) = self.get::<StatusEffects>(id) {
+            if !effects.can_act() {
+                return Err("Entity is stunned".to_string());
+            }
+            if !effects.can_move() {
+                return Err("Entity is rooted".to_string());
+            }
+        }
+
+        // Walking away interrupts whatever the entity was casting
+        self.remove_component::<Casting>(id);
+        let map = map.or(self.navigation.as_ref().map(|navigation| &navigation.map));
+        let position = self.swept_position(id, &new_position, map);
+        self.set_position(id, position.clone());
+        self.insert(id, EntityState::Moving);
+        Ok(position)
+    }
+
+    /// How far an entity gets on its way to `to` before walls or other bodies stop it
+    fn swept_position(&self, id: u64, to: &Vec2, map: Option<&Map>) -> Vec2 {
+        let Some(from) = self.position(id) else {
+            return to.clone();
+        };
+        let collider = self.get::<Collider>(id);
+        let radius = collider.map_or(0.0, |collider| collider.radius);
+        let blocks_entities = collider.is_some_and(|collider| collider.blocks_entities);
+
+        collision::sweep(from, to, |current, next| {
+            if let Some(map) = map {
+                if collision::circle_hits_wall(map, next, radius)
+                    && !collision::circle_hits_wall(map, current, radius)
+                {
+                    return true;
+                }
+            }
+            blocks_entities && self.body_in_the_way(id, radius, current, next)
+        })
+    }
+
+    /// Whether stepping from `current` to `next` pushes further into another blocking body.
+    /// Overlapping bodies may always move apart.
+    fn body_in_the_way(&self, id: u64, radius: f64, current: &Vec2, next: &Vec2) -> bool {
+        self.grid
+            .query_radius(next, radius + MAX_COLLIDER_RADIUS)
+            .into_iter()
+            .filter(|&other| other != id && self.is_alive(other))
+            .any(|other| {
+                let Some(collider) = self.get::<Collider>(other) else {
+                    return false;
+                };
+                let Some(transform) = self.get::<Transform>(other) else {
+                    return false;
+                };
+                let reach = radius + collider.radius.min(MAX_COLLIDER_RADIUS);
+                let distance = transform.distance_to(next);
+                collider.blocks_entities
+                    && distance < reach
+                    && distance < transform.distance_to(current)
+            })
+    }
+
+    /// Attack one entity with another
+    pub fn attack_entity(
+        &mut self,
+        attacker_id: u64,
+        target_id: u64,
+        current_time: f64,
+    ) -> Result<AttackResult, String> {
+        // Check if attacker exists and can attack
+        let strike = {
+            let combat = self
+                .effective_combat(attacker_id)
+                .ok_or("Attacker has no combat stats")?;
+            if !combat.can_attack(current_time) {
+                return Err("Attacker is on cooldown".to_string());
+            }
+            combat.strike()
+        };
+        if !self.is_alive(attacker_id) {
+            return Err("Attacker is dead".to_string());
+        }
+        if self
+            .get::<StatusEffects>(attacker_id)
+            .is_some_and(|effects| !effects.can_act())
+        {
+            return Err("Attacker is stunned".to_string());
+        }
+
+        // Check if target exists and can be damaged
+        let health = self
+            .get::<Health>(target_id)
+            .ok_or("Target cannot be damaged")?;
+        if !health.is_alive() {
+            return Err("Target is already dead".to_string());
+        }
+        let target_position = self.position(target_id).ok_or("Target not found")?.clone();
+
+        // Verify range
+        let distance = self
+            .distance(attacker_id, target_id)
+            .ok_or("Attacker not found")?;
+        if distance > self.get::<Combat>(attacker_id).unwrap().attack_range {
+            return Err("Target is out of range".to_string());
+        }
+        if !self.can_see(attacker_id, target_id) {
+            return Err("Target is not in line of sight".to_string());
+        }
+
+        // Update attacker's last attack time
+        if let Some(combat) = self.get_mut::<Combat>(attacker_id) {
+            combat.last_attack_time = current_time;
+        }
+        self.insert(attacker_id, EntityState::Attacking);
+
+        // Roll the attack against the target's evasion and resistances
+        let defense = self.effective_defense(target_id);
+        let breakdown = combat::resolve(
+            &strike,
+            defense.evasion,
+            &defense.resistances,
+            &mut self.dice,
+        );
+        let target_died = self
+            .get_mut::<Health>(target_id)
+            .is_some_and(|health| health.take_damage(breakdown.damage_dealt));
+        if target_died {
+            self.insert(target_id, EntityState::Dead);
+        } else if let Some(effect) = effects::on_hit_effect(&breakdown, attacker_id, current_time) {
+            let _ = self.apply_effect(target_id, effect);
+        }
+
+        let result = AttackResult {
+            breakdown,
+            target_died,
+            target_position,
+        };
+        self.record_combat_event(CombatEvent {
+            attacker_id,
+            target_id,
+            result: result.clone(),
+        });
+        Ok(result)
+    }
+
+    /// Fail with `too_far` unless two entities are within a range of each other
+    pub fn check_reach(
+        &self,
+        entity_id: u64,
+        target_id: u64,
+        range: f64,
+        too_far: &str,
+    ) -> Result<(), String> {
+        let distance = self
+            .distance(entity_id, target_id)
+            .ok_or("Entity not found")?;
+        if distance > range {
+            return Err(too_far.to_string());
+        }
+        Ok(())
+    }
+
+    /// Handle entity interactions (e.g., picking up items)
+    pub fn interact_entities(
+        &mut self,
+        entity_id: u64,
+        target_id: u64,
+    ) -> Result<InteractionResult, String> {
+        if self.has::<Pickup>(target_id) && self.has::<Inventory>(entity_id) {
+            // Picking up an item
+            self.check_reach(
+                entity_id,
+                target_id,
+                items::PICKUP_RANGE,
+                "Too far from item",
+            )?;
+            let stack = self
+                .get::<ItemStack>(target_id)
+                .cloned()
+                .ok_or("Nothing to pick up")?;
+            let inventory = self.get_mut::<Inventory>(entity_id).unwrap();
+            let left = inventory.add(stack.item, stack.count);
+            if left == stack.count {
+                return Err("Inventory is full".to_string());
+            }
+            self.record_quest_event(
+                entity_id,
+                QuestEvent::Collected {
+                    item: stack.item,
+                    count: stack.count - left,
+                },
+            );
+            // Whatever did not fit stays on the ground
+            if left > 0 {
+                self.get_mut::<ItemStack>(target_id).unwrap().count = left;
+            } else {
+                self.remove_entity(target_id);
+            }
+            Ok(InteractionResult::ItemPickedUp)
+        } else if self.has::<Talkable>(target_id) {
+            // Talking to an NPC
+            self.check_reach(
+                entity_id,
+                target_id,
+                dialogue::TALK_RANGE,
+                "Too far from NPC",
+            )?;
+            let node = self.start_conversation(entity_id, target_id)?;
+            Ok(InteractionResult::NPCInteraction { node })
+        } else {
+            Err("Invalid interaction".to_string())
+        }
+    }
+
+    /// Run every system once
+    pub fn update_entities(&mut self, delta_time: f64, current_time: f64) {
+        systems::reset_states(self);
+        systems::process_effects(self, current_time);
+        systems::regenerate_resources(self, delta_time);
+        systems::process_casts(self, current_time);
+        systems::run_ai(self, delta_time, current_time);
+        systems::advance_projectiles(self, delta_time, current_time);
+        self.restock_vendors(current_time);
+    }
+
+    /// Remove all dead entities, sharing out their experience and leaving their loot behind.
+    /// Respawning entities stay and take their death penalty instead. Returns the removed IDs.
+    pub fn cleanup_dead_entities(&mut self, current_time: f64) -> Vec<u64> {
+        let dead_entity_ids: Vec<u64> = self
+            .store::<Health>()
+            .iter()
+            .filter(|(_, health)| !health.is_alive())
+            .map(|(id, _)| id)
+            .collect();
+
+        let mut removed_ids = Vec::new();
+        for id in dead_entity_ids {
+            if self.has::<Respawns>(id) {
+                if !self.has::<Death>(id) {
+                    self.die(id, current_time);
+                }
+                continue;
+            }
+            self.record_kill(id);
+            self.reward_experience(id);
+            self.drop_loot(id, current_time);
+            self.remove_entity(id);
+            removed_ids.push(id);
+        }
+
+        removed_ids
+    }
+
+    /// Remove all entities whose lifetime has run out
+    pub fn cleanup_expired_entities(&mut self, current_time: f64) -> Vec<u64> {
+        let expired_ids: Vec<u64> = self
+            .store::<Lifetime>()
+            .iter()
+            .filter(|(_, lifetime)| lifetime.expires_at <= current_time)
+            .map(|(id, _)| id)
+            .collect();
+
+        for &id in &expired_ids {
+            self.remove_entity(id);
+        }
+
+        expired_ids
+    }
+}
+
+/// Result of an attack action
+#[derive(Clone, Debug)]
+pub struct AttackResult {
+    pub breakdown: DamageBreakdown,
+    pub target_died: bool,
+    pub target_position: Vec2,
+}
+
+/// An attack that happened, kept until the caller takes it to tell clients
+#[derive(Clone, Debug)]
+pub struct CombatEvent {
+    pub attacker_id: u64,
+    pub target_id: u64,
+    pub result: AttackResult,
+}
+
+/// Result of an interaction action
+#[derive(Clone, Debug)]
+pub enum InteractionResult {
+    ItemPickedUp,
+    NPCInteraction { node: &'static DialogueNode }, // Where the conversation starts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    #[test]
+    fn test_entity_creation() {
+        let mut manager = EntityManager::new();
+        let id = manager.spawn(prefabs::player(at(0.0, 0.0)));
+
+        assert_eq!(id, 1);
+        assert!(manager.is_alive(id));
+        assert_eq!(manager.get::<Faction>(id), Some(&Faction::Players));
+        assert!(!manager.has::<Ai>(id));
+    }
+
+    #[test]
+    fn test_entity_damage() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(0.0, 0.0)));
+        let monster = manager.spawn(prefabs::monster(at(1.0, 0.0)));
+
+        let result = manager.attack_entity(monster, player, 10.0).unwrap();
+        assert_eq!(result.breakdown.damage_dealt, 15);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 85);
+
+        // Still cooling down
+        assert!(manager.attack_entity(monster, player, 10.5).is_err());
+
+        manager.get_mut::<Health>(player).unwrap().current = 10;
+        let result = manager.attack_entity(monster, player, 12.0).unwrap();
+        assert!(result.target_died);
+        assert_eq!(manager.get::<EntityState>(player), Some(&EntityState::Dead));
+        // Players stay where they fell until they respawn
+        assert!(manager.cleanup_dead_entities(12.0).is_empty());
+        assert!(manager.has::<Death>(player));
+        assert!(manager.attack_entity(player, monster, 13.0).is_err());
+    }
+
+    #[test]
+    fn test_attacks_report_the_breakdown() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(0.0, 0.0)).with(Defense {
+            evasion: 0.0,
+            resistances: crate::combat::Resistances {
+                physical: 50,
+                ..Default::default()
+            },
+        }));
+        let monster = manager.spawn(prefabs::monster(at(1.0, 0.0)));
+        let combat = manager.get_mut::<Combat>(monster).unwrap();
+        combat.accuracy = 1.0;
+        combat.crit_chance = 0.0;
+
+        // Half of the 15 damage is absorbed by the player's armour
+        let result = manager.attack_entity(monster, player, 10.0).unwrap();
+        assert_eq!(result.breakdown.outcome, crate::combat::HitOutcome::Hit);
+        assert_eq!(result.breakdown.raw_damage, 15);
+        assert_eq!(result.breakdown.damage_dealt, 8);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 92);
+
+        let events = manager.take_combat_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            (events[0].attacker_id, events[0].target_id),
+            (monster, player)
+        );
+        assert!(manager.take_combat_events().is_empty());
+    }
+
+    #[test]
+    fn test_status_effects_tick_and_expire() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(0.0, 0.0)));
+        let monster = manager.spawn(prefabs::monster(at(1.0, 0.0)));
+        manager.remove_component::<Ai>(monster);
+        let combat = manager.get_mut::<Combat>(monster).unwrap();
+        combat.damage_type = crate::combat::DamageType::Poison;
+        combat.accuracy = 1.0;
+        combat.crit_chance = 0.0;
+
+        // The hit leaves 3 poison damage per second for 5 seconds
+        manager.attack_entity(monster, player, 10.0).unwrap();
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 85);
+        manager.update_entities(0.0, 12.0);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 79);
+        let events = manager.take_combat_events();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|event| event.attacker_id == monster));
+
+        manager.update_entities(0.0, 16.0);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 70);
+        assert!(!manager.has::<StatusEffects>(player));
+
+        // Stunned entities can't do anything, rooted ones can't walk
+        let stun = StatusEffect::new(EffectKind::Stun, Some(player), 0.0, 2.0, 16.0);
+        manager.apply_effect(monster, stun).unwrap();
+        assert!(manager.attack_entity(monster, player, 17.0).is_err());
+        assert!(manager.move_entity(monster, at(2.0, 0.0), None).is_err());
+
+        let root = StatusEffect::new(EffectKind::Root, None, 0.0, 2.0, 16.0);
+        manager.apply_effect(player, root).unwrap();
+        assert_eq!(manager.effective_speed(player), 0.0);
+        assert!(manager.move_entity(player, at(-1.0, 0.0), None).is_err());
+        manager.update_entities(0.0, 19.0);
+        assert!(manager.move_entity(player, at(-1.0, 0.0), None).is_ok());
+    }
+
+    #[test]
+    fn test_projectiles_hit_and_pierce() {
+        use abilities::AbilityOutcome;
+
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(1.0, 1.0)));
+        manager.insert(player, Abilities::new(&["arrow", "fire_bolt"]));
+        let combat = manager.get_mut::<Combat>(player).unwrap();
+        combat.accuracy = 1.0;
+        combat.crit_chance = 0.0;
+        let monsters: Vec<u64> = [4.0, 6.0, 8.0]
+            .into_iter()
+            .map(|x| {
+                let id = manager.spawn(prefabs::monster(at(x, 1.0)));
+                manager.remove_component::<Ai>(id);
+                id
+            })
+            .collect();
+        let health = |manager: &EntityManager| -> Vec<u32> {
+            monsters
+                .iter()
+                .map(|&id| manager.get::<Health>(id).unwrap().current)
+                .collect()
+        };
+
+        // Arrows stop in the first body they hit
+        let outcome = manager
+            .use_ability(player, "arrow", AbilityTarget::Point(at(8.0, 1.0)), 10.0)
+            .unwrap();
+        let AbilityOutcome::Launched { projectile_id } = outcome else {
+            panic!("Expected a projectile, got {:?}", outcome);
+        };
+        manager.update_entities(0.5, 10.5);
+        assert_eq!(health(&manager), vec![38, 50, 50]);
+        assert_eq!(manager.cleanup_expired_entities(10.5), vec![projectile_id]);
+
+        // Fire bolts go through two bodies and stop in the third
+        manager
+            .use_ability(
+                player,
+                "fire_bolt",
+                AbilityTarget::Entity(monsters[2]),
+                20.0,
+            )
+            .unwrap();
+        manager.update_entities(0.0, 20.5);
+        manager.update_entities(1.0, 21.5);
+        assert_eq!(health(&manager), vec![23, 35, 35]);
+        assert_eq!(manager.cleanup_expired_entities(21.5).len(), 1);
+        assert!(manager
+            .take_combat_events()
+            .iter()
+            .all(|event| event.attacker_id == player));
+    }
+
+    #[test]
+    fn test_projectiles_stop_at_walls() {
+        use abilities::AbilityOutcome;
+
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(1.0, 1.0)));
+        let monster = manager.spawn(prefabs::monster(at(5.0, 1.0)));
+        manager.remove_component::<Ai>(monster);
+        manager.set_navigation(
+            crate::pathfinding::tests::map_from(&[
+                "#######", //
+                "#..#..#", //
+                "#######",
+            ]),
+            Default::default(),
+            Default::default(),
+        );
+
+        let outcome = manager
+            .use_ability(player, "arrow", AbilityTarget::Point(at(5.0, 1.0)), 10.0)
+            .unwrap();
+        let AbilityOutcome::Launched { projectile_id } = outcome else {
+            panic!("Expected a projectile, got {:?}", outcome);
+        };
+        // The database may hand out a different ID for the new entity
+        manager.rekey_entity(projectile_id, 1000).unwrap();
+        assert!(!manager.contains(projectile_id));
+
+        manager.update_entities(1.0, 11.0);
+        assert!(manager.position(1000).unwrap().x < 2.5);
+        assert_eq!(manager.get::<Health>(monster).unwrap().current, 50);
+        assert_eq!(manager.cleanup_expired_entities(11.0), vec![1000]);
+    }
+
+    #[test]
+    fn test_monster_moves_by_delta_time() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(0.0, 0.0)));
+        let monster = manager.spawn(prefabs::monster(at(5.0, 0.0)));
+
+        // Monsters move at 1.5 tiles per second
+        manager.update_entities(0.5, 10.0);
+        assert!((manager.position(monster).unwrap().x - 4.25).abs() < 1e-9);
+        assert_eq!(
+            manager.get::<Ai>(monster).unwrap().target_entity_id,
+            Some(player)
+        );
+
+        // A long frame stops at attack range instead of walking through the player
+        manager.update_entities(10.0, 20.0);
+        assert!((manager.position(monster).unwrap().x - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_new_kinds_are_compositions() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(0.0, 0.0)));
+        let monster = manager.spawn(prefabs::monster(at(3.0, 0.0)));
+        let summon = manager.spawn(prefabs::summon(at(2.0, 0.0), player, Faction::Players, 5.0));
+        let trap = manager.spawn(prefabs::trap(at(0.0, 0.0), 7));
+
+        // The summon hunts the monster instead of its owner
+        manager.update_entities(0.1, 1.0);
+        assert_eq!(
+            manager.get::<Ai>(summon).unwrap().target_entity_id,
+            Some(monster)
+        );
+        assert_eq!(manager.get::<Owner>(summon).unwrap().entity_id, player);
+
+        // The trap hurts the player standing on it but cannot be hurt back
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 93);
+        assert!(manager.attack_entity(player, trap, 1.0).is_err());
+
+        assert_eq!(manager.cleanup_expired_entities(5.0), vec![summon]);
+        assert!(!manager.contains(summon));
+    }
+
+    #[test]
+    fn test_monsters_path_around_walls() {
+        let map = crate::pathfinding::tests::map_from(&[
+            "#########", //
+            "#.......#", //
+            "#.#####.#", //
+            "#...#...#", //
+            "#########",
+        ]);
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(5.0, 3.0)));
+        let monster = manager.spawn(prefabs::monster(at(3.0, 3.0)));
+        manager.set_navigation(map.clone(), Default::default(), Default::default());
+        // It saw the player step behind the wall and keeps chasing
+        manager.get_mut::<Ai>(monster).unwrap().target_entity_id = Some(player);
+
+        for step in 1..=20 {
+            manager.update_entities(0.5, step as f64);
+            let position = manager.position(monster).unwrap();
+            let tile = crate::pathfinding::tile_of(position).unwrap();
+            assert!(
+                map.is_walkable(tile.0, tile.1),
+                "Monster walked into a wall"
+            );
+        }
+
+        // It went all the way around and is now hitting the player
+        assert!(manager.distance(monster, player).unwrap() <= 1.5);
+        assert!(manager.get::<Health>(player).unwrap().current < 100);
+        assert!(manager.take_path_cache().is_some());
+    }
+
+    #[test]
+    fn test_walls_block_detection_and_attacks() {
+        let map = crate::pathfinding::tests::map_from(&[
+            "#######", //
+            "#.....#", //
+            "#..#..#", //
+            "#.....#", //
+            "#######",
+        ]);
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(4.0, 2.0)));
+        let monster = manager.spawn(prefabs::monster(at(2.0, 2.0)));
+        manager.get_mut::<Combat>(monster).unwrap().attack_range = 5.0;
+        manager.set_navigation(map, Default::default(), Default::default());
+
+        assert!(!manager.can_see(monster, player));
+        assert!(manager.attack_entity(monster, player, 10.0).is_err());
+        manager.update_entities(0.5, 10.0);
+        assert_eq!(manager.get::<Ai>(monster).unwrap().target_entity_id, None);
+        assert_eq!(manager.position(monster).unwrap().x, 2.0);
+
+        // Stepping out from behind the pillar
+        manager.set_position(player, at(4.0, 1.0));
+        assert!(manager.can_see(monster, player));
+        manager.update_entities(0.5, 20.0);
+        assert_eq!(manager.get::<Health>(player).unwrap().current, 85);
+    }
+
+    #[test]
+    fn test_movement_is_swept() {
+        let map = crate::pathfinding::tests::map_from(&[
+            "#######", //
+            "#..#..#", //
+            "#.....#", //
+            "#######",
+        ]);
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(1.0, 1.0)));
+        let npc = manager.spawn(prefabs::npc(at(4.0, 2.0)));
+
+        // The wall stops the player instead of letting it jump to the other side
+        let end = manager
+            .move_entity(player, at(5.0, 1.0), Some(&map))
+            .unwrap();
+        assert!(end.x < 2.11 && end.x > 2.0);
+
+        // Bodies don't stack: the player stops against the NPC
+        manager.set_position(player, at(1.0, 2.0));
+        let end = manager
+            .move_entity(player, at(4.0, 2.0), Some(&map))
+            .unwrap();
+        assert!((manager.distance(player, npc).unwrap() - 0.8).abs() < 0.01);
+        assert_eq!(manager.position(player).unwrap().x, end.x);
+
+        // Items don't block anything
+        let item = manager.spawn(prefabs::item(
+            at(2.0, 1.0),
+            ItemStack {
+                item: "bone",
+                count: 1,
+            },
+        ));
+        manager.set_position(player, at(1.0, 1.0));
+        manager
+            .move_entity(player, at(2.0, 1.0), Some(&map))
+            .unwrap();
+        assert_eq!(manager.distance(player, item), Some(0.0));
+    }
+
+    #[test]
+    fn test_item_pickup() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(at(0.0, 0.0)));
+        let item = manager.spawn(prefabs::item(
+            at(1.0, 1.0),
+            ItemStack {
+                item: "bone",
+                count: 60,
+            },
+        ));
+        let npc = manager.spawn(prefabs::npc(at(1.0, 0.0)));
+
+        assert!(matches!(
+            manager.interact_entities(player, npc),
+            Ok(InteractionResult::NPCInteraction { .. })
+        ));
+        assert!(matches!(
+            manager.interact_entities(player, item),
+            Ok(InteractionResult::ItemPickedUp)
+        ));
+        assert_eq!(manager.get::<Inventory>(player).unwrap().count("bone"), 60);
+        assert!(!manager.contains(item));
+
+        // Only what fits is picked up
+        let inventory = manager.get_mut::<Inventory>(player).unwrap();
+        inventory.slots.truncate(2);
+        inventory.slots[1] = None;
+        let item = manager.spawn(prefabs::item(
+            at(1.0, 1.0),
+            ItemStack {
+                item: "bone",
+                count: 60,
+            },
+        ));
+        assert!(manager.interact_entities(player, item).is_ok());
+        assert_eq!(manager.get::<ItemStack>(item).unwrap().count, 10);
+        assert_eq!(
+            manager.interact_entities(player, item).unwrap_err(),
+            "Inventory is full"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Component compositions for the kinds of entities the game spawns.
+
+use super::abilities::STARTING_ABILITIES;
+use super::components::*;
+use super::shops::Shop;
+use super::EntityBuilder;
+use crate::combat::{DamageType, Resistances};
+use crate::map::Vec2;
+
+pub fn player(position: Vec2) -> EntityBuilder {
+    EntityBuilder::new()
+        .with(Transform::at(position))
+        .with(EntityState::Idle)
+        .with(Faction::Players)
+        .with(Health::new(100))
+        .with(Combat::default())
+        .with(Collider::default())
+        .with(Movement { speed: 2.0 })
+        .with(Senses::default())
+        .with(Resources::new(100.0, 100.0))
+        .with(Abilities::new(STARTING_ABILITIES))
+        .with(Inventory::default())
+        .with(Experience::default())
+        .with(QuestLog::default())
+        .with(Wallet::default())
+        .with(Respawns)
+}
+
+/// Stats and behaviour of one kind of monster
+#[derive(Debug, Clone)]
+pub struct Archetype {
+    pub name: &'static str,
+    pub health: u32,
+    pub attack_damage: u32,
+    pub attack_range: f64,
+    pub damage_type: DamageType,
+    pub resistances: Resistances,
+    pub speed: f64,
+    pub behavior: &'static str, // Name of a behaviour in `behavior::ALL_BEHAVIORS`
+    pub loot_table: &'static str, // Name of a table in `loot::ALL_LOOT_TABLES`
+    pub experience: u64,        // Shared out among its killers
+}
+
+const NO_RESISTANCES: Resistances = Resistances {
+    physical: 0,
+    fire: 0,
+    ice: 0,
+    poison: 0,
+};
+
+pub const BRUTE: Archetype = Archetype {
+    name: "brute",
+    health: 50,
+    attack_damage: 15,
+    attack_range: 1.5,
+    damage_type: DamageType::Physical,
+    resistances: NO_RESISTANCES,
+    speed: 1.5,
+    behavior: "aggressive",
+    loot_table: "brute",
+    experience: 40,
+};
+
+pub const SKELETON_GUARD: Archetype = Archetype {
+    name: "skeleton_guard",
+    health: 60,
+    attack_damage: 12,
+    attack_range: 1.5,
+    damage_type: DamageType::Physical,
+    resistances: Resistances {
+        physical: 20,
+        fire: -25,
+        ice: 0,
+        poison: 100,
+    },
+    speed: 1.2,
+    behavior: "guard",
+    loot_table: "skeleton_guard",
+    experience: 50,
+};
+
+pub const RAT: Archetype = Archetype {
+    name: "rat",
+    health: 20,
+    attack_damage: 5,
+    attack_range: 1.2,
+    damage_type: DamageType::Poison,
+    resistances: NO_RESISTANCES,
+    speed: 2.5,
+    behavior: "coward",
+    loot_table: "rat",
+    experience: 10,
+};
+
+pub const WOLF: Archetype = Archetype {
+    name: "wolf",
+    health: 35,
+    attack_damage: 10,
+    attack_range: 1.5,
+    damage_type: DamageType::Physical,
+    resistances: Resistances {
+        physical: 0,
+        fire: -10,
+        ice: 40,
+        poison: 0,
+    },
+    speed: 2.5,
+    behavior: "pack",
+    loot_table: "wolf",
+    experience: 25,
+};
+
+pub const ALL_ARCHETYPES: &[Archetype] = &[BRUTE, SKELETON_GUARD, RAT, WOLF];
+
+/// Find a monster archetype by name
+pub fn find_archetype(name: &str) -> Option<&'static Archetype> {
+    ALL_ARCHETYPES
+        .iter()
+        .find(|archetype| archetype.name == name)
+}
+
+/// A monster of the given archetype
+pub fn archetype(archetype: &Archetype, position: Vec2) -> EntityBuilder {
+    EntityBuilder::new()
+        .with(Transform::at(position.clone()))
+        .with(EntityState::Idle)
+        .with(Faction::Monsters)
+        .with(Health::new(archetype.health))
+        .with(Combat {
+            attack_damage: archetype.attack_damage,
+            damage_type: archetype.damage_type,
+            attack_range: archetype.attack_range,
+            ..Combat::default()
+        })
+        .with(Defense {
+            evasion: 0.0,
+            resistances: archetype.resistances.clone(),
+        })
+        .with(Collider::default())
+        .with(Movement {
+            speed: archetype.speed,
+        })
+        .with(Ai::new(archetype.behavior, position))
+        .with(Loot {
+            table: archetype.loot_table,
+        })
+        .with(ExperienceReward {
+            xp: archetype.experience,
+        })
+        .with(MonsterKind {
+            archetype: archetype.name,
+        })
+}
+
+pub fn monster(position: Vec2) -> EntityBuilder {
+    archetype(&BRUTE, position)
+}
+
+pub fn npc(position: Vec2) -> EntityBuilder {
+    EntityBuilder::new()
+        .with(Transform::at(position))
+        .with(EntityState::Idle)
+        .with(Faction::Neutral)
+        .with(Collider::default())
+        .with(Movement { speed: 1.0 })
+        .with(Talkable {
+            dialogue: "villager",
+        })
+}
+
+/// An NPC selling the wares of a shop
+pub fn vendor(position: Vec2, dialogue: &'static str, shop: &Shop) -> EntityBuilder {
+    npc(position)
+        .with(Talkable { dialogue })
+        .with(Vendor::new(shop))
+}
+
+/// A stack of items lying on the ground
+pub fn item(position: Vec2, stack: ItemStack) -> EntityBuilder {
+    EntityBuilder::new()
+        .with(Transform::at(position))
+        .with(EntityState::Idle)
+        .with(Pickup)
+        .with(stack)
+}
+
+/// Marks where a player died until they respawn
+pub fn gravestone(position: Vec2, owner_id: u64) -> EntityBuilder {
+    EntityBuilder::new()
+        .with(Transform::at(position))
+        .with(EntityState::Idle)
+        .with(Gravestone)
+        .with(Owner {
+            entity_id: owner_id,
+        })
+}
+
+/// A creature fighting for its owner until it expires
+pub fn summon(position: Vec2, owner_id: u64, faction: Faction, expires_at: f64) -> EntityBuilder {
+    monster(position)
+        .without::<Loot>()
+        .without::<ExperienceReward>()
+        .without::<MonsterKind>()
+        .with(faction)
+        .with(Owner {
+            entity_id: owner_id,
+        })
+        .with(Lifetime { expires_at })
+}
+
+/// A projectile flying for its owner until it hits something or expires
+pub fn projectile(
+    position: Vec2,
+    owner_id: u64,
+    projectile: Projectile,
+    radius: f64,
+    expires_at: f64,
+) -> EntityBuilder {
+    let direction = (-projectile.velocity.y).atan2(projectile.velocity.x);
+    EntityBuilder::new()
+        .with(Transform {
+            position,
+            direction,
+        })
+        .with(EntityState::Moving)
+        .with(Collider {
+            radius,
+            blocks_entities: false,
+        })
+        .with(projectile)
+        .with(Owner {
+            entity_id: owner_id,
+        })
+        .with(Lifetime { expires_at })
+}
+
+/// Damages whoever walks into it; traps cannot be destroyed
+pub fn trap(position: Vec2, damage: u32) -> EntityBuilder {
+    EntityBuilder::new()
+        .with(Transform::at(position.clone()))
+        .with(EntityState::Idle)
+        .with(Faction::Monsters)
+        .with(Combat {
+            attack_damage: damage,
+            attack_range: 0.5,
+            ..Combat::default()
+        })
+        .with(Ai::new("ambush", position))
+}
@@ -0,0 +1,215 @@
+//! Experience and levels: killers share out the experience of what they kill, and every level
+//! gained grows their stats.
+
+use super::components::*;
+use super::EntityManager;
+use std::cmp::Reverse;
+
+pub const MAX_LEVEL: u32 = 20;
+
+/// Stats every level past the first adds
+#[derive(Debug, Clone)]
+pub struct LevelGrowth {
+    pub max_health: u32,
+    pub attack_damage: u32,
+    pub max_mana: f64,
+    pub max_energy: f64,
+}
+
+pub const GROWTH_PER_LEVEL: LevelGrowth = LevelGrowth {
+    max_health: 10,
+    attack_damage: 2,
+    max_mana: 5.0,
+    max_energy: 5.0,
+};
+
+/// Total experience needed to reach a level: 100 for level 2, 300 for 3, 600 for 4, ...
+pub fn xp_for_level(level: u32) -> u64 {
+    let gained = level.saturating_sub(1) as u64;
+    50 * gained * (gained + 1)
+}
+
+/// The level a total of experience reaches
+pub fn level_for_xp(xp: u64) -> u32 {
+    (2..=MAX_LEVEL)
+        .take_while(|&level| xp >= xp_for_level(level))
+        .count() as u32
+        + 1
+}
+
+/// An entity reached a new level, kept until the caller takes it to tell clients
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelUp {
+    pub entity_id: u64,
+    pub level: u32,
+}
+
+impl EntityManager {
+    pub fn take_level_ups(&mut self) -> Vec<LevelUp> {
+        std::mem::take(&mut self.level_ups)
+    }
+
+    /// Credit damage toward the target's experience split. Summons and projectiles credit
+    /// whoever owns them.
+    pub(super) fn record_damage(&mut self, attacker_id: u64, target_id: u64, damage: u32) {
+        if damage == 0 || !self.has::<ExperienceReward>(target_id) {
+            return;
+        }
+        let attacker_id = self
+            .get::<Owner>(attacker_id)
+            .map_or(attacker_id, |owner| owner.entity_id);
+        if self.get::<DamageTaken>(target_id).is_none() {
+            self.insert(target_id, DamageTaken::default());
+        }
+        let taken = self.get_mut::<DamageTaken>(target_id).unwrap();
+        *taken.by.entry(attacker_id).or_default() += damage;
+    }
+
+    /// Give an entity experience, levelling it up for every threshold it crosses.
+    /// Returns the number of levels gained.
+    pub fn award_experience(&mut self, id: u64, xp: u64) -> u32 {
+        let Some(experience) = self.get_mut::<Experience>(id) else {
+            return 0;
+        };
+        experience.xp += xp;
+        let from = experience.level;
+        let to = level_for_xp(experience.xp).max(from);
+        experience.level = to;
+
+        self.grow(id, to - from);
+        for level in from + 1..=to {
+            self.level_ups.push(LevelUp {
+                entity_id: id,
+                level,
+            });
+        }
+        to - from
+    }
+
+    /// Add the growth of a number of levels to an entity's stats; the dead stay dead
+    pub fn grow(&mut self, id: u64, levels: u32) {
+        if levels == 0 {
+            return;
+        }
+        if let Some(health) = self.get_mut::<Health>(id) {
+            let gained = GROWTH_PER_LEVEL.max_health * levels;
+            health.max += gained;
+            if health.is_alive() {
+                health.current += gained;
+            }
+        }
+        if let Some(combat) = self.get_mut::<Combat>(id) {
+            combat.attack_damage += GROWTH_PER_LEVEL.attack_damage * levels;
+        }
+        if let Some(resources) = self.get_mut::<Resources>(id) {
+            let mana = GROWTH_PER_LEVEL.max_mana * levels as f64;
+            let energy = GROWTH_PER_LEVEL.max_energy * levels as f64;
+            resources.max_mana += mana;
+            resources.mana += mana;
+            resources.max_energy += energy;
+            resources.energy += energy;
+        }
+    }
+
+    /// Split the experience reward of a dying entity among the contributors that can gain
+    /// experience, by damage dealt. The top contributor gets what does not divide evenly.
+    pub(super) fn reward_experience(&mut self, id: u64) {
+        let (Some(reward), Some(taken)) = (
+            self.get::<ExperienceReward>(id),
+            self.get::<DamageTaken>(id),
+        ) else {
+            return;
+        };
+        let xp = reward.xp;
+        let contributors: Vec<(u64, u32)> = taken
+            .by
+            .iter()
+            .map(|(&attacker_id, &damage)| (attacker_id, damage))
+            .filter(|&(attacker_id, _)| self.has::<Experience>(attacker_id))
+            .collect();
+        let total: u64 = contributors.iter().map(|&(_, damage)| damage as u64).sum();
+        if total == 0 {
+            return;
+        }
+
+        let mut shares: Vec<(u64, u64)> = contributors
+            .iter()
+            .map(|&(attacker_id, damage)| (attacker_id, xp * damage as u64 / total))
+            .collect();
+        let remainder = xp - shares.iter().map(|&(_, share)| share).sum::<u64>();
+        if let Some(top) = (0..contributors.len())
+            .max_by_key(|&index| (contributors[index].1, Reverse(contributors[index].0)))
+        {
+            shares[top].1 += remainder;
+        }
+        for (attacker_id, share) in shares {
+            self.award_experience(attacker_id, share);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::prefabs;
+    use crate::map::Vec2;
+
+    #[test]
+    fn test_levels_follow_thresholds_and_grow_stats() {
+        assert_eq!(level_for_xp(0), 1);
+        assert_eq!(level_for_xp(99), 1);
+        assert_eq!(level_for_xp(100), 2);
+        assert_eq!(level_for_xp(300), 3);
+        assert_eq!(level_for_xp(u64::MAX), MAX_LEVEL);
+
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(Vec2 { x: 0.0, y: 0.0 }));
+        assert_eq!(manager.award_experience(player, 350), 2);
+        assert_eq!(
+            manager.get::<Experience>(player),
+            Some(&Experience { level: 3, xp: 350 })
+        );
+        assert_eq!(manager.get::<Health>(player).unwrap().max, 120);
+        assert_eq!(manager.get::<Combat>(player).unwrap().attack_damage, 14);
+        assert_eq!(manager.get::<Resources>(player).unwrap().max_mana, 110.0);
+        assert_eq!(
+            manager.take_level_ups(),
+            vec![
+                LevelUp {
+                    entity_id: player,
+                    level: 2
+                },
+                LevelUp {
+                    entity_id: player,
+                    level: 3
+                },
+            ]
+        );
+        assert!(manager.take_level_ups().is_empty());
+    }
+
+    #[test]
+    fn test_kill_experience_is_split_by_damage() {
+        let mut manager = EntityManager::new();
+        let first = manager.spawn(prefabs::player(Vec2 { x: 0.0, y: 0.0 }));
+        let second = manager.spawn(prefabs::player(Vec2 { x: 2.0, y: 0.0 }));
+        let summon = manager.spawn(prefabs::summon(
+            Vec2 { x: 2.0, y: 2.0 },
+            second,
+            Faction::Players,
+            60.0,
+        ));
+        let monster = manager.spawn(prefabs::monster(Vec2 { x: 1.0, y: 1.0 }));
+        manager.record_damage(first, monster, 20);
+        manager.record_damage(second, monster, 5);
+        manager.record_damage(summon, monster, 5);
+        manager.get_mut::<Health>(monster).unwrap().current = 0;
+
+        // 40 experience: 2/3 for the first player plus the remainder, 1/3 for the second
+        // player and their summon
+        assert_eq!(manager.cleanup_dead_entities(1.0), vec![monster]);
+        assert_eq!(manager.get::<Experience>(first).unwrap().xp, 27);
+        assert_eq!(manager.get::<Experience>(second).unwrap().xp, 13);
+        assert!(!manager.has::<Experience>(summon));
+    }
+}
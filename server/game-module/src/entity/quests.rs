@@ -0,0 +1,393 @@
+//! Quests players take on, usually offered in dialogue.
+//!
+//! A quest is a list of objectives that game events count toward. Once every objective is met
+//! the quest is handed in, paying out its reward and opening up the quests that follow it.
+
+use super::components::*;
+use super::EntityManager;
+use crate::map_generator::ObjectType;
+
+/// How close an entity must come to a map object to reach it
+pub const REACH_RANGE: f64 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuestObjective {
+    Kill { archetype: &'static str, count: u32 },
+    Collect { item: &'static str, count: u32 }, // Counts pickups made while the quest is active
+    Reach(ObjectType),                          // Any map object of this kind
+    TalkTo(&'static str),                       // Any NPC with this dialogue
+    ClearDungeon,                               // Be there when its last monster dies
+}
+
+impl QuestObjective {
+    /// How many times the objective must be met
+    pub fn required(&self) -> u32 {
+        match *self {
+            QuestObjective::Kill { count, .. } | QuestObjective::Collect { count, .. } => count,
+            _ => 1,
+        }
+    }
+
+    /// How much an event counts toward the objective
+    fn counts(&self, event: &QuestEvent) -> u32 {
+        match (*self, *event) {
+            (QuestObjective::Kill { archetype, .. }, QuestEvent::Killed(killed))
+                if archetype == killed =>
+            {
+                1
+            }
+            (QuestObjective::Collect { item, .. }, QuestEvent::Collected { item: got, count })
+                if item == got =>
+            {
+                count
+            }
+            (QuestObjective::Reach(object), QuestEvent::Reached(reached)) if object == reached => 1,
+            (QuestObjective::TalkTo(dialogue), QuestEvent::TalkedTo(talked))
+                if dialogue == talked =>
+            {
+                1
+            }
+            (QuestObjective::ClearDungeon, QuestEvent::ClearedDungeon) => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Something an entity did that objectives may count
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuestEvent {
+    Killed(&'static str), // Monster archetype
+    Collected { item: &'static str, count: u32 },
+    Reached(ObjectType),
+    TalkedTo(&'static str), // Dialogue of the NPC
+    ClearedDungeon,
+}
+
+/// Paid out when a quest is handed in
+#[derive(Debug, Clone)]
+pub struct QuestReward {
+    pub xp: u64,
+    pub gold: u64,
+    pub items: &'static [(&'static str, u32)], // Item ID and count
+}
+
+#[derive(Debug, Clone)]
+pub struct Quest {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub requires: Option<&'static str>, // Quest to complete before this one can be taken
+    pub objectives: &'static [QuestObjective],
+    pub reward: QuestReward,
+}
+
+pub const INTRODUCTIONS: Quest = Quest {
+    id: "introductions",
+    name: "Introductions",
+    description: "The town elder likes to meet every newcomer.",
+    requires: None,
+    objectives: &[QuestObjective::TalkTo("elder")],
+    reward: QuestReward {
+        xp: 25,
+        gold: 10,
+        items: &[("health_potion", 1)],
+    },
+};
+
+pub const RAT_PROBLEM: Quest = Quest {
+    id: "rat_problem",
+    name: "Rat Problem",
+    description: "Rats from the dungeon have been getting into the town's stores.",
+    requires: None,
+    objectives: &[QuestObjective::Kill {
+        archetype: "rat",
+        count: 5,
+    }],
+    reward: QuestReward {
+        xp: 100,
+        gold: 50,
+        items: &[("health_potion", 2)],
+    },
+};
+
+pub const BONES_OF_THE_DEEP: Quest = Quest {
+    id: "bones_of_the_deep",
+    name: "Bones of the Deep",
+    description: "The skeletons guarding the dungeon must be put to rest.",
+    requires: Some("rat_problem"),
+    objectives: &[
+        QuestObjective::Kill {
+            archetype: "skeleton_guard",
+            count: 3,
+        },
+        QuestObjective::Collect {
+            item: "bone",
+            count: 5,
+        },
+    ],
+    reward: QuestReward {
+        xp: 300,
+        gold: 120,
+        items: &[("iron_sword", 1)],
+    },
+};
+
+pub const SECRETS_BELOW: Quest = Quest {
+    id: "secrets_below",
+    name: "Secrets Below",
+    description: "Find the hidden mechanisms of the dungeon and drive out everything in it.",
+    requires: Some("bones_of_the_deep"),
+    objectives: &[
+        QuestObjective::Reach(ObjectType::Lever),
+        QuestObjective::ClearDungeon,
+    ],
+    reward: QuestReward {
+        xp: 500,
+        gold: 250,
+        items: &[("amulet_of_warding", 1)],
+    },
+};
+
+pub const ALL_QUESTS: &[Quest] = &[INTRODUCTIONS, RAT_PROBLEM, BONES_OF_THE_DEEP, SECRETS_BELOW];
+
+/// Find a quest by ID
+pub fn find_quest(id: &str) -> Option<&'static Quest> {
+    ALL_QUESTS.iter().find(|quest| quest.id == id)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuestStatus {
+    Active,
+    Completed,
+}
+
+/// Where an entity stands with a quest it took on
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuestProgress {
+    pub status: QuestStatus,
+    pub counts: Vec<u32>, // Progress on each objective, never above what it requires
+}
+
+impl QuestProgress {
+    /// Whether every objective of the quest is met
+    pub fn is_done(&self, quest: &Quest) -> bool {
+        quest
+            .objectives
+            .iter()
+            .enumerate()
+            .all(|(index, objective)| {
+                self.counts.get(index).copied().unwrap_or(0) >= objective.required()
+            })
+    }
+}
+
+impl QuestLog {
+    /// Take on a quest that was not taken on before and whose required quest is completed
+    pub fn start(&mut self, quest: &'static Quest) -> Result<(), String> {
+        if self.quests.contains_key(quest.id) {
+            return Err("Quest already taken".to_string());
+        }
+        if let Some(required) = quest.requires {
+            if self.quests.get(required).map(|progress| progress.status)
+                != Some(QuestStatus::Completed)
+            {
+                return Err("Another quest must be completed first".to_string());
+            }
+        }
+        self.quests.insert(
+            quest.id,
+            QuestProgress {
+                status: QuestStatus::Active,
+                counts: vec![0; quest.objectives.len()],
+            },
+        );
+        Ok(())
+    }
+
+    /// Mark an active quest with every objective met as completed
+    pub fn complete(&mut self, quest: &Quest) -> Result<(), String> {
+        let progress = self
+            .quests
+            .get_mut(quest.id)
+            .filter(|progress| progress.status == QuestStatus::Active)
+            .ok_or("Quest is not active")?;
+        if !progress.is_done(quest) {
+            return Err("Quest objectives are not met".to_string());
+        }
+        progress.status = QuestStatus::Completed;
+        Ok(())
+    }
+
+    /// Count an event toward the objectives of every active quest. Returns the quests whose
+    /// objectives the event finished.
+    pub fn record(&mut self, event: &QuestEvent) -> Vec<&'static str> {
+        let mut finished = Vec::new();
+        for (&id, progress) in self.quests.iter_mut() {
+            let Some(quest) = find_quest(id) else {
+                continue;
+            };
+            if progress.status != QuestStatus::Active || progress.is_done(quest) {
+                continue;
+            }
+            progress.counts.resize(quest.objectives.len(), 0);
+            for (count, objective) in progress.counts.iter_mut().zip(quest.objectives) {
+                *count = (*count + objective.counts(event)).min(objective.required());
+            }
+            if progress.is_done(quest) {
+                finished.push(id);
+            }
+        }
+        finished
+    }
+}
+
+impl EntityManager {
+    /// Where an entity stands with a quest; `None` if it never took it on
+    pub fn quest_status(&self, id: u64, quest: &str) -> Option<QuestStatus> {
+        Some(self.get::<QuestLog>(id)?.quests.get(quest)?.status)
+    }
+
+    /// Whether an entity has an active quest with every objective met, ready to hand in
+    pub fn quest_ready(&self, id: u64, quest: &str) -> bool {
+        let (Some(log), Some(quest)) = (self.get::<QuestLog>(id), find_quest(quest)) else {
+            return false;
+        };
+        log.quests.get(quest.id).is_some_and(|progress| {
+            progress.status == QuestStatus::Active && progress.is_done(quest)
+        })
+    }
+
+    /// Take on a quest that was not taken on before
+    pub fn start_quest(&mut self, id: u64, quest: &str) -> Result<(), String> {
+        let quest = find_quest(quest).ok_or("Unknown quest")?;
+        self.get_mut::<QuestLog>(id)
+            .ok_or("Entity cannot take quests")?
+            .start(quest)
+    }
+
+    /// Count an event toward an entity's quests. Returns the quests the event finished.
+    pub fn record_quest_event(&mut self, id: u64, event: QuestEvent) -> Vec<&'static str> {
+        self.get_mut::<QuestLog>(id)
+            .map(|log| log.record(&event))
+            .unwrap_or_default()
+    }
+
+    /// Credit the kill of a dying monster to everyone who damaged it
+    pub(super) fn record_kill(&mut self, id: u64) {
+        let (Some(kind), Some(taken)) = (self.get::<MonsterKind>(id), self.get::<DamageTaken>(id))
+        else {
+            return;
+        };
+        let event = QuestEvent::Killed(kind.archetype);
+        let killer_ids: Vec<u64> = taken.by.keys().copied().collect();
+        for killer_id in killer_ids {
+            self.record_quest_event(killer_id, event);
+        }
+    }
+
+    /// Hand in a quest with every objective met, paying out its reward. Nothing changes if the
+    /// reward does not fit in the inventory.
+    pub fn complete_quest(&mut self, id: u64, quest: &str) -> Result<(), String> {
+        let quest = find_quest(quest).ok_or("Unknown quest")?;
+        let mut log = self
+            .get::<QuestLog>(id)
+            .cloned()
+            .ok_or("Entity cannot take quests")?;
+        log.complete(quest)?;
+        let mut inventory = self.get::<Inventory>(id).cloned().unwrap_or_default();
+        for &(item, count) in quest.reward.items {
+            if inventory.add(item, count) > 0 {
+                return Err("Inventory is full".to_string());
+            }
+        }
+        self.insert(id, log);
+        if self.has::<Inventory>(id) {
+            self.insert(id, inventory);
+        }
+        if let Some(wallet) = self.get_mut::<Wallet>(id) {
+            wallet.gold += quest.reward.gold;
+        }
+        self.award_experience(id, quest.reward.xp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{items::find_item, prefabs, ItemStack};
+    use crate::map::Vec2;
+
+    #[test]
+    fn test_quests_reference_what_exists() {
+        for quest in ALL_QUESTS {
+            assert!(quest.requires.is_none_or(|id| find_quest(id).is_some()));
+            for &(item, _) in quest.reward.items {
+                assert!(find_item(item).is_some(), "{}", item);
+            }
+            for objective in quest.objectives {
+                match *objective {
+                    QuestObjective::Kill { archetype, .. } => {
+                        assert!(prefabs::find_archetype(archetype).is_some())
+                    }
+                    QuestObjective::Collect { item, .. } => assert!(find_item(item).is_some()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_quest_objectives_rewards_and_chains() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(Vec2 { x: 0.0, y: 0.0 }));
+        assert_eq!(
+            manager
+                .start_quest(player, "bones_of_the_deep")
+                .unwrap_err(),
+            "Another quest must be completed first"
+        );
+        manager.start_quest(player, "rat_problem").unwrap();
+        assert!(manager.start_quest(player, "rat_problem").is_err());
+
+        // Kills count for everyone who damaged the monster, only for the right archetype
+        for archetype in [&prefabs::RAT, &prefabs::RAT, &prefabs::WOLF] {
+            let monster = manager.spawn(prefabs::archetype(archetype, Vec2 { x: 1.0, y: 0.0 }));
+            manager.record_damage(player, monster, 5);
+            manager.get_mut::<Health>(monster).unwrap().current = 0;
+            manager.cleanup_dead_entities(0.0);
+        }
+        let progress = &manager.get::<QuestLog>(player).unwrap().quests["rat_problem"];
+        assert_eq!(progress.counts, vec![2]);
+        assert!(manager.complete_quest(player, "rat_problem").is_err());
+
+        for _ in 0..4 {
+            manager.record_quest_event(player, QuestEvent::Killed("rat"));
+        }
+        assert!(manager.quest_ready(player, "rat_problem"));
+        let xp = manager.get::<Experience>(player).unwrap().xp;
+        manager.complete_quest(player, "rat_problem").unwrap();
+        assert_eq!(manager.get::<Experience>(player).unwrap().xp, xp + 100);
+        assert_eq!(manager.get::<Wallet>(player).unwrap().gold, 50);
+        let inventory = manager.get::<Inventory>(player).unwrap();
+        assert_eq!(inventory.count("health_potion"), 2);
+        assert_eq!(
+            manager.quest_status(player, "rat_problem"),
+            Some(QuestStatus::Completed)
+        );
+        assert!(manager.complete_quest(player, "rat_problem").is_err());
+
+        // The next quest of the chain opens up; pickups count toward collecting
+        manager.start_quest(player, "bones_of_the_deep").unwrap();
+        let bones = manager.spawn(prefabs::item(
+            Vec2 { x: 1.0, y: 0.0 },
+            ItemStack {
+                item: "bone",
+                count: 7,
+            },
+        ));
+        manager.interact_entities(player, bones).unwrap();
+        let progress = &manager.get::<QuestLog>(player).unwrap().quests["bones_of_the_deep"];
+        assert_eq!(progress.counts, vec![0, 5]);
+    }
+}
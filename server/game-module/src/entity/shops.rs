@@ -0,0 +1,281 @@
+//! Shops: what vendors sell, at what price, and how fast their stock comes back.
+//!
+//! Vendors sell the wares of their shop while stock lasts and buy anything with a value
+//! for that value. Buying and selling both happen within talking range, while the buyer is
+//! talking to the vendor and the vendor showed its wares.
+
+use super::components::*;
+use super::dialogue::TALK_RANGE;
+use super::items::find_item;
+use super::EntityManager;
+
+/// One of the wares of a shop
+#[derive(Debug, Clone)]
+pub struct ShopItem {
+    pub item: &'static str,
+    pub price: u64,     // Gold for one
+    pub max_stock: u32, // Stock after a restock never exceeds this
+    pub restock: u32,   // Stock added at every restock
+}
+
+#[derive(Debug, Clone)]
+pub struct Shop {
+    pub name: &'static str,
+    pub items: &'static [ShopItem],
+    pub restock_interval: f64, // Seconds between restocks
+}
+
+impl Shop {
+    /// Position of an item among the shop's wares
+    pub fn ware(&self, item: &str) -> Option<usize> {
+        self.items.iter().position(|ware| ware.item == item)
+    }
+}
+
+pub const GENERAL_STORE: Shop = Shop {
+    name: "general_store",
+    items: &[
+        ShopItem {
+            item: "health_potion",
+            price: 25,
+            max_stock: 10,
+            restock: 2,
+        },
+        ShopItem {
+            item: "mana_potion",
+            price: 25,
+            max_stock: 10,
+            restock: 2,
+        },
+        ShopItem {
+            item: "rusty_sword",
+            price: 20,
+            max_stock: 2,
+            restock: 1,
+        },
+        ShopItem {
+            item: "leather_armor",
+            price: 45,
+            max_stock: 2,
+            restock: 1,
+        },
+        ShopItem {
+            item: "iron_sword",
+            price: 150,
+            max_stock: 1,
+            restock: 1,
+        },
+    ],
+    restock_interval: 300.0,
+};
+
+pub const ALL_SHOPS: &[Shop] = &[GENERAL_STORE];
+
+/// Find a shop by name
+pub fn find_shop(name: &str) -> Option<&'static Shop> {
+    ALL_SHOPS.iter().find(|shop| shop.name == name)
+}
+
+impl Vendor {
+    /// A vendor of a shop with every ware fully stocked
+    pub fn new(shop: &Shop) -> Self {
+        Self {
+            shop: shop.name,
+            stock: shop.items.iter().map(|ware| ware.max_stock).collect(),
+            restock_at: 0.0,
+        }
+    }
+}
+
+impl EntityManager {
+    /// Buy wares from a vendor. Nothing changes unless the buyer can pay for all of them and
+    /// carry them. Returns the gold paid.
+    pub fn buy_item(
+        &mut self,
+        id: u64,
+        vendor_id: u64,
+        item: &str,
+        count: u32,
+    ) -> Result<u64, String> {
+        if count == 0 {
+            return Err("Nothing to buy".to_string());
+        }
+        let mut vendor = self
+            .get::<Vendor>(vendor_id)
+            .cloned()
+            .ok_or("Not a vendor")?;
+        self.check_reach(id, vendor_id, TALK_RANGE, "Too far from vendor")?;
+        self.check_shop_open(id, vendor_id)?;
+        let shop = find_shop(vendor.shop).ok_or("Unknown shop")?;
+        let index = shop.ware(item).ok_or("Vendor does not sell that")?;
+        let ware = &shop.items[index];
+        let stock = vendor.stock.get_mut(index).ok_or("Out of stock")?;
+        if *stock < count {
+            return Err("Out of stock".to_string());
+        }
+        let price = ware.price * count as u64;
+        let mut wallet = self
+            .get::<Wallet>(id)
+            .cloned()
+            .ok_or("Entity has no wallet")?;
+        if wallet.gold < price {
+            return Err("Not enough gold".to_string());
+        }
+        let mut inventory = self
+            .get::<Inventory>(id)
+            .cloned()
+            .ok_or("Entity has no inventory")?;
+        if inventory.add(ware.item, count) > 0 {
+            return Err("Inventory is full".to_string());
+        }
+
+        *stock -= count;
+        wallet.gold -= price;
+        self.insert(vendor_id, vendor);
+        self.insert(id, wallet);
+        self.insert(id, inventory);
+        Ok(price)
+    }
+
+    /// Sell items from an inventory slot to a vendor for their value. Returns the gold
+    /// received.
+    pub fn sell_item(
+        &mut self,
+        id: u64,
+        vendor_id: u64,
+        slot: usize,
+        count: u32,
+    ) -> Result<u64, String> {
+        if !self.has::<Vendor>(vendor_id) {
+            return Err("Not a vendor".to_string());
+        }
+        self.check_reach(id, vendor_id, TALK_RANGE, "Too far from vendor")?;
+        self.check_shop_open(id, vendor_id)?;
+        if !self.has::<Wallet>(id) {
+            return Err("Entity has no wallet".to_string());
+        }
+        let inventory = self
+            .get_mut::<Inventory>(id)
+            .ok_or("Entity has no inventory")?;
+        let stack = inventory
+            .slots
+            .get(slot)
+            .cloned()
+            .flatten()
+            .ok_or("Inventory slot is empty")?;
+        if count == 0 || stack.count < count {
+            return Err("Not enough items".to_string());
+        }
+        let value = find_item(stack.item).map_or(0, |item| item.value);
+        if value == 0 {
+            return Err("Vendor does not buy that".to_string());
+        }
+        inventory.take(slot, count);
+        let gold = value * count as u64;
+        self.get_mut::<Wallet>(id).unwrap().gold += gold;
+        Ok(gold)
+    }
+
+    /// Check that an entity is in a conversation in which the vendor showed its wares
+    fn check_shop_open(&self, id: u64, vendor_id: u64) -> Result<(), String> {
+        let open = self
+            .get::<Conversation>(id)
+            .is_some_and(|conversation| conversation.npc_id == vendor_id && conversation.shop_open);
+        if !open {
+            return Err("Shop is not open".to_string());
+        }
+        Ok(())
+    }
+
+    /// Stock up the wares of every vendor whose restock is due
+    pub(super) fn restock_vendors(&mut self, current_time: f64) {
+        let due_ids: Vec<u64> = self
+            .store::<Vendor>()
+            .iter()
+            .filter(|(_, vendor)| vendor.restock_at <= current_time)
+            .map(|(id, _)| id)
+            .collect();
+        for id in due_ids {
+            let vendor = self.get_mut::<Vendor>(id).unwrap();
+            let Some(shop) = find_shop(vendor.shop) else {
+                continue;
+            };
+            vendor.stock.resize(shop.items.len(), 0);
+            for (stock, ware) in vendor.stock.iter_mut().zip(shop.items) {
+                *stock = (*stock + ware.restock).min(ware.max_stock);
+            }
+            vendor.restock_at = current_time + shop.restock_interval;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::prefabs;
+    use crate::map::Vec2;
+
+    #[test]
+    fn test_buy_sell_and_restock() {
+        let mut manager = EntityManager::new();
+        let player = manager.spawn(prefabs::player(Vec2 { x: 0.0, y: 0.0 }));
+        let vendor = manager.spawn(prefabs::vendor(
+            Vec2 { x: 1.0, y: 0.0 },
+            "merchant",
+            &GENERAL_STORE,
+        ));
+        // Vendors only trade once they showed their wares
+        assert_eq!(
+            manager.buy_item(player, vendor, "health_potion", 1),
+            Err("Shop is not open".to_string())
+        );
+        manager.start_conversation(player, vendor).unwrap();
+        assert_eq!(
+            manager.sell_item(player, vendor, 0, 1),
+            Err("Shop is not open".to_string())
+        );
+        manager.choose_dialogue_option(player, 0).unwrap();
+        assert_eq!(
+            manager.buy_item(player, vendor, "health_potion", 1),
+            Err("Not enough gold".to_string())
+        );
+
+        manager.get_mut::<Wallet>(player).unwrap().gold = 200;
+        assert_eq!(
+            manager.buy_item(player, vendor, "health_potion", 4),
+            Ok(100)
+        );
+        assert_eq!(
+            manager.buy_item(player, vendor, "iron_sword", 2),
+            Err("Out of stock".to_string())
+        );
+        assert!(manager.buy_item(player, vendor, "war_axe", 1).is_err());
+        assert_eq!(manager.get::<Wallet>(player).unwrap().gold, 100);
+        assert_eq!(
+            manager
+                .get::<Inventory>(player)
+                .unwrap()
+                .count("health_potion"),
+            4
+        );
+
+        // Selling pays the item's value, not the shop's price
+        assert_eq!(manager.sell_item(player, vendor, 0, 3), Ok(24));
+        assert!(manager.sell_item(player, vendor, 0, 2).is_err());
+        assert_eq!(manager.get::<Wallet>(player).unwrap().gold, 124);
+
+        manager.set_position(player, Vec2 { x: 5.0, y: 0.0 });
+        assert_eq!(
+            manager.buy_item(player, vendor, "health_potion", 1),
+            Err("Too far from vendor".to_string())
+        );
+
+        // Restocks come back a few at a time, on schedule
+        manager.restock_vendors(10.0);
+        assert_eq!(manager.get::<Vendor>(vendor).unwrap().stock[0], 8);
+        manager.restock_vendors(20.0);
+        assert_eq!(manager.get::<Vendor>(vendor).unwrap().stock[0], 8);
+        manager.restock_vendors(310.0);
+        assert_eq!(manager.get::<Vendor>(vendor).unwrap().stock[0], 10);
+    }
+}
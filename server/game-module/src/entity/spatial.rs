@@ -0,0 +1,267 @@
+use crate::map::Vec2;
+use std::collections::HashMap;
+
+/// Default grid cell size in tiles, a bit over half the usual monster detection range
+pub const DEFAULT_CELL_SIZE: f64 = 4.0;
+
+type Cell = (i64, i64);
+
+/// Uniform grid over entity positions for range and nearest-entity queries
+#[derive(Debug)]
+pub struct SpatialGrid {
+    cell_size: f64,
+    cells: HashMap<Cell, Vec<(u64, Vec2)>>, // Positions are kept inline so queries never look them up
+    entity_cells: HashMap<u64, Cell>,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f64) -> Self {
+        Self {
+            cell_size: cell_size.max(f64::EPSILON),
+            cells: HashMap::new(),
+            entity_cells: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entity_cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entity_cells.is_empty()
+    }
+
+    fn cell_of(&self, position: &Vec2) -> Cell {
+        (
+            (position.x / self.cell_size).floor() as i64,
+            (position.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    /// Add an entity, or move it if it is already indexed
+    pub fn insert(&mut self, id: u64, position: &Vec2) {
+        let cell = self.cell_of(position);
+        match self.entity_cells.insert(id, cell) {
+            Some(previous_cell) if previous_cell == cell => {
+                let entries = self.cells.get_mut(&cell).expect("indexed cell exists");
+                if let Some(entry) = entries.iter_mut().find(|(other, _)| *other == id) {
+                    entry.1 = position.clone();
+                }
+            }
+            previous_cell => {
+                if let Some(previous_cell) = previous_cell {
+                    self.remove_from_cell(previous_cell, id);
+                }
+                self.cells
+                    .entry(cell)
+                    .or_default()
+                    .push((id, position.clone()));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        if let Some(cell) = self.entity_cells.remove(&id) {
+            self.remove_from_cell(cell, id);
+        }
+    }
+
+    fn remove_from_cell(&mut self, cell: Cell, id: u64) {
+        if let Some(entries) = self.cells.get_mut(&cell) {
+            entries.retain(|(other, _)| *other != id);
+            if entries.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Entities in the cells overlapping a rectangle, before exact filtering
+    fn candidates(&self, min: &Vec2, max: &Vec2) -> impl Iterator<Item = &(u64, Vec2)> + '_ {
+        let (min_x, min_y) = self.cell_of(min);
+        let (max_x, max_y) = self.cell_of(max);
+        (min_y..=max_y)
+            .flat_map(move |y| (min_x..=max_x).map(move |x| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+
+    /// Entities within `radius` of `center`, in ID order
+    pub fn query_radius(&self, center: &Vec2, radius: f64) -> Vec<u64> {
+        let min = Vec2 {
+            x: center.x - radius,
+            y: center.y - radius,
+        };
+        let max = Vec2 {
+            x: center.x + radius,
+            y: center.y + radius,
+        };
+        let mut ids: Vec<u64> = self
+            .candidates(&min, &max)
+            .filter(|(_, position)| distance(position, center) <= radius)
+            .map(|&(id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Entities inside the rectangle spanned by `min` and `max` (inclusive), in ID order
+    pub fn query_rect(&self, min: &Vec2, max: &Vec2) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .candidates(min, max)
+            .filter(|(_, position)| {
+                position.x >= min.x
+                    && position.x <= max.x
+                    && position.y >= min.y
+                    && position.y <= max.y
+            })
+            .map(|&(id, _)| id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Up to `k` entities accepted by `filter` that are closest to `center` and no further
+    /// than `max_distance`, nearest first. Ties are broken by ID.
+    pub fn k_nearest(
+        &self,
+        center: &Vec2,
+        k: usize,
+        max_distance: f64,
+        filter: impl Fn(u64) -> bool,
+    ) -> Vec<(u64, f64)> {
+        let mut found: Vec<(u64, f64)> = Vec::new();
+        if k == 0 || self.is_empty() {
+            return found;
+        }
+
+        let (center_x, center_y) = self.cell_of(center);
+        let mut seen = 0;
+        let mut ring: i64 = 0;
+        loop {
+            // Visit the cells on the border of the square `ring` cells away from the center
+            for y in center_y - ring..=center_y + ring {
+                let on_edge = y == center_y - ring || y == center_y + ring;
+                let step = if on_edge {
+                    1
+                } else {
+                    (2 * ring).max(1) as usize
+                };
+                for x in (center_x - ring..=center_x + ring).step_by(step) {
+                    let Some(entries) = self.cells.get(&(x, y)) else {
+                        continue;
+                    };
+                    seen += entries.len();
+                    for (id, position) in entries {
+                        let distance = distance(position, center);
+                        if distance <= max_distance && filter(*id) {
+                            found.push((*id, distance));
+                        }
+                    }
+                }
+            }
+
+            // Anything in later rings is at least `ring` cells away
+            let ring_distance = ring as f64 * self.cell_size;
+            found.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            let done = found.len() >= k && found[k - 1].1 <= ring_distance;
+            if done || seen >= self.len() || ring_distance >= max_distance {
+                break;
+            }
+            ring += 1;
+        }
+
+        found.truncate(k);
+        found
+    }
+}
+
+fn distance(a: &Vec2, b: &Vec2) -> f64 {
+    let dx = a.x - b.x;
+    let dy = a.y - b.y;
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f64, y: f64) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    /// Brute-force reference for the grid queries
+    fn linear_nearest(points: &[(u64, Vec2)], center: &Vec2, k: usize) -> Vec<u64> {
+        let mut all: Vec<(u64, f64)> = points
+            .iter()
+            .map(|(id, p)| {
+                (
+                    *id,
+                    ((p.x - center.x).powi(2) + (p.y - center.y).powi(2)).sqrt(),
+                )
+            })
+            .collect();
+        all.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        all.into_iter().take(k).map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn test_queries_match_linear_scan() {
+        let mut grid = SpatialGrid::new(3.0);
+        let mut points = Vec::new();
+        for id in 0..400u64 {
+            // Deterministic scatter, including negative coordinates
+            let position = at(
+                ((id * 37) % 101) as f64 - 20.0 + 0.25,
+                ((id * 53) % 89) as f64 - 10.0 + 0.5,
+            );
+            grid.insert(id, &position);
+            points.push((id, position));
+        }
+
+        let center = at(12.3, 7.7);
+        let mut expected: Vec<u64> = points
+            .iter()
+            .filter(|(_, p)| ((p.x - center.x).powi(2) + (p.y - center.y).powi(2)).sqrt() <= 9.0)
+            .map(|(id, _)| *id)
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(grid.query_radius(&center, 9.0), expected);
+
+        let nearest: Vec<u64> = grid
+            .k_nearest(&center, 5, f64::INFINITY, |_| true)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(nearest, linear_nearest(&points, &center, 5));
+
+        let far = at(500.0, 500.0);
+        let nearest = grid.k_nearest(&far, 1, f64::INFINITY, |_| true);
+        assert_eq!(nearest[0].0, linear_nearest(&points, &far, 1)[0]);
+        assert!(grid.k_nearest(&far, 1, 10.0, |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_moves_and_removals_stay_in_sync() {
+        let mut grid = SpatialGrid::new(4.0);
+        grid.insert(1, &at(1.0, 1.0));
+        grid.insert(2, &at(2.0, 2.0));
+
+        grid.insert(1, &at(30.0, 30.0));
+        assert_eq!(grid.query_rect(&at(0.0, 0.0), &at(5.0, 5.0)), vec![2]);
+        assert_eq!(grid.query_radius(&at(30.0, 30.0), 0.5), vec![1]);
+
+        grid.remove(2);
+        assert_eq!(grid.len(), 1);
+        assert!(grid.query_rect(&at(0.0, 0.0), &at(5.0, 5.0)).is_empty());
+
+        // The filter skips entities without stopping the search
+        let nearest = grid.k_nearest(&at(0.0, 0.0), 1, f64::INFINITY, |id| id != 1);
+        assert!(nearest.is_empty());
+    }
+}
@@ -0,0 +1,586 @@
+//! Systems run by `EntityManager::update_entities`, each over the components it needs.
+
+use super::abilities::{find_ability, ENERGY_REGEN, MANA_REGEN};
+use super::behavior::{
+    find_behavior, room_waypoints, Behavior, Situation, AGGRESSIVE, PURSUIT_FACTOR,
+};
+use super::components::*;
+use super::effects::{EffectKind, StatusEffects, EFFECT_TICK_INTERVAL};
+use super::{AttackResult, CombatEvent, EntityManager, Navigation, SpatialGrid};
+use crate::collision::circle_hits_wall;
+use crate::combat::{self, DamageBreakdown, HitOutcome};
+use crate::map::Vec2;
+use crate::pathfinding::{tile_center, tile_of};
+use std::collections::HashMap;
+
+/// Distance at which a patrol waypoint counts as reached
+const WAYPOINT_RADIUS: f64 = 0.1;
+
+/// Longest distance a projectile moves between two hit checks
+const PROJECTILE_STEP: f64 = 0.25;
+
+/// Entities without AI go back to idle after moving or attacking
+pub(super) fn reset_states(manager: &mut EntityManager) {
+    let ids: Vec<u64> = manager
+        .store::<EntityState>()
+        .iter()
+        .filter(|(id, state)| {
+            !manager.has::<Ai>(*id) && matches!(state, EntityState::Moving | EntityState::Attacking)
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in ids {
+        manager.insert(id, EntityState::Idle);
+    }
+}
+
+/// Tick damage and healing over time and drop the effects that ran out. Damage is credited
+/// to whoever applied the effect.
+pub(super) fn process_effects(manager: &mut EntityManager, current_time: f64) {
+    for id in manager.store::<StatusEffects>().ids() {
+        if !manager.is_alive(id) {
+            manager.remove_component::<StatusEffects>(id);
+            continue;
+        }
+
+        let mut effects = manager.get::<StatusEffects>(id).unwrap().clone();
+        let position = manager
+            .position(id)
+            .cloned()
+            .unwrap_or(Vec2 { x: 0.0, y: 0.0 });
+        let resistances = manager.effective_defense(id).resistances;
+        for effect in &mut effects.active {
+            while effect.next_tick_at <= current_time && effect.next_tick_at <= effect.expires_at {
+                effect.next_tick_at += EFFECT_TICK_INTERVAL;
+                let amount = (effect.magnitude * effect.stacks as f64).round() as u32;
+                if effect.kind == EffectKind::Regeneration {
+                    if let Some(health) = manager.get_mut::<Health>(id) {
+                        health.heal(amount);
+                    }
+                    continue;
+                }
+                let Some(damage_type) = effect.kind.damage_type() else {
+                    break;
+                };
+                let damage_dealt = combat::mitigate(amount, resistances.get(damage_type));
+                let Some(health) = manager.get_mut::<Health>(id) else {
+                    break;
+                };
+                let target_died = health.take_damage(damage_dealt);
+                if let Some(source_id) = effect.source_entity_id {
+                    manager.record_combat_event(CombatEvent {
+                        attacker_id: source_id,
+                        target_id: id,
+                        result: AttackResult {
+                            breakdown: DamageBreakdown {
+                                outcome: HitOutcome::Hit,
+                                damage_type,
+                                raw_damage: amount,
+                                mitigated_damage: amount as i64 - damage_dealt as i64,
+                                damage_dealt,
+                            },
+                            target_died,
+                            target_position: position.clone(),
+                        },
+                    });
+                }
+                if target_died {
+                    break;
+                }
+            }
+        }
+
+        if !manager.is_alive(id) {
+            manager.insert(id, EntityState::Dead);
+            manager.remove_component::<StatusEffects>(id);
+            continue;
+        }
+        effects.remove_expired(current_time);
+        if effects.active.is_empty() {
+            manager.remove_component::<StatusEffects>(id);
+        } else {
+            manager.insert(id, effects);
+        }
+    }
+}
+
+/// Refill mana and energy over time
+pub(super) fn regenerate_resources(manager: &mut EntityManager, delta_time: f64) {
+    for id in manager.store::<Resources>().ids() {
+        if !manager.is_alive(id) {
+            continue;
+        }
+        let resources = manager.get_mut::<Resources>(id).unwrap();
+        resources.mana = (resources.mana + MANA_REGEN * delta_time).min(resources.max_mana);
+        resources.energy = (resources.energy + ENERGY_REGEN * delta_time).min(resources.max_energy);
+    }
+}
+
+/// Let abilities whose cast time has passed go off. Stunned or dead casters lose the cast,
+/// and so do casts whose target is no longer valid.
+pub(super) fn process_casts(manager: &mut EntityManager, current_time: f64) {
+    for id in manager.store::<Casting>().ids() {
+        let casting = manager.get::<Casting>(id).unwrap();
+        if casting.completes_at > current_time {
+            continue;
+        }
+        let casting = manager.remove_component::<Casting>(id).unwrap();
+        let interrupted = manager
+            .get::<StatusEffects>(id)
+            .is_some_and(|effects| !effects.can_act());
+        if let (false, true, Some(ability)) = (
+            interrupted,
+            manager.is_alive(id),
+            find_ability(casting.ability),
+        ) {
+            let _ = manager.resolve_ability(id, ability, &casting.target, current_time);
+        }
+    }
+}
+
+/// Let every AI-driven entity act on its behaviour
+pub(super) fn run_ai(manager: &mut EntityManager, delta_time: f64, current_time: f64) {
+    // Living entities that can be hunted, indexed per faction so hunters only ever look at
+    // their prey instead of every entity around them
+    let mut huntable: HashMap<Faction, SpatialGrid> = HashMap::new();
+    for (id, &faction) in manager.store::<Faction>().iter() {
+        if faction == Faction::Neutral || !manager.is_alive(id) {
+            continue;
+        }
+        if let Some(position) = manager.position(id) {
+            huntable.entry(faction).or_default().insert(id, position);
+        }
+    }
+
+    for id in manager.store::<Ai>().ids() {
+        let stunned = manager
+            .get::<StatusEffects>(id)
+            .is_some_and(|effects| !effects.can_act());
+        if manager.is_alive(id) && !stunned {
+            update_ai(manager, &mut huntable, id, delta_time, current_time);
+        }
+    }
+}
+
+fn update_ai(
+    manager: &mut EntityManager,
+    huntable: &mut HashMap<Faction, SpatialGrid>,
+    id: u64,
+    delta_time: f64,
+    current_time: f64,
+) {
+    let Some(position) = manager.position(id).cloned() else {
+        return;
+    };
+    let faction = manager
+        .get::<Faction>(id)
+        .copied()
+        .unwrap_or(Faction::Neutral);
+    let ai = manager.get::<Ai>(id).unwrap();
+    let behavior = find_behavior(ai.behavior).unwrap_or(&AGGRESSIVE);
+    let previous_mode = ai.mode;
+    let home = ai.home.clone();
+    let attack_range = manager.get::<Combat>(id).map_or(0.0, |c| c.attack_range);
+    let health_fraction = manager.get::<Health>(id).map_or(1.0, |health| {
+        health.current as f64 / health.max.max(1) as f64
+    });
+
+    if behavior.patrols {
+        plan_patrol(manager, id);
+    }
+    let target = find_target(manager, huntable, id, faction, &position, behavior);
+    let situation = Situation {
+        target_distance: target.as_ref().map(|(_, _, distance)| *distance),
+        attack_range,
+        health_fraction,
+        distance_from_home: distance(&position, &home),
+        can_patrol: !manager.get::<Ai>(id).unwrap().patrol_route.is_empty(),
+    };
+    let mode = behavior.next_mode(previous_mode, &situation);
+
+    let ai = manager.get_mut::<Ai>(id).unwrap();
+    ai.mode = mode;
+    ai.target_entity_id = match mode {
+        AiMode::Chase | AiMode::Attack | AiMode::Flee => target.as_ref().map(|(id, _, _)| *id),
+        _ => None,
+    };
+
+    // A fight just started: bring the allies in
+    let engaged = |mode: AiMode| matches!(mode, AiMode::Chase | AiMode::Attack);
+    if engaged(mode) && !engaged(previous_mode) && behavior.help_range > 0.0 {
+        if let Some((target_id, _, _)) = target {
+            call_for_help(
+                manager,
+                id,
+                faction,
+                &position,
+                behavior.help_range,
+                target_id,
+            );
+        }
+    }
+
+    let budget = manager.effective_speed(id) * delta_time;
+    match (mode, target) {
+        (AiMode::Attack, Some((target_id, _, _))) => {
+            let attack = manager.attack_entity(id, target_id, current_time);
+            if attack.is_ok_and(|result| result.target_died) {
+                for grid in huntable.values_mut() {
+                    grid.remove(target_id);
+                }
+            }
+        }
+        (AiMode::Chase, Some((_, target_position, _))) => {
+            walk_towards(
+                manager,
+                huntable,
+                id,
+                &target_position,
+                attack_range,
+                budget,
+            );
+        }
+        (AiMode::Flee, Some((_, threat, _))) => {
+            let away = distance(&position, &threat).max(f64::EPSILON);
+            let destination = Vec2 {
+                x: position.x + (position.x - threat.x) / away * budget,
+                y: position.y + (position.y - threat.y) / away * budget,
+            };
+            step_to(manager, huntable, id, destination);
+        }
+        (AiMode::Return, _) => {
+            walk_towards(manager, huntable, id, &home, 0.0, budget);
+        }
+        (AiMode::Patrol, _) => {
+            let ai = manager.get::<Ai>(id).unwrap();
+            let mut index = ai.patrol_index % ai.patrol_route.len();
+            if distance(&position, &ai.patrol_route[index]) <= WAYPOINT_RADIUS {
+                index = (index + 1) % ai.patrol_route.len();
+            }
+            let waypoint = ai.patrol_route[index].clone();
+            manager.get_mut::<Ai>(id).unwrap().patrol_index = index;
+            walk_towards(manager, huntable, id, &waypoint, 0.0, budget);
+        }
+        _ => {
+            manager.insert(id, EntityState::Idle);
+            if let Some(navigation) = manager.navigation.as_mut() {
+                navigation.paths.clear(id);
+            }
+        }
+    }
+}
+
+/// The target to keep fighting, or else the nearest living hostile entity in sight.
+/// Returns its ID, position and distance.
+fn find_target(
+    manager: &EntityManager,
+    huntable: &HashMap<Faction, SpatialGrid>,
+    id: u64,
+    faction: Faction,
+    position: &Vec2,
+    behavior: &Behavior,
+) -> Option<(u64, Vec2, f64)> {
+    // A target that is already being fought is not forgotten as soon as it steps around a
+    // corner
+    let current = manager.get::<Ai>(id).unwrap().target_entity_id;
+    if let Some(target_id) = current {
+        let hostile = manager
+            .get::<Faction>(target_id)
+            .is_some_and(|&other| faction.is_hostile_to(other));
+        if let (true, true, Some(target_position)) = (
+            hostile,
+            manager.is_alive(target_id),
+            manager.position(target_id),
+        ) {
+            let distance = distance(position, target_position);
+            if distance <= behavior.detection_range * PURSUIT_FACTOR {
+                return Some((target_id, target_position.clone(), distance));
+            }
+        }
+    }
+
+    huntable
+        .iter()
+        .filter(|(prey, _)| faction.is_hostile_to(**prey))
+        .filter_map(|(_, grid)| {
+            let visible = |other| other != id && manager.can_see(id, other);
+            grid.k_nearest(position, 1, behavior.detection_range, visible)
+                .into_iter()
+                .next()
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+        .and_then(|(other, distance)| Some((other, manager.position(other)?.clone(), distance)))
+}
+
+/// Compute the patrol route from the room around home the first time it is needed
+fn plan_patrol(manager: &mut EntityManager, id: u64) {
+    let Some(navigation) = manager.navigation.as_ref() else {
+        return;
+    };
+    let ai = manager.get::<Ai>(id).unwrap();
+    if !ai.patrol_route.is_empty() {
+        return;
+    }
+    let Some(home) = tile_of(&ai.home) else {
+        return;
+    };
+    let route: Vec<Vec2> = room_waypoints(&navigation.map, home)
+        .into_iter()
+        .map(tile_center)
+        .collect();
+    manager.get_mut::<Ai>(id).unwrap().patrol_route = route;
+}
+
+/// Point idle allies within `range` at the target
+fn call_for_help(
+    manager: &mut EntityManager,
+    id: u64,
+    faction: Faction,
+    position: &Vec2,
+    range: f64,
+    target_id: u64,
+) {
+    let allies: Vec<u64> = manager
+        .get_entities_in_range(position, range)
+        .into_iter()
+        .filter(|&other| {
+            other != id
+                && manager.is_alive(other)
+                && manager.get::<Faction>(other) == Some(&faction)
+                && manager.get::<Ai>(other).is_some_and(|ai| {
+                    ai.target_entity_id.is_none()
+                        && matches!(ai.mode, AiMode::Idle | AiMode::Patrol)
+                })
+        })
+        .collect();
+
+    for ally in allies {
+        let ai = manager.get_mut::<Ai>(ally).unwrap();
+        ai.mode = AiMode::Chase;
+        ai.target_entity_id = Some(target_id);
+    }
+}
+
+/// Walk up to `budget` tiles towards `goal`, stopping `stop_distance` short of it
+fn walk_towards(
+    manager: &mut EntityManager,
+    huntable: &mut HashMap<Faction, SpatialGrid>,
+    id: u64,
+    goal: &Vec2,
+    stop_distance: f64,
+    budget: f64,
+) {
+    let Some(position) = manager.position(id).cloned() else {
+        return;
+    };
+    let distance = distance(&position, goal);
+    if budget <= 0.0 || distance <= stop_distance {
+        return;
+    }
+
+    let destination = match manager.navigation.as_mut() {
+        Some(navigation) => follow_path(navigation, id, &position, goal, stop_distance, budget),
+        None => {
+            // No map to walk on, head straight for the goal but never overshoot
+            let move_distance = budget.min(distance - stop_distance);
+            Some(Vec2 {
+                x: position.x + (goal.x - position.x) / distance * move_distance,
+                y: position.y + (goal.y - position.y) / distance * move_distance,
+            })
+        }
+    };
+    if let Some(destination) = destination {
+        step_to(manager, huntable, id, destination);
+    }
+}
+
+/// Move towards `destination` as far as walls and bodies allow
+fn step_to(
+    manager: &mut EntityManager,
+    huntable: &mut HashMap<Faction, SpatialGrid>,
+    id: u64,
+    destination: Vec2,
+) {
+    // Bodies in the way stop the entity or make it slide around them
+    let map = manager
+        .navigation
+        .as_ref()
+        .map(|navigation| &navigation.map);
+    let new_position = manager.swept_position(id, &destination, map);
+
+    if let Some(grid) = manager
+        .get::<Faction>(id)
+        .and_then(|faction| huntable.get_mut(faction))
+    {
+        grid.insert(id, &new_position);
+    }
+    manager.set_position(id, new_position);
+    manager.insert(id, EntityState::Moving);
+}
+
+/// Walk up to `budget` tiles along the path towards `target`, stopping in attack range.
+/// Returns `None` when the target can't be reached.
+fn follow_path(
+    navigation: &mut Navigation,
+    id: u64,
+    position: &Vec2,
+    target: &Vec2,
+    attack_range: f64,
+    mut budget: f64,
+) -> Option<Vec2> {
+    let goal = tile_of(target)?;
+    let mut position = position.clone();
+
+    // A step never crosses more than one tile, so this only bounds very long frames
+    for _ in 0..64 {
+        if budget <= 0.0 || distance(&position, target) <= attack_range {
+            break;
+        }
+
+        let from = tile_of(&position)?;
+        let waypoint = if from == goal {
+            target.clone()
+        } else {
+            let next = navigation.paths.next_waypoint(
+                id,
+                &navigation.map,
+                from,
+                goal,
+                &navigation.params,
+            )?;
+            tile_center(next)
+        };
+
+        let remaining = distance(&position, &waypoint);
+        if remaining <= 0.0 {
+            break;
+        }
+        let step = budget.min(remaining);
+        position.x += (waypoint.x - position.x) / remaining * step;
+        position.y += (waypoint.y - position.y) / remaining * step;
+        budget -= step;
+    }
+
+    Some(position)
+}
+
+/// Fly every projectile along its velocity, hitting hostile bodies on the way. Projectiles
+/// that hit a wall or run out of pierce expire on the spot.
+pub(super) fn advance_projectiles(manager: &mut EntityManager, delta_time: f64, current_time: f64) {
+    for id in manager.store::<Projectile>().ids() {
+        let expired = manager
+            .get::<Lifetime>(id)
+            .is_some_and(|lifetime| lifetime.expires_at <= current_time);
+        let Some(start) = manager.position(id).cloned().filter(|_| !expired) else {
+            continue;
+        };
+        let projectile = manager.get::<Projectile>(id).unwrap().clone();
+        let Some(ability) = find_ability(projectile.ability) else {
+            expire(manager, id, current_time);
+            continue;
+        };
+        let owner_id = manager.get::<Owner>(id).map_or(id, |owner| owner.entity_id);
+        let radius = manager.get::<Collider>(id).map_or(0.0, |c| c.radius);
+
+        let travel_x = projectile.velocity.x * delta_time;
+        let travel_y = projectile.velocity.y * delta_time;
+        let length = (travel_x * travel_x + travel_y * travel_y).sqrt();
+        let steps = (length / PROJECTILE_STEP).ceil().max(1.0) as u32;
+        let mut position = start;
+        let mut spent = false;
+
+        for _ in 0..steps {
+            let next = Vec2 {
+                x: position.x + travel_x / steps as f64,
+                y: position.y + travel_y / steps as f64,
+            };
+            let hits_wall = manager
+                .navigation
+                .as_ref()
+                .is_some_and(|navigation| circle_hits_wall(&navigation.map, &next, radius));
+            if hits_wall {
+                spent = true;
+                break;
+            }
+            position = next;
+
+            for target_id in bodies_hit(manager, id, owner_id, &position, radius) {
+                let landed = manager.hit_with_ability(owner_id, target_id, ability, current_time);
+                let projectile = manager.get_mut::<Projectile>(id).unwrap();
+                projectile.hit_entity_ids.push(target_id);
+                // Dodged projectiles fly on
+                if !landed {
+                    continue;
+                }
+                if projectile.pierce == 0 {
+                    spent = true;
+                    break;
+                }
+                projectile.pierce -= 1;
+            }
+            if spent {
+                break;
+            }
+        }
+
+        manager.set_position(id, position);
+        if spent {
+            expire(manager, id, current_time);
+        }
+    }
+}
+
+/// Living bodies hostile to a projectile that it touches at `position`, nearest first
+fn bodies_hit(
+    manager: &EntityManager,
+    id: u64,
+    owner_id: u64,
+    position: &Vec2,
+    radius: f64,
+) -> Vec<u64> {
+    let projectile = manager.get::<Projectile>(id).unwrap();
+    let mut hits: Vec<(u64, f64)> = manager
+        .get_entities_in_range(position, radius + MAX_COLLIDER_RADIUS)
+        .into_iter()
+        .filter(|&other| {
+            other != id
+                && other != owner_id
+                && manager.has::<Health>(other)
+                && manager.is_alive(other)
+                && !projectile.hit_entity_ids.contains(&other)
+                && manager
+                    .get::<Faction>(other)
+                    .is_some_and(|&faction| projectile.faction.is_hostile_to(faction))
+        })
+        .filter_map(|other| {
+            let distance = distance(position, manager.position(other)?);
+            let reach = radius
+                + manager
+                    .get::<Collider>(other)
+                    .map_or(0.0, |c| c.radius.min(MAX_COLLIDER_RADIUS));
+            (distance <= reach).then_some((other, distance))
+        })
+        .collect();
+    hits.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    hits.into_iter().map(|(other, _)| other).collect()
+}
+
+/// Stop a projectile and let it be cleaned up with the other expired entities
+fn expire(manager: &mut EntityManager, id: u64, current_time: f64) {
+    if let Some(projectile) = manager.get_mut::<Projectile>(id) {
+        projectile.velocity = Vec2 { x: 0.0, y: 0.0 };
+    }
+    manager.insert(
+        id,
+        Lifetime {
+            expires_at: current_time,
+        },
+    );
+}
+
+fn distance(a: &Vec2, b: &Vec2) -> f64 {
+    let dx = a.x - b.x;
+    let dy = a.y - b.y;
+    (dx * dx + dy * dy).sqrt()
+}
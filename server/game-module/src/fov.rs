@@ -0,0 +1,224 @@
+//! Field of view and line of sight over a map's collision layer.
+//!
+//! Field of view uses symmetric shadowcasting: if tile B is visible from A, then A is
+//! visible from B, which keeps monster detection and player vision consistent.
+
+use crate::map::Map;
+use crate::pathfinding::Tile;
+use std::collections::HashSet;
+
+/// A slope as an exact fraction, `numerator / denominator` with a positive denominator
+#[derive(Clone, Copy, Debug)]
+struct Slope {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Slope {
+    fn new(numerator: i64, denominator: i64) -> Self {
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+}
+
+/// One row of tiles at `depth` from the origin, between two slopes
+#[derive(Clone, Copy, Debug)]
+struct Row {
+    depth: i64,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    /// Columns of the tiles in this row: start and end slopes rounded towards the centre
+    fn columns(&self) -> std::ops::RangeInclusive<i64> {
+        // round_ties_up(depth * start) and round_ties_down(depth * end)
+        let min = (2 * self.depth * self.start.numerator + self.start.denominator)
+            .div_euclid(2 * self.start.denominator);
+        let max = -(-(2 * self.depth * self.end.numerator - self.end.denominator))
+            .div_euclid(2 * self.end.denominator);
+        min..=max
+    }
+
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+
+    /// Floor tiles are only visible when their centre lies within the row's slopes
+    fn is_symmetric(&self, column: i64) -> bool {
+        column * self.start.denominator >= self.depth * self.start.numerator
+            && column * self.end.denominator <= self.depth * self.end.numerator
+    }
+}
+
+/// Slope through the near edge of a tile
+fn slope(depth: i64, column: i64) -> Slope {
+    Slope::new(2 * column - 1, 2 * depth)
+}
+
+/// Map a (depth, column) pair of one of the four quadrants to map coordinates
+fn transform(origin: Tile, quadrant: u8, depth: i64, column: i64) -> (i64, i64) {
+    let (x, y) = (origin.0 as i64, origin.1 as i64);
+    match quadrant {
+        0 => (x + column, y - depth), // North
+        1 => (x + depth, y + column), // East
+        2 => (x + column, y + depth), // South
+        _ => (x - depth, y + column), // West
+    }
+}
+
+fn opaque_at(map: &Map, (x, y): (i64, i64)) -> bool {
+    x < 0 || y < 0 || map.is_opaque(x as usize, y as usize)
+}
+
+/// Every tile visible from `origin` within `radius` tiles, including the walls that block
+/// the view
+pub fn compute_fov(map: &Map, origin: Tile, radius: u32) -> HashSet<Tile> {
+    let mut visible = HashSet::new();
+    visible.insert(origin);
+    let radius = radius as i64;
+
+    for quadrant in 0..4 {
+        let mut rows = vec![Row {
+            depth: 1,
+            start: Slope::new(-1, 1),
+            end: Slope::new(1, 1),
+        }];
+
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+
+            let mut previous_opaque: Option<bool> = None;
+            for column in row.columns() {
+                let tile = transform(origin, quadrant, row.depth, column);
+                let opaque = opaque_at(map, tile);
+                let in_radius = row.depth * row.depth + column * column <= radius * radius;
+
+                if in_radius && tile.0 >= 0 && tile.1 >= 0 && (opaque || row.is_symmetric(column)) {
+                    visible.insert((tile.0 as usize, tile.1 as usize));
+                }
+                if previous_opaque == Some(true) && !opaque {
+                    row.start = slope(row.depth, column);
+                }
+                if previous_opaque == Some(false) && opaque {
+                    let mut next = row.next();
+                    next.end = slope(row.depth, column);
+                    rows.push(next);
+                }
+                previous_opaque = Some(opaque);
+            }
+            if previous_opaque == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+
+    visible
+}
+
+/// Tiles a straight line from `from` to `to` passes through, endpoints excluded
+fn line_between(from: Tile, to: Tile) -> Vec<Tile> {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let dx = (x1 - x).abs();
+    let dy = -(y1 - y).abs();
+    let step_x = if x < x1 { 1 } else { -1 };
+    let step_y = if y < y1 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut tiles = Vec::new();
+
+    loop {
+        if (x, y) == (x1, y1) {
+            break;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+        if (x, y) != (x1, y1) {
+            tiles.push((x as usize, y as usize));
+        }
+    }
+    tiles
+}
+
+/// Whether nothing opaque stands between two tiles. Symmetric: a line is tried in both
+/// directions so A sees B exactly when B sees A.
+pub fn has_line_of_sight(map: &Map, from: Tile, to: Tile) -> bool {
+    let clear = |a: Tile, b: Tile| {
+        line_between(a, b)
+            .into_iter()
+            .all(|(x, y)| !map.is_opaque(x, y))
+    };
+    clear(from, to) || clear(to, from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pathfinding::tests::map_from;
+
+    #[test]
+    fn test_walls_block_the_view() {
+        let map = map_from(&[
+            "#########", //
+            "#.......#", //
+            "#...#...#", //
+            "#.......#", //
+            "#########",
+        ]);
+        let visible = compute_fov(&map, (2, 2), 10);
+
+        assert!(visible.contains(&(3, 2)));
+        assert!(visible.contains(&(4, 2)), "The wall itself is visible");
+        assert!(
+            !visible.contains(&(6, 2)),
+            "Tiles behind the pillar are hidden"
+        );
+        assert!(visible.contains(&(5, 1)));
+        assert!(visible.contains(&(0, 0)));
+
+        assert!(!has_line_of_sight(&map, (2, 2), (6, 2)));
+        assert!(has_line_of_sight(&map, (2, 2), (5, 1)));
+    }
+
+    #[test]
+    fn test_fov_is_symmetric_and_limited_by_radius() {
+        let map = map_from(&[
+            "############", //
+            "#....#.....#", //
+            "#.##.....#.#", //
+            "#....#..#..#", //
+            "#.#......#.#", //
+            "############",
+        ]);
+        let floors: Vec<Tile> = (0..map.height as usize)
+            .flat_map(|y| (0..map.width as usize).map(move |x| (x, y)))
+            .filter(|&(x, y)| !map.is_opaque(x, y))
+            .collect();
+
+        for &a in &floors {
+            let from_a = compute_fov(&map, a, 20);
+            for &b in &floors {
+                let from_b = compute_fov(&map, b, 20);
+                assert_eq!(from_a.contains(&b), from_b.contains(&a), "{:?} {:?}", a, b);
+                assert_eq!(has_line_of_sight(&map, a, b), has_line_of_sight(&map, b, a));
+            }
+        }
+
+        let near = compute_fov(&map, (6, 1), 2);
+        assert!(!near.contains(&(10, 1)));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod collision;
+pub mod combat;
 pub mod entity;
+pub mod fov;
 pub mod map;
 pub mod map_generator;
+pub mod pathfinding;
+pub mod rotation;
+pub mod secrets;
@@ -1,3 +1,5 @@
+use crate::map_generator::LayerProp;
+
 // Type definitions that match the SpacetimeDB table structures
 #[derive(Clone, Debug)]
 pub struct Vec2 {
@@ -11,6 +13,7 @@ pub enum MapType {
     Town,
     Wilderness,
     Instance,
+    Interior,
 }
 
 #[derive(Clone, Debug)]
@@ -20,7 +23,10 @@ pub struct Map {
     pub map_type: MapType,
     pub width: u64,
     pub height: u64,
-    pub tiles: Vec<u8>, // Flattened 2D array: tiles[y * width + x] = tile_type (0=Wall, 1=Floor, 2=Door)
+    pub tiles: Vec<u8>, // Collision layer, flattened 2D array: tiles[y * width + x] = tile_type (0=Wall, 1=Floor, 2=Door)
+    pub terrain: Vec<u8>, // Terrain layer, same layout as tiles
+    pub decorations: Vec<LayerProp>, // Sparse decoration layer
+    pub objects: Vec<LayerProp>, // Sparse interactable object layer
     pub spawn_position: Vec2, // Primary spawn position
     pub spawn_points: Vec<Vec2>, // All possible spawn points
     pub is_starting_town: bool, // Whether this is the main starting town (only relevant for towns)
@@ -41,7 +47,32 @@ impl Map {
         self.tiles.get(index).copied()
     }
 
-    /// Check if a position is walkable (Floor or Door)
+    /// Get terrain type at the given coordinates
+    pub fn get_terrain(&self, x: usize, y: usize) -> Option<u8> {
+        if x >= self.width as usize || y >= self.height as usize {
+            return None;
+        }
+        let index = y * (self.width as usize) + x;
+        self.terrain.get(index).copied()
+    }
+
+    /// Get all decorations placed on the given tile
+    pub fn get_decorations_at(&self, x: usize, y: usize) -> Vec<&LayerProp> {
+        self.decorations
+            .iter()
+            .filter(|prop| prop.position.x == x && prop.position.y == y)
+            .collect()
+    }
+
+    /// Get the interactable object placed on the given tile, if any
+    pub fn get_object_at(&self, x: usize, y: usize) -> Option<&LayerProp> {
+        self.objects
+            .iter()
+            .find(|prop| prop.position.x == x && prop.position.y == y)
+    }
+
+    /// Check if a position is walkable (Floor or Door).
+    /// Only the collision layer is consulted; decorations and objects never block.
     pub fn is_walkable(&self, x: usize, y: usize) -> bool {
         match self.get_tile(x, y) {
             Some(1) | Some(2) => true, // Floor or Door
@@ -49,6 +80,11 @@ impl Map {
         }
     }
 
+    /// Check if a tile blocks sight. Walls and anything outside the map are opaque.
+    pub fn is_opaque(&self, x: usize, y: usize) -> bool {
+        !self.is_walkable(x, y)
+    }
+
     /// Get all valid spawn points in the map
     pub fn get_spawn_positions(&self) -> &Vec<Vec2> {
         &self.spawn_points
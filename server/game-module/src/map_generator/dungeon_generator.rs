@@ -1,6 +1,8 @@
 use spacetimedb::rand::rngs::StdRng;
 use spacetimedb::rand::{Rng, SeedableRng};
 
+use crate::map_generator::generator::{HiddenArea, LootSpot, MonsterSpot};
+use crate::map_generator::layers::{LayerProp, MapLayers, ObjectType, TerrainType};
 use crate::map_generator::room::Room;
 use crate::map_generator::room_manager::RoomManager;
 use crate::map_generator::types::{Position, TileType};
@@ -11,10 +13,15 @@ pub struct DungeonGenerator {
     pub room_width: usize,
     pub room_height: usize,
     pub central_room_multiplier: usize,
-    pub map: Vec<Vec<u8>>,
+    pub map: Vec<Vec<u8>>,     // Collision layer
+    pub terrain: Vec<Vec<u8>>, // Terrain layer
+    pub decorations: Vec<LayerProp>,
+    pub objects: Vec<LayerProp>,
     pub rooms: Vec<Room>,
     pub room_grid: Vec<Vec<Option<usize>>>, // Grid indicating which room occupies each cell
     pub spawn_points: Vec<Position>,        // List of possible spawn points at map edges
+    pub secret_passages: Vec<Position>,     // Secret door tiles that look like walls to clients
+    pub secret_door_chance: f64,            // Chance for an optional connection to become secret
     pub rng: StdRng,
     pub room_manager: RoomManager,
 }
@@ -36,6 +43,7 @@ impl DungeonGenerator {
         let height = room_height + (rooms_height - 1) * (room_height - 1);
 
         let map = vec![vec![TileType::Wall as u8; width]; height];
+        let terrain = vec![vec![TerrainType::Stone as u8; width]; height];
         let room_grid = vec![vec![None; rooms_width]; rooms_height];
 
         DungeonGenerator {
@@ -45,9 +53,14 @@ impl DungeonGenerator {
             room_height,
             central_room_multiplier,
             map,
+            terrain,
+            decorations: Vec::new(),
+            objects: Vec::new(),
             rooms: Vec::new(),
             room_grid,
             spawn_points: Vec::new(),
+            secret_passages: Vec::new(),
+            secret_door_chance: 0.0,
             rng: StdRng::seed_from_u64(seed),
             room_manager: RoomManager::for_dungeons(),
         }
@@ -58,9 +71,15 @@ impl DungeonGenerator {
         self.render_map(); // Render rooms first
         self.connect_rooms(); // Then place doors on top
         self.generate_spawn_points(); // Generate spawn points at map edges
+        self.collect_secret_passages(); // Record every secret door, including template alcoves
         self.map.clone()
     }
 
+    /// Set the chance (0.0 - 1.0) for an optional connection to become a secret door
+    pub fn set_secret_door_chance(&mut self, chance: f64) {
+        self.secret_door_chance = chance.clamp(0.0, 1.0);
+    }
+
     /// Set a specific central room template to use
     pub fn set_central_room_template(&mut self, template_name: &str) -> Result<(), String> {
         self.room_manager.set_central_room(template_name)
@@ -278,10 +297,85 @@ impl DungeonGenerator {
             }
         }
 
-        // Then create all connections
-        for (i, j, conn_points) in all_connections {
-            self.create_connection(i, j, &conn_points);
+        // Hide some of the connections that are not needed to reach every room
+        let secret = self.select_secret_connections(&all_connections);
+
+        // Then create all connections. Secret doors go first so a visible door sharing
+        // the same tile always wins and never gets hidden by accident.
+        let (secret_connections, open_connections): (Vec<_>, Vec<_>) = all_connections
+            .into_iter()
+            .zip(secret)
+            .partition(|(_, is_secret)| *is_secret);
+        for ((i, j, conn_points), is_secret) in
+            secret_connections.into_iter().chain(open_connections)
+        {
+            self.create_connection(i, j, &conn_points, is_secret);
+        }
+    }
+
+    /// Decide which connections become secret doors.
+    ///
+    /// A connection can only be hidden if its two rooms stay reachable from each other
+    /// through the remaining visible connections, so secret doors never lock off a room.
+    fn select_secret_connections(
+        &mut self,
+        connections: &[(usize, usize, Vec<Position>)],
+    ) -> Vec<bool> {
+        let mut secret = vec![false; connections.len()];
+        if self.secret_door_chance <= 0.0 {
+            return secret;
+        }
+
+        for idx in 0..connections.len() {
+            if !self.rng.gen_bool(self.secret_door_chance) {
+                continue;
+            }
+
+            secret[idx] = true;
+            let (room1, room2, _) = &connections[idx];
+            if !Self::rooms_reachable(self.rooms.len(), connections, &secret, *room1, *room2) {
+                secret[idx] = false;
+            }
         }
+
+        secret
+    }
+
+    /// Check whether `to` can be reached from `from` using only visible connections
+    fn rooms_reachable(
+        room_count: usize,
+        connections: &[(usize, usize, Vec<Position>)],
+        secret: &[bool],
+        from: usize,
+        to: usize,
+    ) -> bool {
+        let mut visited = vec![false; room_count];
+        let mut stack = vec![from];
+        visited[from] = true;
+
+        while let Some(room) = stack.pop() {
+            if room == to {
+                return true;
+            }
+            for (idx, (a, b, _)) in connections.iter().enumerate() {
+                if secret[idx] {
+                    continue;
+                }
+                let next = if *a == room {
+                    *b
+                } else if *b == room {
+                    *a
+                } else {
+                    continue;
+                };
+                if !visited[next] {
+                    visited[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+
+        false
     }
 
     fn find_connection_points(&self, room1: &Room, room2: &Room) -> Vec<Position> {
@@ -312,14 +406,20 @@ impl DungeonGenerator {
         _room1_idx: usize,
         _room2_idx: usize,
         conn_points: &[Position],
+        is_secret: bool,
     ) {
+        let door = if is_secret {
+            TileType::SecretDoor
+        } else {
+            TileType::Door
+        };
+
         // Connect ALL adjacent connection points (every pair represents one door)
         // conn_points come in pairs: [pos1, pos2, pos3, pos4, ...] where (pos1, pos2) is one connection
         for i in (0..conn_points.len()).step_by(2) {
             if let (Some(pos1), Some(pos2)) = (conn_points.get(i), conn_points.get(i + 1)) {
-                // Always create doors for full connectivity
-                self.set_tile(pos1.x, pos1.y, TileType::Door);
-                self.set_tile(pos2.x, pos2.y, TileType::Door);
+                self.set_tile(pos1.x, pos1.y, door);
+                self.set_tile(pos2.x, pos2.y, door);
             }
         }
     }
@@ -338,6 +438,9 @@ impl DungeonGenerator {
             }
         }
 
+        self.decorations.clear();
+        self.objects.clear();
+
         // Render all rooms
         for room in &self.rooms {
             for (row_idx, row) in room.tiles.iter().enumerate() {
@@ -347,9 +450,17 @@ impl DungeonGenerator {
 
                     if global_x < self.width && global_y < self.height {
                         self.map[global_y][global_x] = tile as u8;
+                        self.terrain[global_y][global_x] = room.terrain[row_idx][col_idx] as u8;
                     }
                 }
             }
+
+            let in_bounds =
+                |prop: &LayerProp| prop.position.x < self.width && prop.position.y < self.height;
+            self.decorations
+                .extend(room.get_global_decorations().into_iter().filter(in_bounds));
+            self.objects
+                .extend(room.get_global_objects().into_iter().filter(in_bounds));
         }
     }
 
@@ -441,6 +552,18 @@ impl DungeonGenerator {
         }
     }
 
+    fn collect_secret_passages(&mut self) {
+        self.secret_passages.clear();
+
+        for (y, row) in self.map.iter().enumerate() {
+            for (x, &tile) in row.iter().enumerate() {
+                if TileType::from(tile) == TileType::SecretDoor {
+                    self.secret_passages.push(Position { x, y });
+                }
+            }
+        }
+    }
+
     pub fn get_central_room_position(&self) -> Option<Position> {
         self.rooms
             .iter()
@@ -456,6 +579,164 @@ impl DungeonGenerator {
         &self.spawn_points
     }
 
+    /// Get the terrain, decoration and object layers of the map
+    pub fn get_layers(&self) -> MapLayers {
+        MapLayers {
+            terrain: self.terrain.iter().flatten().copied().collect(),
+            decorations: self.decorations.clone(),
+            objects: self.objects.clone(),
+        }
+    }
+
+    /// Get all secret door tiles in the map
+    pub fn get_secret_passages(&self) -> &Vec<Position> {
+        &self.secret_passages
+    }
+
+    /// Get the floor that only secret passages lead to, like the alcoves of room templates.
+    /// Secret doors between rooms that are connected anyway hide nothing.
+    pub fn get_hidden_areas(&self) -> Vec<HiddenArea> {
+        // Label the connected walkable regions, counting secret doors as walls
+        let mut labels = vec![vec![None; self.width]; self.height];
+        let mut sizes: Vec<usize> = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if labels[y][x].is_some() || !self.is_open(x, y) {
+                    continue;
+                }
+                let label = sizes.len();
+                let mut size = 0;
+                let mut stack = vec![Position { x, y }];
+                labels[y][x] = Some(label);
+                while let Some(pos) = stack.pop() {
+                    size += 1;
+                    for next in self.neighbors(pos) {
+                        if labels[next.y][next.x].is_none() && self.is_open(next.x, next.y) {
+                            labels[next.y][next.x] = Some(label);
+                            stack.push(next);
+                        }
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+
+        // Everything but the largest region is hidden if a secret passage leads into it
+        let Some(main) = (0..sizes.len()).max_by_key(|&label| sizes[label]) else {
+            return Vec::new();
+        };
+        let mut passages: Vec<Vec<Position>> = vec![Vec::new(); sizes.len()];
+        for &passage in &self.secret_passages {
+            for next in self.neighbors(passage) {
+                if let Some(label) = labels[next.y][next.x] {
+                    if label != main && !passages[label].contains(&passage) {
+                        passages[label].push(passage);
+                    }
+                }
+            }
+        }
+
+        let mut areas: Vec<Option<HiddenArea>> = passages
+            .into_iter()
+            .map(|passages| {
+                (!passages.is_empty()).then(|| HiddenArea {
+                    passages,
+                    tiles: Vec::new(),
+                })
+            })
+            .collect();
+        for (y, row) in labels.iter().enumerate() {
+            for (x, label) in row.iter().enumerate() {
+                if let Some(Some(area)) = label.map(|label| &mut areas[label]) {
+                    area.tiles.push(Position { x, y });
+                }
+            }
+        }
+        areas.into_iter().flatten().collect()
+    }
+
+    /// Check if a tile can be walked on without discovering anything
+    fn is_open(&self, x: usize, y: usize) -> bool {
+        matches!(
+            TileType::from(self.map[y][x]),
+            TileType::Floor | TileType::Door
+        )
+    }
+
+    /// The tiles next to a tile, inside the map
+    fn neighbors(&self, pos: Position) -> impl Iterator<Item = Position> {
+        let (width, height) = (self.width, self.height);
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .filter_map(move |(dx, dy): (i64, i64)| {
+                let x = pos.x as i64 + dx;
+                let y = pos.y as i64 + dy;
+                let inside = x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height;
+                inside.then_some(Position {
+                    x: x as usize,
+                    y: y as usize,
+                })
+            })
+    }
+
+    /// Get the chests of every room type that has loot, with the table to fill them from
+    pub fn get_loot_spots(&self) -> Vec<LootSpot> {
+        self.rooms
+            .iter()
+            .filter_map(|room| Some((room, room.room_type.loot_table()?)))
+            .flat_map(|(room, table)| {
+                room.get_global_objects()
+                    .into_iter()
+                    .filter(|object| ObjectType::from_u8(object.kind) == Some(ObjectType::Chest))
+                    .filter(|object| {
+                        object.position.x < self.width && object.position.y < self.height
+                    })
+                    .map(move |object| LootSpot {
+                        position: object.position,
+                        table,
+                    })
+            })
+            .collect()
+    }
+
+    /// Get a guard post for every monster of every room, spread out over the room's free
+    /// floor tiles
+    pub fn get_monster_spots(&self) -> Vec<MonsterSpot> {
+        let mut spots = Vec::new();
+        for room in &self.rooms {
+            let archetypes = room.room_type.monsters();
+            if archetypes.is_empty() {
+                continue;
+            }
+            let objects = room.get_global_objects();
+            let mut floor = Vec::new();
+            for y in room.position.y..(room.position.y + room.height).min(self.height) {
+                for x in room.position.x..(room.position.x + room.width).min(self.width) {
+                    let position = Position { x, y };
+                    if self.map[y][x] == TileType::Floor as u8
+                        && !objects.iter().any(|object| object.position == position)
+                    {
+                        floor.push(position);
+                    }
+                }
+            }
+            if floor.is_empty() {
+                continue;
+            }
+            let count = archetypes.len();
+            spots.extend(
+                archetypes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &archetype)| MonsterSpot {
+                        position: floor[(2 * index + 1) * floor.len() / (2 * count)],
+                        archetype,
+                    }),
+            );
+        }
+        spots
+    }
+
     /// Get a random spawn point from the available spawn points
     pub fn get_random_spawn_point(&self) -> Option<Position> {
         if self.spawn_points.is_empty() {
@@ -582,4 +863,144 @@ mod tests {
             generator.spawn_points.len()
         );
     }
+
+    /// Count the floor tiles reachable from `start` through floors and visible doors
+    fn reachable_floor_count(map: &[Vec<u8>], start: Position) -> usize {
+        let mut visited = vec![vec![false; map[0].len()]; map.len()];
+        let mut stack = vec![start];
+        let mut count = 0;
+
+        while let Some(pos) = stack.pop() {
+            if visited[pos.y][pos.x] {
+                continue;
+            }
+            visited[pos.y][pos.x] = true;
+            match TileType::from(map[pos.y][pos.x]) {
+                TileType::Floor => count += 1,
+                TileType::Door => {}
+                _ => continue,
+            }
+
+            if pos.x > 0 {
+                stack.push(Position {
+                    x: pos.x - 1,
+                    y: pos.y,
+                });
+            }
+            if pos.y > 0 {
+                stack.push(Position {
+                    x: pos.x,
+                    y: pos.y - 1,
+                });
+            }
+            if pos.x + 1 < map[0].len() {
+                stack.push(Position {
+                    x: pos.x + 1,
+                    y: pos.y,
+                });
+            }
+            if pos.y + 1 < map.len() {
+                stack.push(Position {
+                    x: pos.x,
+                    y: pos.y + 1,
+                });
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn test_hidden_areas_cover_the_floor_behind_alcove_doors() {
+        let mut generator = DungeonGenerator::new(1, 1, 20, 20, 1, 1);
+        // A hall with a secret door into an alcove and one that leads nowhere new
+        let layout = [
+            "############",
+            "#........H..",
+            "#........#..",
+            "#........###",
+            "#.##H#######",
+            "#..........#",
+            "############",
+        ];
+        for (y, row) in layout.iter().enumerate() {
+            for (x, glyph) in row.chars().enumerate() {
+                generator.map[y][x] = match glyph {
+                    '.' => TileType::Floor,
+                    'H' => TileType::SecretDoor,
+                    _ => TileType::Wall,
+                } as u8;
+            }
+        }
+        generator.collect_secret_passages();
+
+        let areas = generator.get_hidden_areas();
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].passages, vec![Position { x: 9, y: 1 }]);
+        assert_eq!(areas[0].tiles.len(), 4);
+        assert!(areas[0].contains(&Position { x: 11, y: 2 }));
+        assert!(!areas[0].contains(&Position { x: 5, y: 5 }));
+    }
+
+    #[test]
+    fn test_secret_doors_only_hide_optional_connections() {
+        let mut generator = DungeonGenerator::new(5, 5, 20, 20, 2, 777);
+        generator.set_secret_door_chance(1.0);
+        let map = generator.generate();
+
+        assert!(
+            !generator.get_secret_passages().is_empty(),
+            "Some connections should have become secret doors"
+        );
+        for pos in generator.get_secret_passages() {
+            assert_eq!(TileType::from(map[pos.y][pos.x]), TileType::SecretDoor);
+        }
+
+        // Hidden alcoves from templates are meant to be cut off, so leave them hidden
+        let template_secrets: Vec<Position> = generator
+            .rooms
+            .iter()
+            .flat_map(|room| {
+                room.tiles.iter().enumerate().flat_map(move |(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_, &tile)| tile == TileType::SecretDoor)
+                        .map(move |(x, _)| Position {
+                            x: room.position.x + x,
+                            y: room.position.y + y,
+                        })
+                })
+            })
+            .collect();
+
+        let mut revealed_map = map.clone();
+        for pos in generator.get_secret_passages() {
+            if !template_secrets.contains(pos) {
+                revealed_map[pos.y][pos.x] = TileType::Door as u8;
+            }
+        }
+
+        // Every floor reachable once the secret connections are found must already be reachable
+        let center = generator.get_central_room_position().unwrap();
+        assert_eq!(
+            reachable_floor_count(&map, center),
+            reachable_floor_count(&revealed_map, center),
+            "Secret doors must not cut rooms off from the rest of the dungeon"
+        );
+    }
+
+    #[test]
+    fn test_no_secret_doors_by_default() {
+        let mut generator = DungeonGenerator::new(4, 4, 20, 20, 2, 99);
+        generator.generate();
+
+        // Only hidden alcoves from templates may produce secret tiles
+        let template_secrets: usize = generator
+            .rooms
+            .iter()
+            .flat_map(|room| room.tiles.iter().flatten())
+            .filter(|&&tile| tile == TileType::SecretDoor)
+            .count();
+        assert_eq!(generator.get_secret_passages().len(), template_secrets);
+    }
 }
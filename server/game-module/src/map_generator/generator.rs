@@ -1,6 +1,8 @@
 use crate::map_generator::dungeon_generator::DungeonGenerator;
+use crate::map_generator::layers::{LayerProp, MapLayers, ObjectType, TerrainType};
+use crate::map_generator::room_templates::{building_interiors, find_interior_template};
 use crate::map_generator::town_generator::TownGenerator;
-use crate::map_generator::types::Position;
+use crate::map_generator::types::{Position, TileType};
 
 /// Enum representing different types of maps that can be generated
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,6 +11,7 @@ pub enum MapType {
     Town,
     Wilderness,
     Instance,
+    Interior,
 }
 
 /// Parameters for dungeon generation
@@ -20,6 +23,7 @@ pub struct DungeonParams {
     pub room_height: usize,
     pub central_room_multiplier: usize,
     pub central_room_template: Option<String>,
+    pub secret_door_chance: f64, // Chance for an optional connection to become a secret door
 }
 
 impl Default for DungeonParams {
@@ -31,6 +35,7 @@ impl Default for DungeonParams {
             room_height: 20,
             central_room_multiplier: 2,
             central_room_template: None,
+            secret_door_chance: 0.15,
         }
     }
 }
@@ -80,13 +85,68 @@ pub struct MapGenerationResult {
     pub name: String,
     pub width: usize,
     pub height: usize,
-    pub tiles: Vec<u8>, // Flattened 2D array
+    pub tiles: Vec<u8>,    // Flattened 2D collision layer
+    pub layers: MapLayers, // Terrain, decoration and object layers
     pub spawn_position: Position,
     pub spawn_points: Vec<Position>,
+    pub secret_passages: Vec<Position>, // Secret door tiles, disguised as walls until discovered
+    pub hidden_areas: Vec<HiddenArea>,  // Floor only secret passages lead to
+    pub entrances: Vec<Entrance>,       // Tiles that lead to another map
+    pub loot_spots: Vec<LootSpot>,      // Chests to fill with loot
+    pub monster_spots: Vec<MonsterSpot>, // Where monsters keep respawning
     pub is_starting_town: bool,
     pub metadata: MapMetadata,
 }
 
+impl MapGenerationResult {
+    /// Get the positions of every portal object, the ends of links to other maps
+    pub fn portals(&self) -> Vec<Position> {
+        self.layers
+            .objects
+            .iter()
+            .filter(|o| ObjectType::from_u8(o.kind) == Some(ObjectType::Portal))
+            .map(|o| o.position)
+            .collect()
+    }
+}
+
+/// A tile that moves whoever steps on it to another map
+#[derive(Debug, Clone)]
+pub struct Entrance {
+    pub position: Position,
+    pub arrival: Position, // Where travellers arrive when coming back through this entrance
+    pub interior: Option<&'static str>, // Interior template behind the entrance, if any
+}
+
+/// Walkable tiles cut off from the rest of the map until one of the secret passages into them
+/// is discovered
+#[derive(Debug, Clone)]
+pub struct HiddenArea {
+    pub passages: Vec<Position>, // Secret doors leading into the area
+    pub tiles: Vec<Position>,
+}
+
+impl HiddenArea {
+    /// Check if a tile lies in the area
+    pub fn contains(&self, position: &Position) -> bool {
+        self.tiles.contains(position)
+    }
+}
+
+/// A chest and the loot table to fill it from
+#[derive(Debug, Clone)]
+pub struct LootSpot {
+    pub position: Position,
+    pub table: &'static str, // Name of a table in `entity::loot::ALL_LOOT_TABLES`
+}
+
+/// A floor tile a monster of some archetype guards
+#[derive(Debug, Clone)]
+pub struct MonsterSpot {
+    pub position: Position,
+    pub archetype: &'static str, // Name of an archetype in `entity::prefabs::ALL_ARCHETYPES`
+}
+
 /// Additional metadata about the generated map
 #[derive(Debug, Clone)]
 pub struct MapMetadata {
@@ -112,6 +172,9 @@ impl Generator {
             MapType::Town => Self::generate_town_map(name, seed, params.town),
             MapType::Wilderness => Self::generate_wilderness_map(name, seed, params.wilderness),
             MapType::Instance => Err("Instance generation not yet implemented".to_string()),
+            MapType::Interior => {
+                Err("Interiors are generated from a template with generate_interior".to_string())
+            }
         }?;
 
         // Set generation time to None since timing is not available in WASM
@@ -181,6 +244,7 @@ impl Generator {
         if let Some(template) = &params.central_room_template {
             dungeon_gen.set_central_room_template(template)?;
         }
+        dungeon_gen.set_secret_door_chance(params.secret_door_chance);
 
         let map = dungeon_gen.generate();
         let spawn_position = dungeon_gen.get_best_spawn_point().unwrap_or(Position {
@@ -188,10 +252,18 @@ impl Generator {
             y: dungeon_gen.height / 2,
         });
         let spawn_points = dungeon_gen.get_spawn_points().clone();
+        let secret_passages = dungeon_gen.get_secret_passages().clone();
+        let hidden_areas = dungeon_gen.get_hidden_areas();
+        let loot_spots = dungeon_gen.get_loot_spots();
+        let monster_spots = dungeon_gen.get_monster_spots();
+        let mut layers = dungeon_gen.get_layers();
 
         // Flatten the 2D map into 1D
         let tiles: Vec<u8> = map.into_iter().flatten().collect();
 
+        // The way back out sits right next to where players arrive
+        Self::place_portal(&tiles, dungeon_gen.width, &mut layers, spawn_position);
+
         let mut special_features = Vec::new();
         if params.central_room_template.is_some() {
             special_features.push("Central Room Template".to_string());
@@ -202,6 +274,9 @@ impl Generator {
                 central_pos.x, central_pos.y
             ));
         }
+        if !secret_passages.is_empty() {
+            special_features.push(format!("{} Secret Passages", secret_passages.len()));
+        }
 
         Ok(MapGenerationResult {
             map_type: MapType::Dungeon,
@@ -209,8 +284,14 @@ impl Generator {
             width: dungeon_gen.width,
             height: dungeon_gen.height,
             tiles,
+            layers,
             spawn_position,
             spawn_points,
+            secret_passages,
+            hidden_areas,
+            entrances: Vec::new(),
+            loot_spots,
+            monster_spots,
             is_starting_town: false,
             metadata: MapMetadata {
                 room_count: dungeon_gen.rooms.len(),
@@ -235,6 +316,8 @@ impl Generator {
             y: town_gen.height / 2,
         });
         let spawn_points = town_gen.get_spawn_points().clone();
+        let layers = town_gen.get_layers();
+        let entrances = Self::collect_entrances(&town_gen);
 
         // Flatten the 2D map into 1D
         let tiles: Vec<u8> = map.into_iter().flatten().collect();
@@ -244,6 +327,9 @@ impl Generator {
             special_features.push("Starting Town".to_string());
         }
         special_features.push("Town Square Template".to_string());
+        if !entrances.is_empty() {
+            special_features.push(format!("{} Buildings", entrances.len()));
+        }
 
         Ok(MapGenerationResult {
             map_type: MapType::Town,
@@ -251,8 +337,14 @@ impl Generator {
             width: town_gen.width,
             height: town_gen.height,
             tiles,
+            layers,
             spawn_position,
             spawn_points,
+            secret_passages: Vec::new(),
+            hidden_areas: Vec::new(),
+            entrances,
+            loot_spots: Vec::new(),
+            monster_spots: Vec::new(),
             is_starting_town: params.is_starting_town,
             metadata: MapMetadata {
                 room_count: if town_gen.room.is_some() { 1 } else { 0 },
@@ -263,6 +355,119 @@ impl Generator {
         })
     }
 
+    /// Generate the interior of a town building from an interior template
+    pub fn generate_interior(
+        name: String,
+        seed: u64,
+        template_name: &str,
+    ) -> Result<MapGenerationResult, String> {
+        let template = find_interior_template(template_name)
+            .ok_or_else(|| format!("Interior template '{}' not found", template_name))?;
+
+        let mut interior_gen = TownGenerator::from_template_with_seed(template, seed);
+        let map = interior_gen.generate_from_custom_template(template);
+        let spawn_points = interior_gen.get_spawn_points().clone();
+        let spawn_position = spawn_points
+            .first()
+            .copied()
+            .ok_or_else(|| format!("Interior template '{}' has no spawn point", template_name))?;
+        let layers = interior_gen.get_layers();
+        let entrances = Self::collect_entrances(&interior_gen);
+        if entrances.is_empty() {
+            return Err(format!("Interior template '{}' has no exit", template_name));
+        }
+
+        // Flatten the 2D map into 1D
+        let tiles: Vec<u8> = map.into_iter().flatten().collect();
+
+        Ok(MapGenerationResult {
+            map_type: MapType::Interior,
+            name,
+            width: interior_gen.width,
+            height: interior_gen.height,
+            tiles,
+            layers,
+            spawn_position,
+            spawn_points,
+            secret_passages: Vec::new(),
+            hidden_areas: Vec::new(),
+            entrances,
+            loot_spots: Vec::new(),
+            monster_spots: Vec::new(),
+            is_starting_town: false,
+            metadata: MapMetadata {
+                room_count: 1,
+                seed,
+                generation_time_ms: None,
+                special_features: vec![format!("Interior: {}", template_name)],
+            },
+        })
+    }
+
+    /// Pair each entrance of a town or interior with its arrival tile and the building behind it
+    fn collect_entrances(town_gen: &TownGenerator) -> Vec<Entrance> {
+        let interiors = town_gen
+            .room
+            .as_ref()
+            .and_then(|room| room.get_template_name())
+            .map(building_interiors)
+            .unwrap_or(&[]);
+
+        town_gen
+            .get_entrances()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &position)| {
+                let arrival = town_gen.get_entrance_arrival(position)?;
+                Some(Entrance {
+                    position,
+                    arrival,
+                    interior: interiors.get(i).copied(),
+                })
+            })
+            .collect()
+    }
+
+    /// Place a portal object on a free floor tile next to `near`, or on `near` itself
+    /// if none of its neighbours are free
+    fn place_portal(tiles: &[u8], width: usize, layers: &mut MapLayers, near: Position) {
+        let height = tiles.len() / width;
+        let is_free = |pos: &Position| {
+            pos.x < width
+                && pos.y < height
+                && tiles[pos.y * width + pos.x] == TileType::Floor as u8
+                && !layers.objects.iter().any(|o| o.position == *pos)
+        };
+
+        let neighbors = [
+            Position {
+                x: near.x + 1,
+                y: near.y,
+            },
+            Position {
+                x: near.x.wrapping_sub(1),
+                y: near.y,
+            },
+            Position {
+                x: near.x,
+                y: near.y + 1,
+            },
+            Position {
+                x: near.x,
+                y: near.y.wrapping_sub(1),
+            },
+        ];
+        let position = neighbors
+            .into_iter()
+            .find(|pos| is_free(pos))
+            .unwrap_or(near);
+
+        layers.objects.push(LayerProp {
+            position,
+            kind: ObjectType::Portal as u8,
+        });
+    }
+
     /// Generate a wilderness map (placeholder)
     fn generate_wilderness_map(
         name: String,
@@ -272,11 +477,16 @@ impl Generator {
         // Placeholder implementation - generates a simple open area
         let total_tiles = params.width * params.height;
         let tiles = vec![1u8; total_tiles]; // All floor tiles
+        let mut layers = MapLayers {
+            terrain: vec![TerrainType::Grass as u8; total_tiles],
+            ..Default::default()
+        };
 
         let spawn_position = Position {
             x: params.width / 2,
             y: params.height / 2,
         };
+        Self::place_portal(&tiles, params.width, &mut layers, spawn_position);
 
         // Generate spawn points around the edges
         let mut spawn_points = Vec::new();
@@ -302,8 +512,14 @@ impl Generator {
             width: params.width,
             height: params.height,
             tiles,
+            layers,
             spawn_position,
             spawn_points,
+            secret_passages: Vec::new(),
+            hidden_areas: Vec::new(),
+            entrances: Vec::new(),
+            loot_spots: Vec::new(),
+            monster_spots: Vec::new(),
             is_starting_town: false,
             metadata: MapMetadata {
                 room_count: 0,
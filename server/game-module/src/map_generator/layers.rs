@@ -0,0 +1,152 @@
+use crate::map_generator::room_templates::RoomType;
+use crate::map_generator::types::{Position, TileType};
+
+/// Ground material drawn underneath the collision layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainType {
+    Stone = 0,
+    Dirt = 1,
+    Grass = 2,
+    Cobblestone = 3,
+    Wood = 4,
+}
+
+impl From<u8> for TerrainType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => TerrainType::Stone,
+            1 => TerrainType::Dirt,
+            2 => TerrainType::Grass,
+            3 => TerrainType::Cobblestone,
+            4 => TerrainType::Wood,
+            _ => TerrainType::Stone,
+        }
+    }
+}
+
+impl TerrainType {
+    /// Terrain used for tiles that don't specify one in their template
+    pub fn default_for(room_type: RoomType) -> Self {
+        match room_type {
+            RoomType::Town => TerrainType::Cobblestone,
+            RoomType::Interior => TerrainType::Wood,
+            _ => TerrainType::Stone,
+        }
+    }
+}
+
+/// Purely visual props that never affect collision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecorationType {
+    Torch = 0,
+    Rubble = 1,
+    Rug = 2,
+    Bones = 3,
+}
+
+impl DecorationType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(DecorationType::Torch),
+            1 => Some(DecorationType::Rubble),
+            2 => Some(DecorationType::Rug),
+            3 => Some(DecorationType::Bones),
+            _ => None,
+        }
+    }
+}
+
+/// Props players can interact with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectType {
+    Chest = 0,
+    Lever = 1,
+    Fountain = 2,
+    Portal = 3,
+}
+
+impl ObjectType {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ObjectType::Chest),
+            1 => Some(ObjectType::Lever),
+            2 => Some(ObjectType::Fountain),
+            3 => Some(ObjectType::Portal),
+            _ => None,
+        }
+    }
+}
+
+/// A single prop on one of the sparse layers (decorations or objects).
+/// `kind` holds a `DecorationType` or `ObjectType` value depending on the layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerProp {
+    pub position: Position,
+    pub kind: u8,
+}
+
+impl LayerProp {
+    /// Get the same prop moved by an offset (e.g. from room to map coordinates)
+    pub fn offset(&self, dx: usize, dy: usize) -> Self {
+        LayerProp {
+            position: Position {
+                x: self.position.x + dx,
+                y: self.position.y + dy,
+            },
+            kind: self.kind,
+        }
+    }
+}
+
+/// Every layer of a generated map except collision, which stays in `tiles`.
+///
+/// Terrain covers every tile so it is stored densely, while decorations and
+/// objects are rare and stored as sparse prop lists.
+#[derive(Debug, Clone, Default)]
+pub struct MapLayers {
+    pub terrain: Vec<u8>, // Flattened 2D array: terrain[y * width + x] = terrain_type
+    pub decorations: Vec<LayerProp>,
+    pub objects: Vec<LayerProp>,
+}
+
+/// What a layer template character writes on each layer
+pub(crate) struct LayerGlyph {
+    pub collision: TileType,
+    pub terrain: Option<TerrainType>,
+    pub decoration: Option<DecorationType>,
+    pub object: Option<ObjectType>,
+}
+
+impl LayerGlyph {
+    /// Look up a template character that writes to the terrain, decoration or object layer
+    pub(crate) fn from_char(ch: char) -> Option<Self> {
+        let glyph = |collision, terrain, decoration, object| {
+            Some(LayerGlyph {
+                collision,
+                terrain,
+                decoration,
+                object,
+            })
+        };
+
+        match ch {
+            ',' => glyph(TileType::Floor, Some(TerrainType::Grass), None, None),
+            '_' => glyph(TileType::Floor, Some(TerrainType::Dirt), None, None),
+            '=' => glyph(TileType::Floor, Some(TerrainType::Wood), None, None),
+            't' => glyph(TileType::Wall, None, Some(DecorationType::Torch), None),
+            'r' => glyph(
+                TileType::Floor,
+                Some(TerrainType::Dirt),
+                Some(DecorationType::Rubble),
+                None,
+            ),
+            'u' => glyph(TileType::Floor, None, Some(DecorationType::Rug), None),
+            'b' => glyph(TileType::Floor, None, Some(DecorationType::Bones), None),
+            'c' => glyph(TileType::Floor, None, None, Some(ObjectType::Chest)),
+            'l' => glyph(TileType::Wall, None, None, Some(ObjectType::Lever)),
+            'f' => glyph(TileType::Wall, None, None, Some(ObjectType::Fountain)),
+            'p' => glyph(TileType::Floor, None, None, Some(ObjectType::Portal)),
+            _ => None,
+        }
+    }
+}
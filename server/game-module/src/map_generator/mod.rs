@@ -1,5 +1,8 @@
 pub mod dungeon_generator;
 pub mod generator;
+pub mod layers;
+#[cfg(test)]
+mod property_tests;
 pub mod room;
 pub mod room_manager;
 pub mod room_templates;
@@ -9,15 +12,16 @@ pub mod utils;
 
 // Re-export the main public API
 pub use generator::{
-    DungeonParams, GenerationParams, Generator, MapGenerationResult, MapMetadata, MapType,
-    TownParams, WildernessParams,
+    DungeonParams, Entrance, GenerationParams, Generator, HiddenArea, LootSpot,
+    MapGenerationResult, MapMetadata, MapType, MonsterSpot, TownParams, WildernessParams,
 };
+pub use layers::{DecorationType, LayerProp, MapLayers, ObjectType, TerrainType};
 pub use types::{Position, TileType};
 
 // Internal API for advanced usage
 pub use room::Room;
 pub use room_manager::RoomManager;
-pub use room_templates::{RoomTemplate, DUNGEON_TEMPLATES, TOWN_TEMPLATES};
+pub use room_templates::{RoomTemplate, DUNGEON_TEMPLATES, INTERIOR_TEMPLATES, TOWN_TEMPLATES};
 pub use town_generator::TownGenerator;
 
 #[cfg(test)]
@@ -33,6 +37,7 @@ mod tests {
                     TileType::Wall => '#',
                     TileType::Floor => '.',
                     TileType::Door => 'D',
+                    TileType::SecretDoor => 'H',
                 };
                 print!("{}", ch);
             }
@@ -137,7 +142,7 @@ mod tests {
                 match TileType::from(tile) {
                     TileType::Floor => has_floor = true,
                     TileType::Door => {} // Doors are expected but not required for this test
-                    TileType::Wall => {}
+                    TileType::Wall | TileType::SecretDoor => {}
                 }
             }
         }
@@ -233,7 +238,7 @@ mod tests {
         for row in &map {
             for &tile in row {
                 match TileType::from(tile) {
-                    TileType::Wall => wall_count += 1,
+                    TileType::Wall | TileType::SecretDoor => wall_count += 1,
                     TileType::Floor => floor_count += 1,
                     TileType::Door => connection_count += 1,
                 }
@@ -259,4 +264,210 @@ mod tests {
         assert_eq!(map.len(), 30, "Town should be 30 tiles high");
         assert_eq!(map[0].len(), 30, "Town should be 30 tiles wide");
     }
+
+    #[test]
+    fn test_template_layers() {
+        let template = RoomTemplate {
+            name: "layer_test",
+            room_type: room_manager::RoomType::Rest,
+            weight: 1,
+            is_central: false,
+            template: "
+#t#l#
+#,ur#
+#c_b#
+#####",
+        };
+        let parsed = RoomManager::parse_room_template(&template).expect("Template should parse");
+
+        // Layer glyphs still produce the right collision tiles
+        assert_eq!(parsed.tiles[0][1], TileType::Wall);
+        assert_eq!(parsed.tiles[0][3], TileType::Wall);
+        assert_eq!(parsed.tiles[1][1], TileType::Floor);
+        assert_eq!(parsed.tiles[2][1], TileType::Floor);
+
+        assert_eq!(parsed.terrain[1][1], TerrainType::Grass);
+        assert_eq!(parsed.terrain[1][3], TerrainType::Dirt);
+        assert_eq!(parsed.terrain[2][2], TerrainType::Dirt);
+        assert_eq!(parsed.terrain[1][2], TerrainType::Stone);
+
+        let decorations: Vec<u8> = parsed.decorations.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            decorations,
+            vec![
+                DecorationType::Torch as u8,
+                DecorationType::Rug as u8,
+                DecorationType::Rubble as u8,
+                DecorationType::Bones as u8,
+            ]
+        );
+        let objects: Vec<u8> = parsed.objects.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            objects,
+            vec![ObjectType::Lever as u8, ObjectType::Chest as u8]
+        );
+    }
+
+    #[test]
+    fn test_town_layers() {
+        let result = Generator::generate_town("Layer Town".to_string(), 1, 3, 20, 20, false)
+            .expect("Town should generate");
+
+        assert_eq!(result.layers.terrain.len(), result.tiles.len());
+        assert!(result
+            .layers
+            .terrain
+            .iter()
+            .any(|&t| TerrainType::from(t) == TerrainType::Grass));
+        assert!(result
+            .layers
+            .objects
+            .iter()
+            .any(|o| ObjectType::from_u8(o.kind) == Some(ObjectType::Fountain)));
+
+        // Props must stay inside the map
+        for prop in result
+            .layers
+            .decorations
+            .iter()
+            .chain(&result.layers.objects)
+        {
+            assert!(prop.position.x < result.width && prop.position.y < result.height);
+        }
+    }
+
+    #[test]
+    fn test_town_building_interiors() {
+        let town = Generator::generate_town("Interior Town".to_string(), 1, 3, 20, 20, true)
+            .expect("Town should generate");
+        assert_eq!(
+            town.entrances.len(),
+            4,
+            "Town square should have four buildings"
+        );
+
+        for entrance in &town.entrances {
+            let index = entrance.position.y * town.width + entrance.position.x;
+            assert_eq!(TileType::from(town.tiles[index]), TileType::Door);
+
+            // Coming back out must put the traveller on the street side of the door
+            let arrival = entrance.arrival.y * town.width + entrance.arrival.x;
+            assert_eq!(TileType::from(town.tiles[arrival]), TileType::Floor);
+            assert!(!town
+                .entrances
+                .iter()
+                .any(|e| e.position == entrance.arrival));
+
+            let template = entrance.interior.expect("Entrance should lead somewhere");
+            let interior = Generator::generate_interior(template.to_string(), 1, template)
+                .expect("Interior should generate");
+            assert_eq!(interior.map_type, MapType::Interior);
+            assert_eq!(interior.entrances.len(), 1, "Interior should have one exit");
+            assert!(interior
+                .layers
+                .terrain
+                .iter()
+                .all(|&t| TerrainType::from(t) == TerrainType::Wood
+                    || TerrainType::from(t) == TerrainType::Dirt));
+
+            let spawn = interior.spawn_position;
+            let spawn_index = spawn.y * interior.width + spawn.x;
+            assert_ne!(TileType::from(interior.tiles[spawn_index]), TileType::Wall);
+            assert_ne!(interior.entrances[0].position, spawn);
+        }
+    }
+
+    #[test]
+    fn test_interior_templates() {
+        for template in INTERIOR_TEMPLATES {
+            let parsed =
+                RoomManager::parse_room_template(template).unwrap_or_else(|e| panic!("{}", e));
+            assert!(
+                !parsed.spawn_points.is_empty(),
+                "{} needs a spawn point",
+                template.name
+            );
+            assert_eq!(
+                parsed.entrances.len(),
+                1,
+                "{} needs one exit",
+                template.name
+            );
+        }
+        assert!(Generator::generate_interior("x".to_string(), 0, "missing").is_err());
+    }
+
+    #[test]
+    fn test_every_map_type_has_a_portal() {
+        let params = GenerationParams {
+            wilderness: WildernessParams {
+                width: 30,
+                height: 30,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let maps = [
+            Generator::generate_town("Town".to_string(), 1, 3, 20, 20, true).unwrap(),
+            Generator::generate_dungeon("Dungeon".to_string(), 1, 3, 3, 20, 20).unwrap(),
+            Generator::generate_map(MapType::Wilderness, "Wilds".to_string(), 1, params).unwrap(),
+        ];
+
+        for map in &maps {
+            let portals = map.portals();
+            assert_eq!(portals.len(), 1, "{} should have one portal", map.name);
+
+            // Players must be able to stand on the portal
+            let portal = portals[0];
+            let tile = TileType::from(map.tiles[portal.y * map.width + portal.x]);
+            assert_eq!(
+                tile,
+                TileType::Floor,
+                "{} portal should be on floor",
+                map.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_dungeon_chests_are_loot_spots() {
+        let dungeons: Vec<MapGenerationResult> = (1..=5)
+            .map(|seed| {
+                Generator::generate_dungeon("Dungeon".to_string(), seed, 6, 6, 20, 20).unwrap()
+            })
+            .collect();
+        assert!(dungeons
+            .iter()
+            .any(|dungeon| !dungeon.loot_spots.is_empty()));
+
+        for dungeon in &dungeons {
+            for spot in &dungeon.loot_spots {
+                assert!(dungeon.layers.objects.iter().any(|object| {
+                    object.position == spot.position
+                        && ObjectType::from_u8(object.kind) == Some(ObjectType::Chest)
+                }));
+                assert_eq!(spot.table, "treasure_room");
+            }
+        }
+    }
+
+    #[test]
+    fn test_dungeon_monsters_guard_free_floor() {
+        let dungeon = Generator::generate_dungeon("Dungeon".to_string(), 3, 6, 6, 20, 20).unwrap();
+        assert!(!dungeon.monster_spots.is_empty());
+        for spot in &dungeon.monster_spots {
+            let position = spot.position;
+            assert_eq!(
+                dungeon.tiles[position.y * dungeon.width + position.x],
+                TileType::Floor as u8
+            );
+            assert!(crate::entity::prefabs::find_archetype(spot.archetype).is_some());
+        }
+        assert!(
+            Generator::generate_town("Town".to_string(), 3, 2, 20, 20, true)
+                .unwrap()
+                .monster_spots
+                .is_empty()
+        );
+    }
 }
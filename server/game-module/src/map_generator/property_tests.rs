@@ -0,0 +1,195 @@
+//! Property-based tests for map generation over random seeds and parameter ranges.
+
+use super::dungeon_generator::DungeonGenerator;
+use super::room_templates::RoomType;
+use super::*;
+use proptest::prelude::*;
+use std::collections::VecDeque;
+
+fn tile_at(map: &[Vec<u8>], x: usize, y: usize) -> TileType {
+    TileType::from(map[y][x])
+}
+
+fn is_passable(tile: TileType) -> bool {
+    // Secret doors are real passages, they are only hidden from players
+    matches!(
+        tile,
+        TileType::Floor | TileType::Door | TileType::SecretDoor
+    )
+}
+
+fn check_borders_are_walls(map: &[Vec<u8>]) -> Result<(), String> {
+    let height = map.len();
+    let width = map[0].len();
+    for (y, row) in map.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            let on_border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            if on_border && TileType::from(tile) != TileType::Wall {
+                return Err(format!("Border tile ({}, {}) is not a wall", x, y));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_spawn_points_walkable(map: &[Vec<u8>], spawn_points: &[Position]) -> Result<(), String> {
+    for spawn in spawn_points {
+        if tile_at(map, spawn.x, spawn.y) != TileType::Floor {
+            return Err(format!(
+                "Spawn point ({}, {}) is not floor",
+                spawn.x, spawn.y
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A door must lead somewhere: floor on both sides, horizontally or vertically
+fn check_doors_between_floors(map: &[Vec<u8>]) -> Result<(), String> {
+    let height = map.len();
+    let width = map[0].len();
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            if !matches!(tile_at(map, x, y), TileType::Door | TileType::SecretDoor) {
+                continue;
+            }
+            let horizontal =
+                is_passable(tile_at(map, x - 1, y)) && is_passable(tile_at(map, x + 1, y));
+            let vertical =
+                is_passable(tile_at(map, x, y - 1)) && is_passable(tile_at(map, x, y + 1));
+            if !horizontal && !vertical {
+                return Err(format!("Door at ({}, {}) does not join two floors", x, y));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_floors_reachable(map: &[Vec<u8>], start: Position) -> Result<(), String> {
+    let height = map.len();
+    let width = map[0].len();
+    let mut visited = vec![vec![false; width]; height];
+    let mut queue = VecDeque::new();
+    visited[start.y][start.x] = true;
+    queue.push_back(start);
+
+    while let Some(pos) = queue.pop_front() {
+        let neighbors = [
+            (pos.x - 1, pos.y),
+            (pos.x + 1, pos.y),
+            (pos.x, pos.y - 1),
+            (pos.x, pos.y + 1),
+        ];
+        for (x, y) in neighbors {
+            if !visited[y][x] && is_passable(tile_at(map, x, y)) {
+                visited[y][x] = true;
+                queue.push_back(Position { x, y });
+            }
+        }
+    }
+
+    for (y, row) in map.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            if TileType::from(tile) == TileType::Floor && !visited[y][x] {
+                return Err(format!("Floor tile ({}, {}) is unreachable", x, y));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_dungeon(
+    rooms_width: usize,
+    rooms_height: usize,
+    room_size: usize,
+    secret_door_chance: f64,
+    seed: u64,
+) -> Result<(), String> {
+    // Central rooms are always built over a 2x2 block of the room grid, so the multiplier is
+    // not a free parameter
+    let mut generator = DungeonGenerator::new(
+        rooms_width,
+        rooms_height,
+        room_size,
+        room_size,
+        DungeonParams::default().central_room_multiplier,
+        seed,
+    );
+    generator.set_secret_door_chance(secret_door_chance);
+    let map = generator.generate();
+
+    check_borders_are_walls(&map)?;
+    check_spawn_points_walkable(&map, generator.get_spawn_points())?;
+    check_doors_between_floors(&map)?;
+
+    let start = generator
+        .get_best_spawn_point()
+        .ok_or("Dungeon has no spawn point")?;
+    check_floors_reachable(&map, start)
+}
+
+#[test]
+fn test_all_templates_parse() {
+    let templates = DUNGEON_TEMPLATES
+        .iter()
+        .chain(TOWN_TEMPLATES)
+        .chain(INTERIOR_TEMPLATES);
+    for template in templates {
+        if let Err(e) = RoomManager::parse_room_template(template) {
+            panic!("{}", e);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn prop_dungeon_invariants(
+        rooms_width in 3usize..=8,
+        rooms_height in 3usize..=8,
+        // Templates are drawn for 20x20 cells and a 2x2 central block; smaller sizes get
+        // clamped up to 20 by the generator
+        room_size in 10usize..=20,
+        secret_door_chance in 0.0f64..=1.0,
+        seed in any::<u64>(),
+    ) {
+        let result = check_dungeon(
+            rooms_width,
+            rooms_height,
+            room_size,
+            secret_door_chance,
+            seed,
+        );
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    fn prop_town_invariants(seed in any::<u64>()) {
+        let mut town_generator = TownGenerator::with_seed(seed);
+        let map = town_generator.generate();
+
+        // Connection points on the town edge are walls until something is connected to them
+        let result = check_borders_are_walls(&map);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+        let spawn_points = town_generator.get_spawn_points().clone();
+        let result = check_spawn_points_walkable(&map, &spawn_points);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+        let result = check_doors_between_floors(&map);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+
+        let start = town_generator.get_primary_spawn_point().unwrap();
+        let result = check_floors_reachable(&map, start);
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    fn prop_parse_room_template_never_panics(text in "[#.DCSTHE ,_=trubclfp\\n]{0,200}") {
+        let _ = RoomManager::parse_template_text("prop", RoomType::Combat, false, &text);
+    }
+
+    #[test]
+    fn prop_parse_room_template_arbitrary_text(text in any::<String>()) {
+        let _ = RoomManager::parse_template_text("prop", RoomType::Combat, false, &text);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::map_generator::layers::{LayerProp, TerrainType};
 use crate::map_generator::room_manager::RoomManager;
 use crate::map_generator::room_templates::{RoomTemplate, RoomType};
 use crate::map_generator::types::{Position, TileType};
@@ -9,8 +10,12 @@ pub struct Room {
     pub width: usize,
     pub height: usize,
     pub tiles: Vec<Vec<TileType>>,
-    pub connections: Vec<Position>, // Potential connection points (relative to room)
-    pub spawn_points: Vec<Position>, // Spawn points marked in template (relative to room)
+    pub terrain: Vec<Vec<TerrainType>>, // Ground under each tile
+    pub decorations: Vec<LayerProp>,    // Visual props (relative to room)
+    pub objects: Vec<LayerProp>,        // Interactable props (relative to room)
+    pub connections: Vec<Position>,     // Potential connection points (relative to room)
+    pub spawn_points: Vec<Position>,    // Spawn points marked in template (relative to room)
+    pub entrances: Vec<Position>,       // Doors leading to other maps (relative to room)
     pub is_central: bool,
     pub room_type: RoomType,
     pub template_name: Option<String>, // Track which template was used
@@ -89,6 +94,33 @@ impl Room {
             .collect()
     }
 
+    /// Get entrances to other maps in global coordinates
+    pub fn get_global_entrances(&self) -> Vec<Position> {
+        self.entrances
+            .iter()
+            .map(|entrance| Position {
+                x: self.position.x + entrance.x,
+                y: self.position.y + entrance.y,
+            })
+            .collect()
+    }
+
+    /// Get decoration props in global coordinates
+    pub fn get_global_decorations(&self) -> Vec<LayerProp> {
+        self.decorations
+            .iter()
+            .map(|prop| prop.offset(self.position.x, self.position.y))
+            .collect()
+    }
+
+    /// Get interactable object props in global coordinates
+    pub fn get_global_objects(&self) -> Vec<LayerProp> {
+        self.objects
+            .iter()
+            .map(|prop| prop.offset(self.position.x, self.position.y))
+            .collect()
+    }
+
     /// Get the template name used to create this room (if any)
     pub fn get_template_name(&self) -> Option<&str> {
         self.template_name.as_deref()
@@ -1,3 +1,4 @@
+use crate::map_generator::layers::{LayerGlyph, LayerProp, TerrainType};
 use crate::map_generator::room_templates::RoomTemplate;
 use crate::map_generator::room_templates::{DUNGEON_TEMPLATES, TOWN_TEMPLATES};
 use crate::map_generator::types::{Position, TileType};
@@ -14,8 +15,12 @@ pub struct ParsedRoom {
     pub width: usize,
     pub height: usize,
     pub tiles: Vec<Vec<TileType>>,
+    pub terrain: Vec<Vec<TerrainType>>,
+    pub decorations: Vec<LayerProp>,
+    pub objects: Vec<LayerProp>,
     pub connections: Vec<Position>,
     pub spawn_points: Vec<Position>,
+    pub entrances: Vec<Position>,
     pub is_central: bool,
 }
 
@@ -119,7 +124,22 @@ impl RoomManager {
     }
 
     pub fn parse_room_template(template: &RoomTemplate) -> Result<ParsedRoom, String> {
-        let lines: Vec<&str> = template.template.trim().lines().collect();
+        Self::parse_template_text(
+            template.name,
+            template.room_type,
+            template.is_central,
+            template.template,
+        )
+    }
+
+    /// Parse room template text that doesn't come from a `RoomTemplate` constant
+    pub fn parse_template_text(
+        name: &str,
+        room_type: RoomType,
+        is_central: bool,
+        text: &str,
+    ) -> Result<ParsedRoom, String> {
+        let lines: Vec<&str> = text.trim().lines().collect();
 
         if lines.is_empty() {
             return Err("Empty template".to_string());
@@ -131,16 +151,17 @@ impl RoomManager {
         // Validate that all lines have the same width
         for line in &lines {
             if line.len() != width {
-                return Err(format!(
-                    "Inconsistent line width in template '{}'",
-                    template.name
-                ));
+                return Err(format!("Inconsistent line width in template '{}'", name));
             }
         }
 
         let mut tiles = vec![vec![TileType::Wall; width]; height];
+        let mut terrain = vec![vec![TerrainType::default_for(room_type); width]; height];
+        let mut decorations = Vec::new();
+        let mut objects = Vec::new();
         let mut connections = Vec::new();
         let mut spawn_points = Vec::new();
+        let mut entrances = Vec::new();
 
         for (y, line) in lines.iter().enumerate() {
             for (x, ch) in line.chars().enumerate() {
@@ -160,25 +181,53 @@ impl RoomManager {
                         spawn_points.push(Position { x, y });
                         TileType::Floor
                     }
+                    'H' => TileType::SecretDoor, // Hidden passage into an alcove
+                    'E' => {
+                        // Entrances are doors that lead to another map
+                        entrances.push(Position { x, y });
+                        TileType::Door
+                    }
                     _ => {
-                        return Err(format!(
-                            "Invalid character '{}' in template '{}'",
-                            ch, template.name
-                        ))
+                        // Anything else writes to the terrain, decoration or object layers
+                        let glyph = LayerGlyph::from_char(ch).ok_or_else(|| {
+                            format!("Invalid character '{}' in template '{}'", ch, name)
+                        })?;
+
+                        let position = Position { x, y };
+                        if let Some(ground) = glyph.terrain {
+                            terrain[y][x] = ground;
+                        }
+                        if let Some(decoration) = glyph.decoration {
+                            decorations.push(LayerProp {
+                                position,
+                                kind: decoration as u8,
+                            });
+                        }
+                        if let Some(object) = glyph.object {
+                            objects.push(LayerProp {
+                                position,
+                                kind: object as u8,
+                            });
+                        }
+                        glyph.collision
                     }
                 };
             }
         }
 
         Ok(ParsedRoom {
-            name: template.name.to_string(),
-            room_type: template.room_type,
+            name: name.to_string(),
+            room_type,
             width,
             height,
             tiles,
+            terrain,
+            decorations,
+            objects,
             connections, // Use parsed connections from template instead of manual connection_points
             spawn_points,
-            is_central: template.is_central,
+            entrances,
+            is_central,
         })
     }
 
@@ -190,13 +239,15 @@ impl RoomManager {
         }
 
         // For regular rooms, exclude Central and Boss types
-        let eligible_weights: Vec<(RoomType, u32)> = self
+        let mut eligible_weights: Vec<(RoomType, u32)> = self
             .room_type_weights
             .weights
             .iter()
             .filter(|(&room_type, _)| !matches!(room_type, RoomType::Central))
             .map(|(&room_type, &weight)| (room_type, weight))
             .collect();
+        // HashMap order differs between runs, so sort to keep layouts reproducible from a seed
+        eligible_weights.sort_by_key(|&(room_type, _)| room_type as u8);
 
         let total_weight: u32 = eligible_weights.iter().map(|(_, weight)| weight).sum();
         if total_weight == 0 {
@@ -316,8 +367,12 @@ impl RoomManager {
             width: parsed.width,
             height: parsed.height,
             tiles: parsed.tiles,
+            terrain: parsed.terrain,
+            decorations: parsed.decorations,
+            objects: parsed.objects,
             connections: parsed.connections,
             spawn_points: parsed.spawn_points,
+            entrances: parsed.entrances,
             is_central: parsed.is_central,
             room_type: parsed.room_type,
             template_name: Some(template.name.to_string()),
@@ -339,46 +394,77 @@ impl RoomManager {
         let final_width = parsed.width.max(min_width);
         let final_height = parsed.height.max(min_height);
 
+        // Offset of the template inside the room (zero unless we scale up)
+        let offset_x = (final_width - parsed.width) / 2;
+        let offset_y = (final_height - parsed.height) / 2;
+
         // If we need to scale up, create a new tile grid and adjust connections
-        let (final_tiles, final_connections) =
-            if final_width > parsed.width || final_height > parsed.height {
-                // Create a larger room with walls
-                let mut new_tiles = vec![vec![TileType::Wall; final_width]; final_height];
-
-                // Copy the original template in the center
-                let offset_x = (final_width - parsed.width) / 2;
-                let offset_y = (final_height - parsed.height) / 2;
-
-                for (y, row) in parsed.tiles.iter().enumerate() {
-                    for (x, &tile) in row.iter().enumerate() {
-                        if offset_y + y < final_height && offset_x + x < final_width {
-                            new_tiles[offset_y + y][offset_x + x] = tile;
-                        }
+        let (final_tiles, final_terrain, final_connections) = if final_width > parsed.width
+            || final_height > parsed.height
+        {
+            // Create a larger room with walls
+            let mut new_tiles = vec![vec![TileType::Wall; final_width]; final_height];
+            let mut new_terrain =
+                vec![vec![TerrainType::default_for(parsed.room_type); final_width]; final_height];
+
+            // Copy the original template in the center
+            for (y, row) in parsed.tiles.iter().enumerate() {
+                for (x, &tile) in row.iter().enumerate() {
+                    if offset_y + y < final_height && offset_x + x < final_width {
+                        new_tiles[offset_y + y][offset_x + x] = tile;
+                        new_terrain[offset_y + y][offset_x + x] = parsed.terrain[y][x];
                     }
                 }
+            }
 
-                // Adjust connection points for the new offset
-                let adjusted_connections = parsed
-                    .connections
-                    .iter()
-                    .map(|pos| Position {
-                        x: pos.x + offset_x,
-                        y: pos.y + offset_y,
-                    })
-                    .collect();
-
-                (new_tiles, adjusted_connections)
-            } else {
-                (parsed.tiles, parsed.connections.clone())
-            };
+            // Adjust connection points for the new offset
+            let adjusted_connections = parsed
+                .connections
+                .iter()
+                .map(|pos| Position {
+                    x: pos.x + offset_x,
+                    y: pos.y + offset_y,
+                })
+                .collect();
+
+            (new_tiles, new_terrain, adjusted_connections)
+        } else {
+            (parsed.tiles, parsed.terrain, parsed.connections.clone())
+        };
 
         Ok(crate::map_generator::room::Room {
             position: Position { x, y },
             width: final_width,
             height: final_height,
             tiles: final_tiles,
+            terrain: final_terrain,
+            decorations: parsed
+                .decorations
+                .iter()
+                .map(|prop| prop.offset(offset_x, offset_y))
+                .collect(),
+            objects: parsed
+                .objects
+                .iter()
+                .map(|prop| prop.offset(offset_x, offset_y))
+                .collect(),
             connections: final_connections,
-            spawn_points: parsed.spawn_points,
+            spawn_points: parsed
+                .spawn_points
+                .iter()
+                .map(|pos| Position {
+                    x: pos.x + offset_x,
+                    y: pos.y + offset_y,
+                })
+                .collect(),
+            entrances: parsed
+                .entrances
+                .iter()
+                .map(|pos| Position {
+                    x: pos.x + offset_x,
+                    y: pos.y + offset_y,
+                })
+                .collect(),
             is_central: parsed.is_central,
             room_type: parsed.room_type,
             template_name: Some(template.name.to_string()),
@@ -6,6 +6,7 @@ pub enum RoomType {
     Rest,
     Spawn,
     Town,
+    Interior,
 }
 
 impl RoomType {
@@ -19,6 +20,25 @@ impl RoomType {
             _ => 0, // Town types not used in dungeons
         }
     }
+
+    /// Monster archetypes guarding a room of this type, one of each
+    pub fn monsters(&self) -> &'static [&'static str] {
+        match self {
+            RoomType::Combat => &["brute", "skeleton_guard", "wolf"],
+            RoomType::Treasure => &["skeleton_guard"],
+            RoomType::Central => &["brute", "skeleton_guard"],
+            RoomType::Rest => &["rat"],
+            _ => &[],
+        }
+    }
+
+    /// Loot table the chests of this room type are filled from
+    pub fn loot_table(&self) -> Option<&'static str> {
+        match self {
+            RoomType::Treasure => Some("treasure_room"),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,6 +55,15 @@ pub struct RoomTemplate {
 // . = Floor
 // D = Door
 // C = Connection point (will become door when connected)
+// H = Hidden passage (secret door that looks like a wall until discovered)
+// E = Entrance to another map (a door that moves whoever steps on it)
+//
+// Layer glyphs (collision tile in brackets):
+// , = Grass floor  _ = Dirt floor  = = Wooden floor            [floor]
+// t = Torch on a wall                                          [wall]
+// r = Rubble on dirt  u = Rug  b = Bones                       [floor]
+// c = Chest  p = Portal to another map                         [floor]
+// l = Lever  f = Fountain                                      [wall]
 
 pub const BASIC_ROOM: RoomTemplate = RoomTemplate {
     name: "basic_room",
@@ -85,9 +114,9 @@ C......#...........C
 #..................#
 #..................#
 #..................#
-#..................#
-#..................#
-#..................#
+#......uuuuuu......#
+#......uuuuuu......#
+#......uuuuuu......#
 #..................#
 #########CC#########",
 };
@@ -100,22 +129,22 @@ pub const COMBAT_ROOM: RoomTemplate = RoomTemplate {
     template: "
 #########CC#########
 #..................#
-#..................#
+#.............rr...#
+#.....######...r...#
+#.....######.......#
+#.....######.......#
 #.....######.......#
-#.....#....#.......#
-#.....#....#.......#
-#.....#....#.......#
 #.....######.......#
 C..................C
 C..................C
 #..................#
 #.....######.......#
-#.....#....#.......#
-#.....#....#.......#
-#.....#....#.......#
 #.....######.......#
-#..................#
-#..................#
+#.....######.......#
+#.....######.......#
+#.....######.......#
+#.............b....#
+#..b...............#
 #..................#
 #########CC#########",
 };
@@ -130,7 +159,7 @@ pub const TREASURE_ROOM: RoomTemplate = RoomTemplate {
 #..................#
 #..................#
 #.....########.....#
-#.....#......#.....#
+#.....#.c..c.#.....#
 #.....#......#.....#
 #.....#......#.....#
 #.....#......#.....#
@@ -139,7 +168,7 @@ C.....D......D.....C
 #.....#......#.....#
 #.....#......#.....#
 #.....#......#.....#
-#.....#......#.....#
+#.....#.c..c.#.....#
 #.....########.....#
 #..................#
 #..................#
@@ -148,6 +177,34 @@ C.....D......D.....C
 #########CC#########",
 };
 
+pub const HIDDEN_ALCOVE_ROOM: RoomTemplate = RoomTemplate {
+    name: "hidden_alcove",
+    room_type: RoomType::Treasure,
+    weight: 2,
+    is_central: false,
+    template: "
+#########CC#########
+#...........#......#
+#...........#....c.#
+#...........H......#
+#...........#....b.#
+#...........########
+#..................#
+#..................#
+C..................C
+C..................C
+#..................#
+#..................#
+#..................#
+#..................#
+#..................#
+#..................#
+#..................#
+#..................#
+#..................#
+#########CC#########",
+};
+
 pub const SPAWN_ROOM_BASIC: RoomTemplate = RoomTemplate {
     name: "spawn_room_basic",
     room_type: RoomType::Spawn,
@@ -190,8 +247,8 @@ pub const SPAWN_ROOM_SAFE: RoomTemplate = RoomTemplate {
 #....#....S...#....#
 #....#........#....#
 #....#........#....#
-C....#........#....C
-C....#........#....C
+C....D........D....C
+C....D........D....C
 #....#........#....#
 #....#........#....#
 #....#....S...#....#
@@ -241,8 +298,8 @@ pub const SPAWN_ROOM_OUTPOST: RoomTemplate = RoomTemplate {
 #########CC#########
 #..................#
 #...###........###.#
-#...#..........#..#
-#...#....S.....#..#
+#...#..........#...#
+#...#....S.....#...#
 #..................#
 #..................#
 #.........S........#
@@ -251,8 +308,8 @@ C..................C
 #.........S........#
 #..................#
 #..................#
-#...#....S.....#..#
-#...#..........#..#
+#...#....S.....#...#
+#...#..........#...#
 #...###........###.#
 #..................#
 #..................#
@@ -15,7 +15,7 @@ pub const CENTRAL_HALL: RoomTemplate = RoomTemplate {
 #.....................................#
 #.....................................#
 #.....................................#
-#.....................................#
+t.....................................t
 #.....................................#
 #.....................................#
 #.....................................#
@@ -24,7 +24,7 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
-#.....................................#
+t.....................................t
 #.....................................#
 #.....................................#
 #.....................................#
@@ -34,8 +34,7 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
-#.....................................#
-#.....................................#
+t.....................................t
 #.....................................#
 #.....................................#
 #.....................................#
@@ -44,6 +43,7 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
+t.....................................t
 #.....................................#
 #.....................................#
 #.....................................#
@@ -85,7 +85,6 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
-#.....................................#
 C.....................................C
 #.....................................#
 #.....................................#
@@ -95,6 +94,7 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
+#.....................................#
 #########C#########C#########C#########",
 };
 
@@ -104,8 +104,7 @@ pub const CENTRAL_COURTYARD: RoomTemplate = RoomTemplate {
     weight: 6,
     is_central: true,
     template: "
-###################C###################
-#.....................................#
+#########C###################C#########
 #.....................................#
 #.....................................#
 #.....................................#
@@ -114,6 +113,7 @@ pub const CENTRAL_COURTYARD: RoomTemplate = RoomTemplate {
 #.....................................#
 #.....................................#
 #.....................................#
+C.....................................C
 #.........#################...........#
 #.........#...............#...........#
 #.........#...............#...........#
@@ -123,7 +123,7 @@ pub const CENTRAL_COURTYARD: RoomTemplate = RoomTemplate {
 #.........#...............#...........#
 #.........#...............#...........#
 #.........#...............#...........#
-C.........#...............#...........C
+#.........D...............D...........#
 #.........#...............#...........#
 #.........#...............#...........#
 #.........#...............#...........#
@@ -132,6 +132,7 @@ C.........#...............#...........C
 #.........#...............#...........#
 #.........#...............#...........#
 #.........#################...........#
+C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
@@ -141,7 +142,7 @@ C.........#...............#...........C
 #.....................................#
 #.....................................#
 #.....................................#
-###################C###################",
+#########C###################C#########",
 };
 
 pub const CENTRAL_THRONE_ROOM: RoomTemplate = RoomTemplate {
@@ -176,8 +177,7 @@ C.....................................C
 #.............#.......#...............#
 #.............#.......#...............#
 #.............#.......#...............#
-#.............#########...............#
-#.....................................#
+#.............####D####...............#
 #.....................................#
 C.....................................C
 #.....................................#
@@ -188,6 +188,7 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
+#.....................................#
 #########C#########C#########C#########",
 };
 
@@ -197,7 +198,7 @@ pub const CENTRAL_GREAT_HALL: RoomTemplate = RoomTemplate {
     weight: 12,
     is_central: true,
     template: "
-###################C###################
+#########C###################C#########
 #.....................................#
 #.....................................#
 #.....................................#
@@ -216,15 +217,16 @@ C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
-C.........##################..........C  
-#.........#................#..........#
+#.........##################..........#
 #.........#................#..........#
 #.........#................#..........#
 #.........#................#..........#
+#.........D................D..........#
 #.........#................#..........#
 #.........#................#..........#
 #.........#................#..........#
 #.........##################..........#
+C.....................................C
 #.....................................#
 #.....................................#
 #.....................................#
@@ -234,7 +236,7 @@ C.........##################..........C
 #.....................................#
 #.....................................#
 #.....................................#
-###################C###################",
+#########C###################C#########",
 };
 
 /// All available central room templates
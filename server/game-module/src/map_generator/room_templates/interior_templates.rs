@@ -0,0 +1,85 @@
+use super::{RoomTemplate, RoomType};
+
+// Interior templates for enterable town buildings
+// Each interior has an 'E' exit leading back to the town and an 'S' arrival point just inside
+
+pub const INN: RoomTemplate = RoomTemplate {
+    name: "inn",
+    room_type: RoomType::Interior,
+    weight: 10,
+    is_central: false,
+    template: "
+####t####t####
+#c...#.......#
+#....#..uuu..#
+#....D..uuu..#
+######.......#
+#............#
+#............#
+#.....S......#
+#............#
+######E#######",
+};
+
+pub const SMITHY: RoomTemplate = RoomTemplate {
+    name: "smithy",
+    room_type: RoomType::Interior,
+    weight: 10,
+    is_central: false,
+    template: "
+###t######t###
+#__________c_#
+#_##_________#
+#_##_____rr__#
+#____________#
+#____________#
+#.....S......#
+#............#
+#............#
+######E#######",
+};
+
+pub const GENERAL_STORE: RoomTemplate = RoomTemplate {
+    name: "general_store",
+    room_type: RoomType::Interior,
+    weight: 10,
+    is_central: false,
+    template: "
+##t########t##
+#c.c.c..c.c..#
+#............#
+#..########..#
+#............#
+#.....uu.....#
+#.....uu.....#
+#.....S......#
+#............#
+######E#######",
+};
+
+pub const GUILD_HALL: RoomTemplate = RoomTemplate {
+    name: "guild_hall",
+    room_type: RoomType::Interior,
+    weight: 10,
+    is_central: false,
+    template: "
+####t#######t#####
+#................#
+#..uuuuuuuuuuuu..#
+#..u..........u..#
+#..u..#####...u..#
+#..u..........u..#
+#..uuuuuuuuuuuu..#
+#................#
+#c..............c#
+#.......S........#
+#................#
+########E#########",
+};
+
+pub const ALL_INTERIOR_TEMPLATES: &[RoomTemplate] = &[INN, SMITHY, GENERAL_STORE, GUILD_HALL];
+
+/// Find an interior template by name
+pub fn find_interior_template(name: &str) -> Option<&'static RoomTemplate> {
+    ALL_INTERIOR_TEMPLATES.iter().find(|t| t.name == name)
+}
@@ -1,9 +1,11 @@
 pub mod basic_rooms;
 pub mod central_rooms;
+pub mod interior_templates;
 pub mod town_templates;
 
 pub use basic_rooms::*;
 pub use central_rooms::*;
+pub use interior_templates::*;
 pub use town_templates::*;
 
 /// Collection of all dungeon-related room templates
@@ -13,6 +15,7 @@ pub const DUNGEON_TEMPLATES: &[RoomTemplate] = &[
     basic_rooms::SECONDARY_ROOM,
     basic_rooms::COMBAT_ROOM,
     basic_rooms::TREASURE_ROOM,
+    basic_rooms::HIDDEN_ALCOVE_ROOM,
     // Spawn room templates
     basic_rooms::SPAWN_ROOM_BASIC,
     basic_rooms::SPAWN_ROOM_SAFE,
@@ -28,3 +31,6 @@ pub const DUNGEON_TEMPLATES: &[RoomTemplate] = &[
 
 /// Collection of all town-related room templates
 pub const TOWN_TEMPLATES: &[RoomTemplate] = &[town_templates::TOWN_SQUARE];
+
+/// Collection of all building interior templates
+pub const INTERIOR_TEMPLATES: &[RoomTemplate] = interior_templates::ALL_INTERIOR_TEMPLATES;
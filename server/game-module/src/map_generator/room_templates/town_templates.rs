@@ -11,30 +11,30 @@ pub const TOWN_SQUARE: RoomTemplate = RoomTemplate {
     template: "
 #############CC###############
 #............................#
-#............................#
+#.............p..............#
 #...####..........####.......#
 #...#..#..........#..#.......#
-#...####..........####.......#
-#............................#
-#............................#
+#...#E##..........#E##.......#
+#...................,,,,,,,,.#
+#...................,,,,,,,,.#
 #..........######............#
 #..........#....#............#
 #..........#....#............#
 #..........#....#............#
 #..........#....#............#
-#..........######............#
-C............................C
-C............................C
-#..........######............#
+#..........##E###............#
+C.....................ff.....C
+C.....................ff.....C
+#..........###E##............#
 #..........#....#............#
 #..........#....#............#
 #..........#....#............#
 #..........#....#............#
 #..........######............#
-#............................#
-#............................#
+#,,,,,,,,....................#
+#,,,,,,,,....................#
+#...####..........####.......#
 #...####..........####.......#
-#...#..#..........#..#.......#
 #...####..........####.......#
 #............................#
 #............................#
@@ -44,3 +44,14 @@ C............................C
 pub const ALL_TOWN_TEMPLATES: &[RoomTemplate] = &[
     TOWN_SQUARE,
 ];
+
+/// Interior templates behind the 'E' entrances of the town square, in reading order
+pub const TOWN_SQUARE_BUILDINGS: &[&str] = &["inn", "smithy", "general_store", "guild_hall"];
+
+/// Get the interior template names linked to the entrances of a town template
+pub fn building_interiors(town_template_name: &str) -> &'static [&'static str] {
+    match town_template_name {
+        "town_square" => TOWN_SQUARE_BUILDINGS,
+        _ => &[],
+    }
+}
@@ -1,6 +1,7 @@
 use spacetimedb::rand::rngs::StdRng;
 use spacetimedb::rand::{Rng, SeedableRng};
 
+use crate::map_generator::layers::{LayerProp, MapLayers, TerrainType};
 use crate::map_generator::room::Room;
 use crate::map_generator::room_manager::RoomManager;
 use crate::map_generator::room_templates::{town_templates::*, RoomTemplate};
@@ -26,9 +27,13 @@ use crate::map_generator::types::{Position, TileType};
 pub struct TownGenerator {
     pub width: usize,
     pub height: usize,
-    pub map: Vec<Vec<u8>>,
+    pub map: Vec<Vec<u8>>,     // Collision layer
+    pub terrain: Vec<Vec<u8>>, // Terrain layer
+    pub decorations: Vec<LayerProp>,
+    pub objects: Vec<LayerProp>,
     pub room: Option<Room>,
     pub spawn_points: Vec<Position>,
+    pub entrances: Vec<Position>, // Doors leading into building interiors
     pub rng: StdRng,
     pub room_manager: RoomManager,
 }
@@ -47,6 +52,7 @@ impl TownGenerator {
         let width = parsed.width;
         let height = parsed.height;
         let map = vec![vec![TileType::Wall as u8; width]; height];
+        let terrain = vec![vec![TerrainType::Cobblestone as u8; width]; height];
 
         // Create room manager for towns
         let room_manager = RoomManager::for_towns();
@@ -55,8 +61,12 @@ impl TownGenerator {
             width,
             height,
             map,
+            terrain,
+            decorations: Vec::new(),
+            objects: Vec::new(),
             room: None,
             spawn_points: Vec::new(),
+            entrances: Vec::new(),
             rng: StdRng::seed_from_u64(seed),
             room_manager,
         }
@@ -113,6 +123,7 @@ impl TownGenerator {
                 self.width = parsed.width;
                 self.height = parsed.height;
                 self.map = vec![vec![TileType::Wall as u8; self.width]; self.height];
+                self.terrain = vec![vec![TerrainType::Cobblestone as u8; self.width]; self.height];
             }
 
             // Create room from template
@@ -145,9 +156,14 @@ impl TownGenerator {
 
                     if global_x < self.width && global_y < self.height {
                         self.map[global_y][global_x] = tile as u8;
+                        self.terrain[global_y][global_x] = room.terrain[row_idx][col_idx] as u8;
                     }
                 }
             }
+
+            self.decorations = room.get_global_decorations();
+            self.objects = room.get_global_objects();
+            self.entrances = room.get_global_entrances();
         }
     }
 
@@ -156,6 +172,12 @@ impl TownGenerator {
 
         // Generate spawn points from the room
         if let Some(room) = &self.room {
+            // Spawn points marked in the template take precedence
+            if !room.spawn_points.is_empty() {
+                self.spawn_points = room.get_global_spawn_points();
+                return;
+            }
+
             // Add spawn points around the center of the room
             let center_x = room.position.x + room.width / 2;
             let center_y = room.position.y + room.height / 2;
@@ -188,6 +210,15 @@ impl TownGenerator {
         }
     }
 
+    /// Get the terrain, decoration and object layers of the town
+    pub fn get_layers(&self) -> MapLayers {
+        MapLayers {
+            terrain: self.terrain.iter().flatten().copied().collect(),
+            decorations: self.decorations.clone(),
+            objects: self.objects.clone(),
+        }
+    }
+
     /// Get all possible spawn points in the town
     pub fn get_spawn_points(&self) -> &Vec<Position> {
         &self.spawn_points
@@ -203,6 +234,54 @@ impl TownGenerator {
         self.spawn_points.get(index).copied()
     }
 
+    /// Get the entrances leading into building interiors
+    pub fn get_entrances(&self) -> &Vec<Position> {
+        &self.entrances
+    }
+
+    /// Find where travellers arrive when they come back out of an entrance: the walkable
+    /// tile next to it that can be reached from the primary spawn point without passing
+    /// through any entrance.
+    pub fn get_entrance_arrival(&self, entrance: Position) -> Option<Position> {
+        let start = self.get_primary_spawn_point()?;
+        let passable = |pos: &Position| {
+            let tile = TileType::from(self.map[pos.y][pos.x]);
+            matches!(tile, TileType::Floor | TileType::Door) && !self.entrances.contains(pos)
+        };
+        if !passable(&start) {
+            return None;
+        }
+
+        let mut visited = vec![vec![false; self.width]; self.height];
+        let mut queue = std::collections::VecDeque::new();
+        visited[start.y][start.x] = true;
+        queue.push_back(start);
+
+        while let Some(pos) = queue.pop_front() {
+            if pos.x.abs_diff(entrance.x) + pos.y.abs_diff(entrance.y) == 1 {
+                return Some(pos);
+            }
+
+            let neighbors = [
+                (pos.x.wrapping_sub(1), pos.y),
+                (pos.x + 1, pos.y),
+                (pos.x, pos.y.wrapping_sub(1)),
+                (pos.x, pos.y + 1),
+            ];
+            for (x, y) in neighbors {
+                if x < self.width && y < self.height && !visited[y][x] {
+                    let next = Position { x, y };
+                    if passable(&next) {
+                        visited[y][x] = true;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
     /// Get the primary spawn point (center of the room)
     pub fn get_primary_spawn_point(&self) -> Option<Position> {
         if let Some(room) = &self.room {
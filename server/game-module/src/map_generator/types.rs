@@ -3,6 +3,17 @@ pub enum TileType {
     Wall = 0,
     Floor = 1,
     Door = 2,
+    SecretDoor = 3,
+}
+
+impl TileType {
+    /// The tile as clients are allowed to see it: undiscovered secret doors look like walls
+    pub fn disguised(self) -> TileType {
+        match self {
+            TileType::SecretDoor => TileType::Wall,
+            other => other,
+        }
+    }
 }
 
 impl From<u8> for TileType {
@@ -11,6 +22,7 @@ impl From<u8> for TileType {
             0 => TileType::Wall,
             1 => TileType::Floor,
             2 => TileType::Door,
+            3 => TileType::SecretDoor,
             _ => TileType::Wall,
         }
     }
@@ -20,4 +32,4 @@ impl From<u8> for TileType {
 pub struct Position {
     pub x: usize,
     pub y: usize,
-} 
\ No newline at end of file
+}
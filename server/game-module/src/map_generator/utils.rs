@@ -1,3 +1,4 @@
+use crate::map_generator::layers::TerrainType;
 use crate::map_generator::room_templates::RoomType;
 use crate::map_generator::types::{Position, TileType};
 
@@ -86,8 +87,12 @@ impl RoomUtils {
             width,
             height,
             tiles,
+            terrain: vec![vec![TerrainType::default_for(room_type); width]; height],
+            decorations: Vec::new(),
+            objects: Vec::new(),
             connections,
             spawn_points: Vec::new(), // No spawn points for programmatically created rooms
+            entrances: Vec::new(),
             is_central,
             room_type,
             template_name: None,
@@ -0,0 +1,370 @@
+use crate::map::{Map, Vec2};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// A tile coordinate on a map
+pub type Tile = (usize, usize);
+
+/// Cost of one straight step; diagonal steps cost `DIAGONAL_COST`
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+const TILE_DOOR: u8 = 2;
+
+/// Which neighbours a path may step to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+#[derive(Clone, Debug)]
+pub struct PathParams {
+    pub connectivity: Connectivity,
+    pub door_cost: u32, // Extra cost of stepping onto a door, in tenths of a step
+    pub blocked: HashSet<Tile>, // Walkable tiles to avoid anyway (other monsters, traps, ...)
+    pub max_expanded: usize, // Give up after expanding this many tiles
+}
+
+impl Default for PathParams {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity::Eight,
+            door_cost: 5,
+            blocked: HashSet::new(),
+            max_expanded: 4096,
+        }
+    }
+}
+
+/// The tile an entity standing at `position` occupies (positions are tile centres)
+pub fn tile_of(position: &Vec2) -> Option<Tile> {
+    let x = position.x.round();
+    let y = position.y.round();
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    Some((x as usize, y as usize))
+}
+
+/// The position of the centre of a tile
+pub fn tile_center(tile: Tile) -> Vec2 {
+    Vec2 {
+        x: tile.0 as f64,
+        y: tile.1 as f64,
+    }
+}
+
+fn is_passable(map: &Map, tile: Tile, params: &PathParams) -> bool {
+    map.is_walkable(tile.0, tile.1) && !params.blocked.contains(&tile)
+}
+
+fn heuristic(from: Tile, to: Tile, connectivity: Connectivity) -> u32 {
+    let dx = from.0.abs_diff(to.0) as u32;
+    let dy = from.1.abs_diff(to.1) as u32;
+    match connectivity {
+        Connectivity::Four => STRAIGHT_COST * (dx + dy),
+        // Octile distance
+        Connectivity::Eight => {
+            STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+        }
+    }
+}
+
+/// Neighbours reachable in one step, with the cost of the step
+fn neighbors(map: &Map, tile: Tile, params: &PathParams) -> Vec<(Tile, u32)> {
+    let (x, y) = (tile.0 as i64, tile.1 as i64);
+    let passable = |dx: i64, dy: i64| -> Option<Tile> {
+        let (nx, ny) = (x + dx, y + dy);
+        if nx < 0 || ny < 0 {
+            return None;
+        }
+        let next = (nx as usize, ny as usize);
+        is_passable(map, next, params).then_some(next)
+    };
+    let step_cost = |next: Tile, base: u32| -> u32 {
+        match map.get_tile(next.0, next.1) {
+            Some(TILE_DOOR) => base + params.door_cost,
+            _ => base,
+        }
+    };
+
+    let mut result = Vec::with_capacity(8);
+    for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+        if let Some(next) = passable(dx, dy) {
+            result.push((next, step_cost(next, STRAIGHT_COST)));
+        }
+    }
+    if params.connectivity == Connectivity::Eight {
+        for (dx, dy) in [(1, -1), (1, 1), (-1, 1), (-1, -1)] {
+            // Never cut the corner of a wall
+            if passable(dx, 0).is_none() || passable(0, dy).is_none() {
+                continue;
+            }
+            if let Some(next) = passable(dx, dy) {
+                result.push((next, step_cost(next, DIAGONAL_COST)));
+            }
+        }
+    }
+    result
+}
+
+/// Find the cheapest path from `start` to `goal` with A*.
+/// The returned tiles exclude `start` and end with `goal`; `None` if the goal can't be reached.
+pub fn find_path(map: &Map, start: Tile, goal: Tile, params: &PathParams) -> Option<Vec<Tile>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+    if !is_passable(map, goal, params) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Tile, Tile> = HashMap::new();
+    let mut cost_so_far: HashMap<Tile, u32> = HashMap::new();
+    let mut expanded = 0;
+
+    cost_so_far.insert(start, 0);
+    open.push(Reverse((
+        heuristic(start, goal, params.connectivity),
+        0u32,
+        start,
+    )));
+
+    while let Some(Reverse((_, cost, tile))) = open.pop() {
+        if tile == goal {
+            let mut path = vec![goal];
+            let mut current = goal;
+            while let Some(&previous) = came_from.get(&current) {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        // Skip stale heap entries
+        if cost > cost_so_far[&tile] {
+            continue;
+        }
+        expanded += 1;
+        if expanded > params.max_expanded {
+            return None;
+        }
+
+        for (next, step) in neighbors(map, tile, params) {
+            let new_cost = cost + step;
+            if cost_so_far.get(&next).is_none_or(|&old| new_cost < old) {
+                cost_so_far.insert(next, new_cost);
+                came_from.insert(next, tile);
+                let priority = new_cost + heuristic(next, goal, params.connectivity);
+                open.push(Reverse((priority, new_cost, next)));
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(Clone, Debug)]
+struct CachedPath {
+    goal: Tile,
+    tiles: VecDeque<Tile>,
+}
+
+/// Paths of entities that are following a target, recomputed when the target moves
+#[derive(Clone, Debug, Default)]
+pub struct PathCache {
+    paths: HashMap<u64, CachedPath>,
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Next tile entity `entity_id` standing on `from` should walk to on its way to `goal`
+    pub fn next_waypoint(
+        &mut self,
+        entity_id: u64,
+        map: &Map,
+        from: Tile,
+        goal: Tile,
+        params: &PathParams,
+    ) -> Option<Tile> {
+        if let Some(cached) = self.paths.get_mut(&entity_id) {
+            // Drop the tiles that have been reached
+            if let Some(index) = cached.tiles.iter().position(|&tile| tile == from) {
+                cached.tiles.drain(..=index);
+            }
+
+            let still_valid = cached.goal == goal
+                && cached.tiles.front().is_some_and(|&next| {
+                    next.0.abs_diff(from.0) <= 1
+                        && next.1.abs_diff(from.1) <= 1
+                        && is_passable(map, next, params)
+                });
+            if still_valid {
+                return cached.tiles.front().copied();
+            }
+        }
+
+        let tiles: VecDeque<Tile> = find_path(map, from, goal, params)?.into();
+        let next = tiles.front().copied();
+        self.paths.insert(entity_id, CachedPath { goal, tiles });
+        next
+    }
+
+    /// Forget the path of an entity, e.g. once it stops chasing
+    pub fn clear(&mut self, entity_id: u64) {
+        self.paths.remove(&entity_id);
+    }
+
+    /// Drop the paths of entities that no longer exist
+    pub fn retain(&mut self, keep: impl Fn(u64) -> bool) {
+        self.paths.retain(|&id, _| keep(id));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::map::MapType;
+
+    /// Build a map from rows of '#' (wall), '.' (floor) and 'D' (door)
+    pub(crate) fn map_from(rows: &[&str]) -> Map {
+        let tiles = rows
+            .iter()
+            .flat_map(|row| {
+                row.chars().map(|c| match c {
+                    '.' => 1,
+                    'D' => 2,
+                    _ => 0,
+                })
+            })
+            .collect();
+        Map {
+            id: 1,
+            name: "test".to_string(),
+            map_type: MapType::Dungeon,
+            width: rows[0].len() as u64,
+            height: rows.len() as u64,
+            tiles,
+            terrain: Vec::new(),
+            decorations: Vec::new(),
+            objects: Vec::new(),
+            spawn_position: Vec2 { x: 1.0, y: 1.0 },
+            spawn_points: Vec::new(),
+            is_starting_town: false,
+            entity_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_path_goes_around_walls() {
+        let map = map_from(&[
+            "#######", //
+            "#..#..#", //
+            "#..#..#", //
+            "#.....#", //
+            "#######",
+        ]);
+        let path = find_path(&map, (1, 1), (5, 1), &PathParams::default()).unwrap();
+
+        assert_eq!(path.last(), Some(&(5, 1)));
+        assert!(path.iter().all(|&(x, y)| map.is_walkable(x, y)));
+        assert!(
+            path.contains(&(3, 3)),
+            "The only gap in the wall is at (3, 3)"
+        );
+    }
+
+    #[test]
+    fn test_connectivity_and_corners() {
+        let map = map_from(&[
+            "#####", //
+            "#...#", //
+            "#...#", //
+            "#...#", //
+            "#####",
+        ]);
+        let four = PathParams {
+            connectivity: Connectivity::Four,
+            ..Default::default()
+        };
+        assert_eq!(find_path(&map, (1, 1), (3, 3), &four).unwrap().len(), 4);
+        assert_eq!(
+            find_path(&map, (1, 1), (3, 3), &PathParams::default())
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // Diagonal steps never squeeze between two walls
+        let map = map_from(&[
+            "####", //
+            "#.##", //
+            "##.#", //
+            "####",
+        ]);
+        assert_eq!(
+            find_path(&map, (1, 1), (2, 2), &PathParams::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_door_cost_and_blocked_tiles() {
+        let map = map_from(&[
+            "#######", //
+            "#..D..#", //
+            "#.###.#", //
+            "#.....#", //
+            "#######",
+        ]);
+        let through_door = find_path(&map, (1, 1), (5, 1), &PathParams::default()).unwrap();
+        assert!(through_door.contains(&(3, 1)));
+
+        let avoid_doors = PathParams {
+            door_cost: 100,
+            ..Default::default()
+        };
+        let around = find_path(&map, (1, 1), (5, 1), &avoid_doors).unwrap();
+        assert!(!around.contains(&(3, 1)));
+
+        let mut blocked = PathParams::default();
+        blocked.blocked.insert((3, 1));
+        blocked.blocked.insert((3, 3));
+        assert_eq!(find_path(&map, (1, 1), (5, 1), &blocked), None);
+    }
+
+    #[test]
+    fn test_cache_recomputes_when_target_moves() {
+        let map = map_from(&[
+            "#######", //
+            "#.....#", //
+            "#.....#", //
+            "#######",
+        ]);
+        let params = PathParams::default();
+        let mut cache = PathCache::new();
+
+        assert_eq!(
+            cache.next_waypoint(7, &map, (1, 1), (5, 1), &params),
+            Some((2, 1))
+        );
+        // Still following the same path after reaching the first waypoint
+        assert_eq!(
+            cache.next_waypoint(7, &map, (2, 1), (5, 1), &params),
+            Some((3, 1))
+        );
+        // The target moved to the row below
+        assert_eq!(
+            cache.next_waypoint(7, &map, (2, 1), (2, 2), &params),
+            Some((2, 2))
+        );
+    }
+}
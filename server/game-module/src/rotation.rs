@@ -0,0 +1,257 @@
+use crate::map_generator::DungeonParams;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The Unix epoch fell on a Thursday; shifting by three days makes weeks start on Monday
+const EPOCH_WEEKDAY_OFFSET: u64 = 3;
+
+/// How often a rotating dungeon is replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotationPeriod {
+    Daily,
+    Weekly,
+}
+
+impl RotationPeriod {
+    /// Index of the period containing `unix_seconds` (days or weeks since the epoch)
+    pub fn index_at(self, unix_seconds: u64) -> u64 {
+        let day = unix_seconds / SECONDS_PER_DAY;
+        match self {
+            RotationPeriod::Daily => day,
+            RotationPeriod::Weekly => (day + EPOCH_WEEKDAY_OFFSET) / 7,
+        }
+    }
+
+    /// Whether the rotation of period `index` can be taken down at `unix_seconds`. Monsters and
+    /// loot never leave its map on their own, so only players still inside keep it around.
+    pub fn can_retire(self, index: u64, unix_seconds: u64, players_inside: usize) -> bool {
+        index < self.index_at(unix_seconds) && players_inside == 0
+    }
+
+    /// Seed shared by every server for the given period index
+    pub fn seed(self, index: u64) -> u64 {
+        let salt = match self {
+            RotationPeriod::Daily => 0x4441_494c_5900_0000, // "DAILY"
+            RotationPeriod::Weekly => 0x5745_454b_4c59_0000, // "WEEKLY"
+        };
+        splitmix64(index ^ salt)
+    }
+
+    /// Number of modifiers rolled for a rotation of this period
+    pub fn modifier_count(self) -> usize {
+        match self {
+            RotationPeriod::Daily => 1,
+            RotationPeriod::Weekly => 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RotationPeriod::Daily => "Daily",
+            RotationPeriod::Weekly => "Weekly",
+        }
+    }
+}
+
+/// Twists applied to a rotating dungeon's generation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DungeonModifier {
+    Sprawling, // More rooms
+    Cramped,   // Fewer, smaller rooms
+    Secretive, // Many more secret passages
+    Royal,     // Throne room at the center
+    Vast,      // Great hall at the center and larger rooms
+}
+
+const ALL_MODIFIERS: [DungeonModifier; 5] = [
+    DungeonModifier::Sprawling,
+    DungeonModifier::Cramped,
+    DungeonModifier::Secretive,
+    DungeonModifier::Royal,
+    DungeonModifier::Vast,
+];
+
+impl DungeonModifier {
+    pub fn name(self) -> &'static str {
+        match self {
+            DungeonModifier::Sprawling => "Sprawling",
+            DungeonModifier::Cramped => "Cramped",
+            DungeonModifier::Secretive => "Secretive",
+            DungeonModifier::Royal => "Royal",
+            DungeonModifier::Vast => "Vast",
+        }
+    }
+
+    /// Adjust dungeon generation parameters for this modifier
+    pub fn apply(self, params: &mut DungeonParams) {
+        match self {
+            DungeonModifier::Sprawling => {
+                params.rooms_width += 2;
+                params.rooms_height += 2;
+            }
+            DungeonModifier::Cramped => {
+                params.rooms_width = params.rooms_width.saturating_sub(2).max(3);
+                params.rooms_height = params.rooms_height.saturating_sub(2).max(3);
+            }
+            DungeonModifier::Secretive => params.secret_door_chance = 0.5,
+            DungeonModifier::Royal => {
+                params.central_room_template = Some("central_throne_room".to_string())
+            }
+            DungeonModifier::Vast => {
+                params.central_room_template = Some("central_great_hall".to_string());
+                params.room_width += 5;
+                params.room_height += 5;
+            }
+        }
+    }
+
+    /// Pick `count` distinct modifiers deterministically from a seed
+    pub fn roll(seed: u64, count: usize) -> Vec<DungeonModifier> {
+        let mut pool = ALL_MODIFIERS.to_vec();
+        let mut state = seed;
+        let mut picked = Vec::new();
+
+        while picked.len() < count && !pool.is_empty() {
+            state = splitmix64(state);
+            let modifier = pool.remove((state % pool.len() as u64) as usize);
+
+            // Both modifiers replace the central room, so never pick them together
+            let conflicts = matches!(
+                (modifier, picked.last()),
+                (DungeonModifier::Royal, Some(DungeonModifier::Vast))
+                    | (DungeonModifier::Vast, Some(DungeonModifier::Royal))
+            );
+            if !conflicts {
+                picked.push(modifier);
+            }
+        }
+
+        picked
+    }
+}
+
+/// Everything needed to generate one rotation of a rotating dungeon
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    pub period: RotationPeriod,
+    pub index: u64,
+    pub seed: u64,
+    pub name: String,
+    pub modifiers: Vec<DungeonModifier>,
+    pub params: DungeonParams,
+}
+
+impl Rotation {
+    /// Build the rotation of a period that is active at `unix_seconds`
+    pub fn at(period: RotationPeriod, unix_seconds: u64) -> Self {
+        let index = period.index_at(unix_seconds);
+        let seed = period.seed(index);
+        let modifiers = DungeonModifier::roll(seed, period.modifier_count());
+
+        let mut params = DungeonParams::default();
+        for modifier in &modifiers {
+            modifier.apply(&mut params);
+        }
+
+        Rotation {
+            period,
+            index,
+            seed,
+            name: format!("{} Dungeon #{}", period.name(), index),
+            modifiers,
+            params,
+        }
+    }
+}
+
+/// Small, well-mixed hash so the same period gives the same seed on every platform
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_generator::{GenerationParams, Generator, MapType};
+
+    // Monday 2024-01-01 00:00:00 UTC
+    const MONDAY: u64 = 1_704_067_200;
+
+    #[test]
+    fn test_same_day_same_rotation() {
+        let morning = Rotation::at(RotationPeriod::Daily, MONDAY + 3_600);
+        let evening = Rotation::at(RotationPeriod::Daily, MONDAY + 80_000);
+        let tomorrow = Rotation::at(RotationPeriod::Daily, MONDAY + SECONDS_PER_DAY);
+
+        assert_eq!(morning.seed, evening.seed);
+        assert_eq!(morning.modifiers, evening.modifiers);
+        assert_ne!(morning.seed, tomorrow.seed);
+    }
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        let monday = RotationPeriod::Weekly.index_at(MONDAY);
+        let sunday = RotationPeriod::Weekly.index_at(MONDAY + 7 * SECONDS_PER_DAY - 1);
+        let previous_sunday = RotationPeriod::Weekly.index_at(MONDAY - 1);
+
+        assert_eq!(monday, sunday);
+        assert_eq!(previous_sunday + 1, monday);
+    }
+
+    #[test]
+    fn test_past_rotations_retire_once_players_leave() {
+        let index = RotationPeriod::Daily.index_at(MONDAY);
+        let tomorrow = MONDAY + SECONDS_PER_DAY;
+
+        // Today's rotation stays even when it only holds monsters and loot
+        assert!(!RotationPeriod::Daily.can_retire(index, MONDAY + 3_600, 0));
+        assert!(RotationPeriod::Daily.can_retire(index, tomorrow, 0));
+        assert!(!RotationPeriod::Daily.can_retire(index, tomorrow, 1));
+        assert!(!RotationPeriod::Weekly.can_retire(
+            RotationPeriod::Weekly.index_at(MONDAY),
+            tomorrow,
+            0
+        ));
+    }
+
+    #[test]
+    fn test_modifiers_are_distinct_and_compatible() {
+        for index in 0..200 {
+            let rotation = Rotation::at(RotationPeriod::Weekly, index * 7 * SECONDS_PER_DAY);
+            let modifiers = &rotation.modifiers;
+
+            assert!(!modifiers.is_empty());
+            assert!(modifiers.len() <= RotationPeriod::Weekly.modifier_count());
+            assert!(
+                !(modifiers.contains(&DungeonModifier::Royal)
+                    && modifiers.contains(&DungeonModifier::Vast))
+            );
+            if modifiers.len() == 2 {
+                assert_ne!(modifiers[0], modifiers[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation_generates_the_same_layout() {
+        let rotation = Rotation::at(RotationPeriod::Daily, MONDAY);
+        let generate = || {
+            let params = GenerationParams {
+                dungeon: rotation.params.clone(),
+                ..Default::default()
+            };
+            Generator::generate_map(
+                MapType::Dungeon,
+                rotation.name.clone(),
+                rotation.seed,
+                params,
+            )
+            .expect("Rotation dungeon should generate")
+        };
+
+        assert_eq!(generate().tiles, generate().tiles);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::map::Vec2;
+use crate::map_generator::Position;
+
+/// Radius (in tiles) at which an entity with 1.0 perception notices secret passages
+/// just by walking past them
+pub const PASSIVE_DETECTION_RADIUS: f64 = 1.5;
+
+/// Extra radius granted by actively searching instead of walking past
+pub const SEARCH_BONUS_RADIUS: f64 = 2.0;
+
+/// Radius at which an entity passively notices secret passages
+pub fn passive_detection_radius(perception: f64) -> f64 {
+    PASSIVE_DETECTION_RADIUS * perception.max(0.0)
+}
+
+/// Radius covered by an active search
+pub fn search_radius(perception: f64) -> f64 {
+    passive_detection_radius(perception) + SEARCH_BONUS_RADIUS
+}
+
+/// Check if a secret passage tile is within `radius` of a position
+pub fn is_within_radius(secret: &Position, position: &Vec2, radius: f64) -> bool {
+    let dx = secret.x as f64 - position.x;
+    let dy = secret.y as f64 - position.y;
+    dx * dx + dy * dy <= radius * radius
+}
+
+/// Get all secret passages within `radius` of a position
+pub fn secrets_in_radius<'a>(
+    secrets: &'a [Position],
+    position: &Vec2,
+    radius: f64,
+) -> Vec<&'a Position> {
+    secrets
+        .iter()
+        .filter(|secret| is_within_radius(secret, position, radius))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_finds_more_than_walking_past() {
+        let secrets = [Position { x: 5, y: 5 }, Position { x: 8, y: 5 }];
+        let position = Vec2 { x: 5.0, y: 6.0 };
+
+        let passive = secrets_in_radius(&secrets, &position, passive_detection_radius(1.0));
+        assert_eq!(passive, vec![&Position { x: 5, y: 5 }]);
+
+        let searched = secrets_in_radius(&secrets, &position, search_radius(1.0));
+        assert_eq!(searched.len(), 2);
+    }
+
+    #[test]
+    fn test_no_perception_only_search_works() {
+        let secrets = [Position { x: 2, y: 2 }];
+        let position = Vec2 { x: 2.0, y: 3.0 };
+
+        assert!(secrets_in_radius(&secrets, &position, passive_detection_radius(0.0)).is_empty());
+        assert_eq!(
+            secrets_in_radius(&secrets, &position, search_radius(0.0)).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::entity::{
+    load_game_entity, store_game_entity, store_spawned_entities, FIRST_SPAWNED_ID,
+};
+use crate::init::to_game_map;
+use crate::tables::{map, player};
+use crate::tick::record_combat_events;
+use crate::types::Vec2;
+use game_module::entity::abilities::AbilityOutcome;
+use game_module::entity::{AbilityTarget, EntityManager};
+use game_module::pathfinding::{PathCache, PathParams};
+use spacetimedb::{reducer, ReducerContext};
+
+#[reducer]
+/// Clients invoke this reducer to use one of their abilities on an entity, at a position, or
+/// with neither on themselves. Range and line of sight are checked against the map.
+pub fn use_ability(
+    ctx: &ReducerContext,
+    ability: String,
+    target_entity_id: Option<u64>,
+    target_position: Option<Vec2>,
+) -> Result<(), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+    let map_id = player.current_map_id.ok_or("Player is not in a map")?;
+    let mut map = ctx.db.map().id().find(map_id).ok_or("Map not found")?;
+
+    // Everything in the map may be caught by the ability
+    let mut manager = EntityManager::new();
+    for &id in &map.entity_ids {
+        load_game_entity(ctx, &mut manager, id);
+    }
+    if !manager.contains(entity_id) {
+        load_game_entity(ctx, &mut manager, entity_id);
+    }
+    let loaded_ids: Vec<u64> = manager.entity_ids().collect();
+    manager.set_next_id(FIRST_SPAWNED_ID);
+    manager.set_navigation(
+        to_game_map(&map),
+        PathParams::default(),
+        PathCache::default(),
+    );
+    manager.set_seed(ctx.timestamp.to_micros_since_unix_epoch() as u64 ^ entity_id.rotate_left(32));
+
+    let target = match (target_entity_id, target_position) {
+        (Some(target_id), _) => AbilityTarget::Entity(target_id),
+        (None, Some(position)) => AbilityTarget::Point(game_module::map::Vec2 {
+            x: position.x,
+            y: position.y,
+        }),
+        (None, None) => AbilityTarget::Caster,
+    };
+    let now = ctx.timestamp.to_micros_since_unix_epoch() as f64 / 1_000_000.0;
+    let outcome = manager.use_ability(entity_id, &ability, target, now)?;
+
+    record_combat_events(ctx, map_id, manager.take_combat_events());
+    for id in loaded_ids {
+        store_game_entity(ctx, &manager, id);
+    }
+    if !store_spawned_entities(ctx, &mut manager, &mut map).is_empty() {
+        ctx.db.map().id().update(map);
+    }
+
+    match outcome {
+        AbilityOutcome::Casting { completes_at } => log::info!(
+            "{:?} started casting {} (done at {:.2})",
+            ctx.sender,
+            ability,
+            completes_at
+        ),
+        AbilityOutcome::Resolved { hit_entity_ids } => log::info!(
+            "{:?} used {} and reached {} entities",
+            ctx.sender,
+            ability,
+            hit_entity_ids.len()
+        ),
+        AbilityOutcome::Launched { .. } => {
+            log::info!("{:?} launched {}", ctx.sender, ability)
+        }
+    }
+    Ok(())
+}
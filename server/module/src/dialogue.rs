@@ -0,0 +1,113 @@
+use crate::entity::{load_game_entity, store_game_entity};
+use crate::tables::{map, player, player_conversation};
+use crate::tick::record_level_ups;
+use game_module::entity::{Conversation, EntityManager, InteractionResult, Talkable};
+use spacetimedb::{reducer, ReducerContext};
+
+/// Load the calling player's entity, and the target if it is in the same map.
+/// Returns the manager and the player's entity ID.
+pub fn load_player_and_target(
+    ctx: &ReducerContext,
+    target_entity_id: u64,
+) -> Result<(EntityManager, u64), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+
+    let mut manager = EntityManager::new();
+    load_game_entity(ctx, &mut manager, entity_id);
+    if !manager.contains(entity_id) {
+        return Err("Entity not found".to_string());
+    }
+    let target_in_map = player
+        .current_map_id
+        .and_then(|map_id| ctx.db.map().id().find(map_id))
+        .is_some_and(|map| map.entity_ids.contains(&target_entity_id));
+    if target_in_map {
+        load_game_entity(ctx, &mut manager, target_entity_id);
+    }
+    Ok((manager, entity_id))
+}
+
+#[reducer]
+/// Clients invoke this reducer to start talking to an NPC next to their entity. The
+/// conversation shows up in `player_conversation`.
+pub fn talk_to_npc(ctx: &ReducerContext, npc_entity_id: u64) -> Result<(), String> {
+    let (mut manager, entity_id) = load_player_and_target(ctx, npc_entity_id)?;
+    if !manager.has::<Talkable>(npc_entity_id) {
+        return Err("Cannot talk to that".to_string());
+    }
+    let InteractionResult::NPCInteraction { node } =
+        manager.interact_entities(entity_id, npc_entity_id)?
+    else {
+        return Err("Invalid interaction".to_string());
+    };
+    store_game_entity(ctx, &manager, entity_id);
+
+    log::info!(
+        "{:?} started talking to {} ({})",
+        ctx.sender,
+        npc_entity_id,
+        node.id
+    );
+    Ok(())
+}
+
+#[reducer]
+/// Clients invoke this reducer to answer the NPC they are talking to with one of the
+/// choices in their `player_conversation` row.
+pub fn choose_dialogue_option(ctx: &ReducerContext, option: u32) -> Result<(), String> {
+    let conversation = ctx
+        .db
+        .player_conversation()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Not in a conversation")?;
+    let (mut manager, entity_id) = load_player_and_target(ctx, conversation.npc_entity_id)?;
+    match manager.choose_dialogue_option(entity_id, option as usize) {
+        Ok(_) => {}
+        // Walking away ends the conversation; failing the reducer would undo that
+        Err(error) if !manager.has::<Conversation>(entity_id) => {
+            log::info!("{:?} left a conversation: {}", ctx.sender, error);
+        }
+        Err(error) => return Err(error),
+    }
+    store_game_entity(ctx, &manager, entity_id);
+
+    // Quest rewards can be enough experience for a level
+    if let Some(map_id) = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .and_then(|player| player.current_map_id)
+    {
+        record_level_ups(ctx, map_id, manager.take_level_ups());
+    }
+    Ok(())
+}
+
+#[reducer]
+/// Clients invoke this reducer to walk away from the NPC they are talking to.
+pub fn end_conversation(ctx: &ReducerContext) -> Result<(), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+
+    let mut manager = EntityManager::new();
+    load_game_entity(ctx, &mut manager, entity_id);
+    if !manager.contains(entity_id) {
+        return Err("Entity not found".to_string());
+    }
+    manager.end_conversation(entity_id);
+    store_game_entity(ctx, &manager, entity_id);
+    Ok(())
+}
@@ -1,6 +1,1287 @@
-use crate::tables::{entity, player, Entity, EntityType};
+use crate::init::to_game_map;
+use crate::tables::{
+    entity, entity_abilities, entity_ai, entity_archetype, entity_damage_taken, entity_death,
+    entity_dialogue, entity_effects, entity_inventory, entity_item, entity_projectile,
+    entity_state, entity_stats, entity_vendor, map, player, player_conversation, player_progress,
+    player_quests, player_wallet, AbilityCast, AbilityCooldown, ActiveEffect, AiModeKind,
+    DamageContribution, DamageTypeKind, DialogueChoice, Entity, EntityAbilities, EntityAi,
+    EntityArchetype, EntityDamageTaken, EntityDeath, EntityDialogue, EntityEffects,
+    EntityInventory, EntityItem, EntityProjectile, EntityState, EntityStateKind, EntityStats,
+    EntityType, EntityVendor, EquipSlotKind, EquippedItem, FactionKind, HitOutcomeKind,
+    InventoryStack, Map, PlayerConversation, PlayerProgress, PlayerQuests, PlayerWallet,
+    QuestEntry, QuestStatusKind, StatusEffectKind,
+};
 use crate::types::Vec2;
-use spacetimedb::{reducer, ReducerContext, Table};
+use game_module::combat::{DamageType, HitOutcome, Resistances};
+use game_module::entity::abilities::find_ability;
+use game_module::entity::behavior::find_behavior;
+use game_module::entity::dialogue::find_dialogue;
+use game_module::entity::items::{find_item, EquipSlot};
+use game_module::entity::prefabs::{find_archetype, Archetype};
+use game_module::entity::quests::{find_quest, QuestProgress, QuestStatus};
+use game_module::entity::shops::find_shop;
+use game_module::entity::{self as logic, prefabs, EntityManager};
+use spacetimedb::{reducer, Identity, ReducerContext, Table};
+
+impl From<&logic::EntityState> for EntityStateKind {
+    fn from(state: &logic::EntityState) -> Self {
+        match state {
+            logic::EntityState::Idle => EntityStateKind::Idle,
+            logic::EntityState::Moving => EntityStateKind::Moving,
+            logic::EntityState::Attacking => EntityStateKind::Attacking,
+            logic::EntityState::Dead => EntityStateKind::Dead,
+            logic::EntityState::Interacting => EntityStateKind::Interacting,
+        }
+    }
+}
+
+impl From<EntityStateKind> for logic::EntityState {
+    fn from(state: EntityStateKind) -> Self {
+        match state {
+            EntityStateKind::Idle => logic::EntityState::Idle,
+            EntityStateKind::Moving => logic::EntityState::Moving,
+            EntityStateKind::Attacking => logic::EntityState::Attacking,
+            EntityStateKind::Dead => logic::EntityState::Dead,
+            EntityStateKind::Interacting => logic::EntityState::Interacting,
+        }
+    }
+}
+
+impl From<logic::AiMode> for AiModeKind {
+    fn from(mode: logic::AiMode) -> Self {
+        match mode {
+            logic::AiMode::Idle => AiModeKind::Idle,
+            logic::AiMode::Patrol => AiModeKind::Patrol,
+            logic::AiMode::Chase => AiModeKind::Chase,
+            logic::AiMode::Attack => AiModeKind::Attack,
+            logic::AiMode::Flee => AiModeKind::Flee,
+            logic::AiMode::Return => AiModeKind::Return,
+        }
+    }
+}
+
+impl From<AiModeKind> for logic::AiMode {
+    fn from(mode: AiModeKind) -> Self {
+        match mode {
+            AiModeKind::Idle => logic::AiMode::Idle,
+            AiModeKind::Patrol => logic::AiMode::Patrol,
+            AiModeKind::Chase => logic::AiMode::Chase,
+            AiModeKind::Attack => logic::AiMode::Attack,
+            AiModeKind::Flee => logic::AiMode::Flee,
+            AiModeKind::Return => logic::AiMode::Return,
+        }
+    }
+}
+
+impl From<DamageType> for DamageTypeKind {
+    fn from(damage_type: DamageType) -> Self {
+        match damage_type {
+            DamageType::Physical => DamageTypeKind::Physical,
+            DamageType::Fire => DamageTypeKind::Fire,
+            DamageType::Ice => DamageTypeKind::Ice,
+            DamageType::Poison => DamageTypeKind::Poison,
+        }
+    }
+}
+
+impl From<DamageTypeKind> for DamageType {
+    fn from(damage_type: DamageTypeKind) -> Self {
+        match damage_type {
+            DamageTypeKind::Physical => DamageType::Physical,
+            DamageTypeKind::Fire => DamageType::Fire,
+            DamageTypeKind::Ice => DamageType::Ice,
+            DamageTypeKind::Poison => DamageType::Poison,
+        }
+    }
+}
+
+impl From<HitOutcome> for HitOutcomeKind {
+    fn from(outcome: HitOutcome) -> Self {
+        match outcome {
+            HitOutcome::Miss => HitOutcomeKind::Miss,
+            HitOutcome::Hit => HitOutcomeKind::Hit,
+            HitOutcome::Critical => HitOutcomeKind::Critical,
+        }
+    }
+}
+
+impl From<logic::EffectKind> for StatusEffectKind {
+    fn from(kind: logic::EffectKind) -> Self {
+        match kind {
+            logic::EffectKind::Poison => StatusEffectKind::Poison,
+            logic::EffectKind::Burn => StatusEffectKind::Burn,
+            logic::EffectKind::Bleed => StatusEffectKind::Bleed,
+            logic::EffectKind::Stun => StatusEffectKind::Stun,
+            logic::EffectKind::Slow => StatusEffectKind::Slow,
+            logic::EffectKind::Root => StatusEffectKind::Root,
+            logic::EffectKind::Regeneration => StatusEffectKind::Regeneration,
+            logic::EffectKind::Haste => StatusEffectKind::Haste,
+        }
+    }
+}
+
+impl From<StatusEffectKind> for logic::EffectKind {
+    fn from(kind: StatusEffectKind) -> Self {
+        match kind {
+            StatusEffectKind::Poison => logic::EffectKind::Poison,
+            StatusEffectKind::Burn => logic::EffectKind::Burn,
+            StatusEffectKind::Bleed => logic::EffectKind::Bleed,
+            StatusEffectKind::Stun => logic::EffectKind::Stun,
+            StatusEffectKind::Slow => logic::EffectKind::Slow,
+            StatusEffectKind::Root => logic::EffectKind::Root,
+            StatusEffectKind::Regeneration => logic::EffectKind::Regeneration,
+            StatusEffectKind::Haste => logic::EffectKind::Haste,
+        }
+    }
+}
+
+impl From<logic::Faction> for FactionKind {
+    fn from(faction: logic::Faction) -> Self {
+        match faction {
+            logic::Faction::Players => FactionKind::Players,
+            logic::Faction::Monsters => FactionKind::Monsters,
+            logic::Faction::Neutral => FactionKind::Neutral,
+        }
+    }
+}
+
+impl From<FactionKind> for logic::Faction {
+    fn from(faction: FactionKind) -> Self {
+        match faction {
+            FactionKind::Players => logic::Faction::Players,
+            FactionKind::Monsters => logic::Faction::Monsters,
+            FactionKind::Neutral => logic::Faction::Neutral,
+        }
+    }
+}
+
+impl From<EquipSlot> for EquipSlotKind {
+    fn from(slot: EquipSlot) -> Self {
+        match slot {
+            EquipSlot::Weapon => EquipSlotKind::Weapon,
+            EquipSlot::Armor => EquipSlotKind::Armor,
+            EquipSlot::Trinket1 => EquipSlotKind::Trinket1,
+            EquipSlot::Trinket2 => EquipSlotKind::Trinket2,
+        }
+    }
+}
+
+impl From<EquipSlotKind> for EquipSlot {
+    fn from(slot: EquipSlotKind) -> Self {
+        match slot {
+            EquipSlotKind::Weapon => EquipSlot::Weapon,
+            EquipSlotKind::Armor => EquipSlot::Armor,
+            EquipSlotKind::Trinket1 => EquipSlot::Trinket1,
+            EquipSlotKind::Trinket2 => EquipSlot::Trinket2,
+        }
+    }
+}
+
+impl From<QuestStatus> for QuestStatusKind {
+    fn from(status: QuestStatus) -> Self {
+        match status {
+            QuestStatus::Active => QuestStatusKind::Active,
+            QuestStatus::Completed => QuestStatusKind::Completed,
+        }
+    }
+}
+
+impl From<QuestStatusKind> for QuestStatus {
+    fn from(status: QuestStatusKind) -> Self {
+        match status {
+            QuestStatusKind::Active => QuestStatus::Active,
+            QuestStatusKind::Completed => QuestStatus::Completed,
+        }
+    }
+}
+
+impl EntityStats {
+    /// Stats row of an entity that can fight or be hurt
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let health = manager.get::<logic::Health>(entity_id);
+        let combat = manager.get::<logic::Combat>(entity_id);
+        if health.is_none() && combat.is_none() {
+            return None;
+        }
+
+        let health = health
+            .cloned()
+            .unwrap_or(logic::Health { current: 0, max: 0 });
+        let combat = combat.cloned().unwrap_or_default();
+        let defense = manager
+            .get::<logic::Defense>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        let senses = manager
+            .get::<logic::Senses>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        Some(EntityStats {
+            entity_id,
+            health: health.current,
+            max_health: health.max,
+            attack_damage: combat.attack_damage,
+            damage_type: combat.damage_type.into(),
+            accuracy: combat.accuracy,
+            crit_chance: combat.crit_chance,
+            crit_multiplier: combat.crit_multiplier,
+            evasion: defense.evasion,
+            physical_resistance: defense.resistances.physical,
+            fire_resistance: defense.resistances.fire,
+            ice_resistance: defense.resistances.ice,
+            poison_resistance: defense.resistances.poison,
+            attack_range: combat.attack_range,
+            attack_cooldown: combat.attack_cooldown,
+            last_attack_time: combat.last_attack_time,
+            perception: senses.perception,
+        })
+    }
+
+    /// Overwrite the components the entity's type gave it with the stored values
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let id = self.entity_id;
+        if manager.has::<logic::Health>(id) {
+            manager.insert(
+                id,
+                logic::Health {
+                    current: self.health,
+                    max: self.max_health,
+                },
+            );
+        }
+        if manager.has::<logic::Combat>(id) {
+            manager.insert(
+                id,
+                logic::Combat {
+                    attack_damage: self.attack_damage,
+                    damage_type: self.damage_type.into(),
+                    accuracy: self.accuracy,
+                    crit_chance: self.crit_chance,
+                    crit_multiplier: self.crit_multiplier,
+                    attack_range: self.attack_range,
+                    attack_cooldown: self.attack_cooldown,
+                    last_attack_time: self.last_attack_time,
+                },
+            );
+        }
+        let defense = logic::Defense {
+            evasion: self.evasion,
+            resistances: Resistances {
+                physical: self.physical_resistance,
+                fire: self.fire_resistance,
+                ice: self.ice_resistance,
+                poison: self.poison_resistance,
+            },
+        };
+        if manager.has::<logic::Defense>(id) || defense != logic::Defense::default() {
+            manager.insert(id, defense);
+        }
+        if manager.has::<logic::Senses>(id) {
+            manager.insert(
+                id,
+                logic::Senses {
+                    perception: self.perception,
+                },
+            );
+        }
+    }
+}
+
+impl EntityState {
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Self {
+        EntityState {
+            entity_id,
+            state: manager
+                .get::<logic::EntityState>(entity_id)
+                .map_or(EntityStateKind::Idle, EntityStateKind::from),
+            target_entity_id: manager
+                .get::<logic::Ai>(entity_id)
+                .and_then(|ai| ai.target_entity_id),
+            base_move_speed: manager
+                .get::<logic::Movement>(entity_id)
+                .map_or(0.0, |movement| movement.speed),
+            move_speed: manager.effective_speed(entity_id),
+        }
+    }
+
+    /// Overwrite the components the entity's type gave it with the stored values
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let id = self.entity_id;
+        manager.insert(id, logic::EntityState::from(self.state));
+        if let Some(ai) = manager.get_mut::<logic::Ai>(id) {
+            ai.target_entity_id = self.target_entity_id;
+        }
+        if let Some(movement) = manager.get_mut::<logic::Movement>(id) {
+            movement.speed = self.base_move_speed;
+        }
+    }
+}
+
+impl EntityAi {
+    /// AI row of an entity that acts on its own
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let ai = manager.get::<logic::Ai>(entity_id)?;
+        Some(EntityAi {
+            entity_id,
+            behavior: ai.behavior.to_string(),
+            mode: ai.mode.into(),
+            home: Vec2 {
+                x: ai.home.x,
+                y: ai.home.y,
+            },
+            patrol_route: ai
+                .patrol_route
+                .iter()
+                .map(|waypoint| Vec2 {
+                    x: waypoint.x,
+                    y: waypoint.y,
+                })
+                .collect(),
+            patrol_index: ai.patrol_index as u32,
+        })
+    }
+
+    /// Overwrite the AI the entity's type gave it with the stored values.
+    /// Unknown behaviour names keep the type's behaviour.
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let Some(ai) = manager.get_mut::<logic::Ai>(self.entity_id) else {
+            return;
+        };
+        if let Some(behavior) = find_behavior(&self.behavior) {
+            ai.behavior = behavior.name;
+        }
+        ai.mode = self.mode.into();
+        ai.home = game_module::map::Vec2 {
+            x: self.home.x,
+            y: self.home.y,
+        };
+        ai.patrol_route = self
+            .patrol_route
+            .iter()
+            .map(|waypoint| game_module::map::Vec2 {
+                x: waypoint.x,
+                y: waypoint.y,
+            })
+            .collect();
+        ai.patrol_index = self.patrol_index as usize;
+    }
+}
+
+impl EntityEffects {
+    /// Effects row of an entity with at least one active status effect
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let effects = manager.get::<logic::StatusEffects>(entity_id)?;
+        if effects.active.is_empty() {
+            return None;
+        }
+        Some(EntityEffects {
+            entity_id,
+            effects: effects
+                .active
+                .iter()
+                .map(|effect| ActiveEffect {
+                    kind: effect.kind.into(),
+                    source_entity_id: effect.source_entity_id,
+                    magnitude: effect.magnitude,
+                    stacks: effect.stacks,
+                    expires_at: effect.expires_at,
+                    next_tick_at: effect.next_tick_at,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let active = self
+            .effects
+            .iter()
+            .map(|effect| logic::StatusEffect {
+                kind: effect.kind.into(),
+                source_entity_id: effect.source_entity_id,
+                magnitude: effect.magnitude,
+                stacks: effect.stacks,
+                expires_at: effect.expires_at,
+                next_tick_at: effect.next_tick_at,
+            })
+            .collect();
+        manager.insert(self.entity_id, logic::StatusEffects { active });
+    }
+}
+
+impl EntityAbilities {
+    /// Abilities row of an entity that can use abilities
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let abilities = manager.get::<logic::Abilities>(entity_id)?;
+        let resources = manager
+            .get::<logic::Resources>(entity_id)
+            .cloned()
+            .unwrap_or(logic::Resources::new(0.0, 0.0));
+        let casting = manager.get::<logic::Casting>(entity_id).map(|casting| {
+            let (target_entity_id, target_position) = match &casting.target {
+                logic::AbilityTarget::Caster => (None, None),
+                logic::AbilityTarget::Entity(id) => (Some(*id), None),
+                logic::AbilityTarget::Point(point) => (
+                    None,
+                    Some(Vec2 {
+                        x: point.x,
+                        y: point.y,
+                    }),
+                ),
+            };
+            AbilityCast {
+                ability: casting.ability.to_string(),
+                target_entity_id,
+                target_position,
+                completes_at: casting.completes_at,
+            }
+        });
+        Some(EntityAbilities {
+            entity_id,
+            known: abilities
+                .known
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            cooldowns: abilities
+                .ready_at
+                .iter()
+                .map(|(name, &ready_at)| AbilityCooldown {
+                    ability: name.to_string(),
+                    ready_at,
+                })
+                .collect(),
+            casting,
+            mana: resources.mana,
+            max_mana: resources.max_mana,
+            energy: resources.energy,
+            max_energy: resources.max_energy,
+        })
+    }
+
+    /// Overwrite the abilities the entity's type gave it with the stored values.
+    /// Unknown ability names are dropped.
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let id = self.entity_id;
+        let known = self
+            .known
+            .iter()
+            .filter_map(|name| find_ability(name))
+            .map(|ability| ability.name)
+            .collect();
+        let ready_at = self
+            .cooldowns
+            .iter()
+            .filter_map(|cooldown| Some((find_ability(&cooldown.ability)?.name, cooldown.ready_at)))
+            .collect();
+        manager.insert(id, logic::Abilities { known, ready_at });
+        manager.insert(
+            id,
+            logic::Resources {
+                mana: self.mana,
+                max_mana: self.max_mana,
+                energy: self.energy,
+                max_energy: self.max_energy,
+            },
+        );
+
+        let casting = self.casting.as_ref().and_then(|cast| {
+            let target = match (cast.target_entity_id, &cast.target_position) {
+                (Some(target_id), _) => logic::AbilityTarget::Entity(target_id),
+                (None, Some(point)) => logic::AbilityTarget::Point(game_module::map::Vec2 {
+                    x: point.x,
+                    y: point.y,
+                }),
+                (None, None) => logic::AbilityTarget::Caster,
+            };
+            Some(logic::Casting {
+                ability: find_ability(&cast.ability)?.name,
+                target,
+                completes_at: cast.completes_at,
+            })
+        });
+        match casting {
+            Some(casting) => manager.insert(id, casting),
+            None => {
+                manager.remove_component::<logic::Casting>(id);
+            }
+        }
+    }
+}
+
+impl EntityProjectile {
+    /// Flight row of a projectile entity
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let projectile = manager.get::<logic::Projectile>(entity_id)?;
+        Some(EntityProjectile {
+            entity_id,
+            ability: projectile.ability.to_string(),
+            owner_entity_id: manager
+                .get::<logic::Owner>(entity_id)
+                .map(|owner| owner.entity_id),
+            faction: projectile.faction.into(),
+            velocity: Vec2 {
+                x: projectile.velocity.x,
+                y: projectile.velocity.y,
+            },
+            radius: manager
+                .get::<logic::Collider>(entity_id)
+                .map_or(0.0, |collider| collider.radius),
+            pierce: projectile.pierce,
+            hit_entity_ids: projectile.hit_entity_ids.clone(),
+            expires_at: manager
+                .get::<logic::Lifetime>(entity_id)
+                .map_or(0.0, |lifetime| lifetime.expires_at),
+        })
+    }
+
+    /// Set the projectile in flight again. Unknown ability names leave it to expire.
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let id = self.entity_id;
+        manager.insert(
+            id,
+            logic::Collider {
+                radius: self.radius,
+                blocks_entities: false,
+            },
+        );
+        manager.insert(
+            id,
+            logic::Lifetime {
+                expires_at: self.expires_at,
+            },
+        );
+        if let Some(owner_id) = self.owner_entity_id {
+            manager.insert(
+                id,
+                logic::Owner {
+                    entity_id: owner_id,
+                },
+            );
+        }
+        if let Some(ability) = find_ability(&self.ability) {
+            manager.insert(
+                id,
+                logic::Projectile {
+                    ability: ability.name,
+                    faction: self.faction.into(),
+                    velocity: game_module::map::Vec2 {
+                        x: self.velocity.x,
+                        y: self.velocity.y,
+                    },
+                    pierce: self.pierce,
+                    hit_entity_ids: self.hit_entity_ids.clone(),
+                },
+            );
+        }
+    }
+}
+
+impl EntityInventory {
+    /// Inventory row of an entity that carries items
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let inventory = manager.get::<logic::Inventory>(entity_id)?;
+        let equipment = manager
+            .get::<logic::Equipment>(entity_id)
+            .cloned()
+            .unwrap_or_default();
+        Some(EntityInventory {
+            entity_id,
+            slot_count: inventory.slots.len() as u32,
+            stacks: inventory
+                .slots
+                .iter()
+                .enumerate()
+                .filter_map(|(slot, stack)| {
+                    let stack = stack.as_ref()?;
+                    Some(InventoryStack {
+                        slot: slot as u32,
+                        item_id: stack.item.to_string(),
+                        count: stack.count,
+                    })
+                })
+                .collect(),
+            equipment: equipment
+                .slots
+                .iter()
+                .map(|(&slot, item)| EquippedItem {
+                    slot: slot.into(),
+                    item_id: item.to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Overwrite the inventory the entity's type gave it with the stored items.
+    /// Unknown items are dropped.
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let mut slots = vec![None; self.slot_count as usize];
+        for stack in &self.stacks {
+            let (Some(slot), Some(item)) = (
+                slots.get_mut(stack.slot as usize),
+                find_item(&stack.item_id),
+            ) else {
+                continue;
+            };
+            *slot = Some(logic::ItemStack {
+                item: item.id,
+                count: stack.count,
+            });
+        }
+        let equipment = self
+            .equipment
+            .iter()
+            .filter_map(|worn| Some((worn.slot.into(), find_item(&worn.item_id)?.id)))
+            .collect();
+        manager.insert(self.entity_id, logic::Inventory { slots });
+        manager.insert(self.entity_id, logic::Equipment { slots: equipment });
+    }
+}
+
+impl EntityItem {
+    /// Item row of an item entity on the ground
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let stack = manager.get::<logic::ItemStack>(entity_id)?;
+        Some(EntityItem {
+            entity_id,
+            item_id: stack.item.to_string(),
+            count: stack.count,
+        })
+    }
+
+    /// Give the item entity its stack; unknown items leave it with nothing to pick up
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        if let Some(item) = find_item(&self.item_id) {
+            manager.insert(
+                self.entity_id,
+                logic::ItemStack {
+                    item: item.id,
+                    count: self.count,
+                },
+            );
+        }
+    }
+}
+
+impl EntityDamageTaken {
+    /// Damage row of an entity that has been hurt by someone
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let taken = manager.get::<logic::DamageTaken>(entity_id)?;
+        if taken.by.is_empty() {
+            return None;
+        }
+        Some(EntityDamageTaken {
+            entity_id,
+            contributions: taken
+                .by
+                .iter()
+                .map(|(&attacker_entity_id, &damage)| DamageContribution {
+                    attacker_entity_id,
+                    damage,
+                })
+                .collect(),
+        })
+    }
+
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        let by = self
+            .contributions
+            .iter()
+            .map(|contribution| (contribution.attacker_entity_id, contribution.damage))
+            .collect();
+        manager.insert(self.entity_id, logic::DamageTaken { by });
+    }
+}
+
+impl EntityDeath {
+    /// Death row of a respawning entity that has not been brought back yet
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let death = manager.get::<logic::Death>(entity_id)?;
+        Some(EntityDeath {
+            entity_id,
+            died_at: death.died_at,
+        })
+    }
+
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        manager.insert(
+            self.entity_id,
+            logic::Death {
+                died_at: self.died_at,
+            },
+        );
+    }
+}
+
+impl PlayerProgress {
+    /// Progress row of the player owning an entity that gains experience
+    pub fn from_components(
+        manager: &EntityManager,
+        entity_id: u64,
+        identity: Identity,
+    ) -> Option<Self> {
+        let experience = manager.get::<logic::Experience>(entity_id)?;
+        Some(PlayerProgress {
+            identity,
+            level: experience.level,
+            xp: experience.xp,
+        })
+    }
+
+    /// Give the player's entity their level and experience. Entities without stored stats
+    /// start from the stats of their type, so they also get the growth of every level.
+    pub fn apply_to(&self, manager: &mut EntityManager, entity_id: u64, fresh_stats: bool) {
+        let Some(experience) = manager.get_mut::<logic::Experience>(entity_id) else {
+            return;
+        };
+        let gained = self.level.saturating_sub(experience.level);
+        experience.level = self.level;
+        experience.xp = self.xp;
+        if fresh_stats {
+            manager.grow(entity_id, gained);
+        }
+    }
+}
+
+impl EntityArchetype {
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let kind = manager.get::<logic::MonsterKind>(entity_id)?;
+        Some(EntityArchetype {
+            entity_id,
+            archetype: kind.archetype.to_string(),
+        })
+    }
+
+    /// The archetype the entity's components start from
+    pub fn archetype(&self) -> Option<&'static Archetype> {
+        find_archetype(&self.archetype)
+    }
+}
+
+impl EntityVendor {
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let vendor = manager.get::<logic::Vendor>(entity_id)?;
+        Some(EntityVendor {
+            entity_id,
+            shop: vendor.shop.to_string(),
+            stock: vendor.stock.clone(),
+            restock_at: vendor.restock_at,
+        })
+    }
+
+    /// Vendors of shops that no longer exist stop selling
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        if let Some(shop) = find_shop(&self.shop) {
+            manager.insert(
+                self.entity_id,
+                logic::Vendor {
+                    shop: shop.name,
+                    stock: self.stock.clone(),
+                    restock_at: self.restock_at,
+                },
+            );
+        }
+    }
+}
+
+impl EntityDialogue {
+    pub fn from_components(manager: &EntityManager, entity_id: u64) -> Option<Self> {
+        let talkable = manager.get::<logic::Talkable>(entity_id)?;
+        Some(EntityDialogue {
+            entity_id,
+            dialogue: talkable.dialogue.to_string(),
+        })
+    }
+
+    /// Talk with the stored dialogue; unknown dialogues keep the default of the entity's type
+    pub fn apply_to(&self, manager: &mut EntityManager) {
+        if let Some(dialogue) = find_dialogue(&self.dialogue) {
+            manager.insert(
+                self.entity_id,
+                logic::Talkable {
+                    dialogue: dialogue.name,
+                },
+            );
+        }
+    }
+}
+
+impl PlayerQuests {
+    /// Quest row of the player owning an entity that takes quests
+    pub fn from_components(
+        manager: &EntityManager,
+        entity_id: u64,
+        identity: Identity,
+    ) -> Option<Self> {
+        let log = manager.get::<logic::QuestLog>(entity_id)?;
+        Some(PlayerQuests {
+            identity,
+            quests: log
+                .quests
+                .iter()
+                .map(|(&quest_id, progress)| QuestEntry {
+                    quest_id: quest_id.to_string(),
+                    status: progress.status.into(),
+                    progress: progress.counts.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Quests that no longer exist are dropped
+    pub fn apply_to(&self, manager: &mut EntityManager, entity_id: u64) {
+        let Some(log) = manager.get_mut::<logic::QuestLog>(entity_id) else {
+            return;
+        };
+        log.quests = self
+            .quests
+            .iter()
+            .filter_map(|entry| {
+                let progress = QuestProgress {
+                    status: entry.status.into(),
+                    counts: entry.progress.clone(),
+                };
+                Some((find_quest(&entry.quest_id)?.id, progress))
+            })
+            .collect();
+    }
+}
+
+impl PlayerWallet {
+    /// Wallet row of the player owning an entity that carries gold
+    pub fn from_components(
+        manager: &EntityManager,
+        entity_id: u64,
+        identity: Identity,
+    ) -> Option<Self> {
+        let wallet = manager.get::<logic::Wallet>(entity_id)?;
+        Some(PlayerWallet {
+            identity,
+            gold: wallet.gold,
+        })
+    }
+
+    pub fn apply_to(&self, manager: &mut EntityManager, entity_id: u64) {
+        if let Some(wallet) = manager.get_mut::<logic::Wallet>(entity_id) {
+            wallet.gold = self.gold;
+        }
+    }
+}
+
+impl PlayerConversation {
+    /// Conversation row of the player owning an entity that is talking to an NPC, with the
+    /// options they may currently pick
+    pub fn from_components(
+        manager: &EntityManager,
+        entity_id: u64,
+        identity: Identity,
+    ) -> Option<Self> {
+        let conversation = manager.get::<logic::Conversation>(entity_id)?;
+        let node = manager.dialogue_node(entity_id)?;
+        Some(PlayerConversation {
+            identity,
+            npc_entity_id: conversation.npc_id,
+            dialogue: conversation.dialogue.to_string(),
+            node: node.id.to_string(),
+            text: node.text.to_string(),
+            choices: manager
+                .dialogue_options(entity_id)
+                .into_iter()
+                .map(|option| DialogueChoice {
+                    option: option as u32,
+                    text: node.options[option].text.to_string(),
+                })
+                .collect(),
+            shop_open: conversation.shop_open,
+        })
+    }
+
+    /// Conversations at dialogues or nodes that no longer exist are dropped
+    pub fn apply_to(&self, manager: &mut EntityManager, entity_id: u64) {
+        let Some(dialogue) = find_dialogue(&self.dialogue) else {
+            return;
+        };
+        let Some(node) = dialogue.node(&self.node) else {
+            return;
+        };
+        manager.insert(
+            entity_id,
+            logic::Conversation {
+                npc_id: self.npc_entity_id,
+                dialogue: dialogue.name,
+                node: node.id,
+                shop_open: self.shop_open,
+            },
+        );
+    }
+}
+
+/// Components every entity of a stored type starts with. Monsters without a known archetype
+/// start as the default monster.
+fn prefab(
+    entity_type: &EntityType,
+    archetype: Option<&'static Archetype>,
+    position: game_module::map::Vec2,
+) -> logic::EntityBuilder {
+    match entity_type {
+        EntityType::Player => prefabs::player(position),
+        EntityType::Monster => match archetype {
+            Some(archetype) => prefabs::archetype(archetype, position),
+            None => prefabs::monster(position),
+        },
+        EntityType::Npc => prefabs::npc(position),
+        // Summons are monsters that fight on the players' side
+        EntityType::Summoned => prefabs::monster(position)
+            .with(logic::Faction::Players)
+            .without::<logic::Loot>()
+            .without::<logic::MonsterKind>(),
+        // The stack comes from `entity_item`
+        EntityType::Item => logic::EntityBuilder::new()
+            .with(logic::EntityState::Idle)
+            .with(logic::Pickup),
+        // The flight comes from `entity_projectile`; without it the projectile just expires
+        EntityType::Projectile => logic::EntityBuilder::new()
+            .with(logic::EntityState::Moving)
+            .with(logic::Lifetime { expires_at: 0.0 }),
+        // Removed by `respawn` through the owner identity of its entity row
+        EntityType::Gravestone => logic::EntityBuilder::new()
+            .with(logic::EntityState::Idle)
+            .with(logic::Gravestone),
+    }
+}
+
+/// Rows stored for an entity next to its `entity` row; missing rows leave the defaults of the
+/// entity's type in place
+#[derive(Default)]
+pub struct EntityRows {
+    pub stats: Option<EntityStats>,
+    pub state: Option<EntityState>,
+    pub ai: Option<EntityAi>,
+    pub effects: Option<EntityEffects>,
+    pub abilities: Option<EntityAbilities>,
+    pub projectile: Option<EntityProjectile>,
+    pub inventory: Option<EntityInventory>,
+    pub item: Option<EntityItem>,
+    pub damage_taken: Option<EntityDamageTaken>,
+    pub death: Option<EntityDeath>,
+    pub archetype: Option<EntityArchetype>,
+    pub dialogue: Option<EntityDialogue>,
+    pub vendor: Option<EntityVendor>,
+    pub progress: Option<PlayerProgress>,
+    pub quests: Option<PlayerQuests>,
+    pub wallet: Option<PlayerWallet>,
+    pub conversation: Option<PlayerConversation>,
+}
+
+impl EntityRows {
+    pub fn find(ctx: &ReducerContext, entity: &Entity) -> Self {
+        let entity_id = entity.id;
+        EntityRows {
+            stats: ctx.db.entity_stats().entity_id().find(entity_id),
+            state: ctx.db.entity_state().entity_id().find(entity_id),
+            ai: ctx.db.entity_ai().entity_id().find(entity_id),
+            effects: ctx.db.entity_effects().entity_id().find(entity_id),
+            abilities: ctx.db.entity_abilities().entity_id().find(entity_id),
+            projectile: ctx.db.entity_projectile().entity_id().find(entity_id),
+            inventory: ctx.db.entity_inventory().entity_id().find(entity_id),
+            item: ctx.db.entity_item().entity_id().find(entity_id),
+            damage_taken: ctx.db.entity_damage_taken().entity_id().find(entity_id),
+            death: ctx.db.entity_death().entity_id().find(entity_id),
+            archetype: ctx.db.entity_archetype().entity_id().find(entity_id),
+            dialogue: ctx.db.entity_dialogue().entity_id().find(entity_id),
+            vendor: ctx.db.entity_vendor().entity_id().find(entity_id),
+            progress: find_progress(ctx, entity),
+            quests: player_identity(entity)
+                .and_then(|identity| ctx.db.player_quests().identity().find(identity)),
+            wallet: player_identity(entity)
+                .and_then(|identity| ctx.db.player_wallet().identity().find(identity)),
+            conversation: player_identity(entity)
+                .and_then(|identity| ctx.db.player_conversation().identity().find(identity)),
+        }
+    }
+}
+
+/// Add a stored entity to the game logic
+pub fn spawn_game_entity(manager: &mut EntityManager, entity: &Entity, rows: &EntityRows) {
+    let position = game_module::map::Vec2 {
+        x: entity.position.x,
+        y: entity.position.y,
+    };
+    manager.spawn_with_id(
+        entity.id,
+        prefab(
+            &entity.entity_type,
+            rows.archetype.as_ref().and_then(EntityArchetype::archetype),
+            position.clone(),
+        )
+        .with(logic::Transform {
+            position,
+            direction: entity.direction,
+        }),
+    );
+
+    if let Some(stats) = &rows.stats {
+        stats.apply_to(manager);
+    }
+    if let Some(state) = &rows.state {
+        state.apply_to(manager);
+    }
+    if let Some(ai) = &rows.ai {
+        ai.apply_to(manager);
+    }
+    if let Some(effects) = &rows.effects {
+        effects.apply_to(manager);
+    }
+    if let Some(abilities) = &rows.abilities {
+        abilities.apply_to(manager);
+    }
+    if let Some(projectile) = &rows.projectile {
+        projectile.apply_to(manager);
+    }
+    if let Some(inventory) = &rows.inventory {
+        inventory.apply_to(manager);
+    }
+    if let Some(item) = &rows.item {
+        item.apply_to(manager);
+    }
+    if let Some(damage_taken) = &rows.damage_taken {
+        damage_taken.apply_to(manager);
+    }
+    if let Some(death) = &rows.death {
+        death.apply_to(manager);
+    }
+    if let Some(dialogue) = &rows.dialogue {
+        dialogue.apply_to(manager);
+    }
+    if let Some(vendor) = &rows.vendor {
+        vendor.apply_to(manager);
+    }
+    if let Some(progress) = &rows.progress {
+        progress.apply_to(manager, entity.id, rows.stats.is_none());
+    }
+    if let Some(quests) = &rows.quests {
+        quests.apply_to(manager, entity.id);
+    }
+    if let Some(wallet) = &rows.wallet {
+        wallet.apply_to(manager, entity.id);
+    }
+    if let Some(conversation) = &rows.conversation {
+        conversation.apply_to(manager, entity.id);
+    }
+}
+
+/// Identity of the player an entity is the character of. Other entities a player owns, like
+/// gravestones, do not share their rows.
+fn player_identity(entity: &Entity) -> Option<Identity> {
+    match entity.entity_type {
+        EntityType::Player => entity.owner_identity,
+        _ => None,
+    }
+}
+
+/// Progress of the player owning an entity
+fn find_progress(ctx: &ReducerContext, entity: &Entity) -> Option<PlayerProgress> {
+    ctx.db
+        .player_progress()
+        .identity()
+        .find(player_identity(entity)?)
+}
+
+/// Load an entity together with all of its rows into the game logic
+pub fn load_game_entity(ctx: &ReducerContext, manager: &mut EntityManager, entity_id: u64) {
+    let Some(entity) = ctx.db.entity().id().find(entity_id) else {
+        return;
+    };
+    spawn_game_entity(manager, &entity, &EntityRows::find(ctx, &entity));
+}
+
+/// Bring the row of a companion table in line with what the components say it should be:
+/// insert, update or delete it, leaving it untouched when nothing changed. The key names both
+/// the table's unique column and the variable holding its value.
+macro_rules! sync_row {
+    ($ctx:expr, $table:ident[$key:ident] => $row:expr) => {
+        match ($row, $ctx.db.$table().$key().find($key)) {
+            (Some(row), Some(existing)) => {
+                if row != existing {
+                    $ctx.db.$table().$key().update(row);
+                }
+            }
+            (Some(row), None) => {
+                $ctx.db.$table().insert(row);
+            }
+            (None, Some(_)) => {
+                $ctx.db.$table().$key().delete($key);
+            }
+            (None, None) => {}
+        }
+    };
+}
+
+/// Write the components of an entity back to the tables, touching only rows that changed
+pub fn store_game_entity(ctx: &ReducerContext, manager: &EntityManager, entity_id: u64) {
+    let entity = ctx.db.entity().id().find(entity_id);
+    let owner_identity = entity.as_ref().and_then(player_identity);
+    if let (Some(mut entity), Some(transform)) =
+        (entity, manager.get::<logic::Transform>(entity_id))
+    {
+        if entity.position.x != transform.position.x
+            || entity.position.y != transform.position.y
+            || entity.direction != transform.direction
+        {
+            entity.position.x = transform.position.x;
+            entity.position.y = transform.position.y;
+            entity.direction = transform.direction;
+            ctx.db.entity().id().update(entity);
+        }
+    }
+
+    sync_row!(ctx, entity_stats[entity_id] => EntityStats::from_components(manager, entity_id));
+    sync_row!(ctx, entity_state[entity_id] =>
+        Some(EntityState::from_components(manager, entity_id)));
+    sync_row!(ctx, entity_ai[entity_id] => EntityAi::from_components(manager, entity_id));
+    sync_row!(ctx, entity_effects[entity_id] => EntityEffects::from_components(manager, entity_id));
+    sync_row!(ctx, entity_abilities[entity_id] =>
+        EntityAbilities::from_components(manager, entity_id));
+    sync_row!(ctx, entity_projectile[entity_id] =>
+        EntityProjectile::from_components(manager, entity_id));
+    sync_row!(ctx, entity_inventory[entity_id] =>
+        EntityInventory::from_components(manager, entity_id));
+    sync_row!(ctx, entity_item[entity_id] => EntityItem::from_components(manager, entity_id));
+    sync_row!(ctx, entity_damage_taken[entity_id] =>
+        EntityDamageTaken::from_components(manager, entity_id));
+    sync_row!(ctx, entity_death[entity_id] => EntityDeath::from_components(manager, entity_id));
+    sync_row!(ctx, entity_archetype[entity_id] =>
+        EntityArchetype::from_components(manager, entity_id));
+    sync_row!(ctx, entity_vendor[entity_id] => EntityVendor::from_components(manager, entity_id));
+    sync_row!(ctx, entity_dialogue[entity_id] =>
+        EntityDialogue::from_components(manager, entity_id));
+
+    // Player rows are kept by identity, so they are never deleted with the entity; only the
+    // conversation ends
+    let Some(identity) = owner_identity else {
+        return;
+    };
+    if let Some(progress) = PlayerProgress::from_components(manager, entity_id, identity) {
+        sync_row!(ctx, player_progress[identity] => Some(progress));
+    }
+    if let Some(quests) = PlayerQuests::from_components(manager, entity_id, identity) {
+        sync_row!(ctx, player_quests[identity] => Some(quests));
+    }
+    if let Some(wallet) = PlayerWallet::from_components(manager, entity_id, identity) {
+        sync_row!(ctx, player_wallet[identity] => Some(wallet));
+    }
+    sync_row!(ctx, player_conversation[identity] =>
+        PlayerConversation::from_components(manager, entity_id, identity));
+}
+
+/// Entities spawned by the game logic get IDs from here on until they are stored, far above
+/// anything the database assigns
+pub const FIRST_SPAWNED_ID: u64 = 1 << 62;
+
+/// Insert the entities the game logic spawned into a map, giving them database IDs.
+/// Returns the new IDs; the caller saves the map row.
+pub fn store_spawned_entities(
+    ctx: &ReducerContext,
+    manager: &mut EntityManager,
+    map: &mut Map,
+) -> Vec<u64> {
+    let spawned_ids: Vec<u64> = manager
+        .entity_ids()
+        .filter(|&id| id >= FIRST_SPAWNED_ID)
+        .collect();
+    let mut stored_ids = Vec::new();
+    for spawned_id in spawned_ids {
+        let Some(transform) = manager.get::<logic::Transform>(spawned_id).cloned() else {
+            manager.remove_entity(spawned_id);
+            continue;
+        };
+        let entity_type = if manager.has::<logic::Projectile>(spawned_id) {
+            EntityType::Projectile
+        } else if manager.has::<logic::ItemStack>(spawned_id) {
+            EntityType::Item
+        } else if manager.has::<logic::Gravestone>(spawned_id) {
+            EntityType::Gravestone
+        } else if manager.has::<logic::Talkable>(spawned_id) {
+            EntityType::Npc
+        } else {
+            EntityType::Monster
+        };
+        // Gravestones belong to the player who died, so `respawn` can clear them
+        let owner_identity = match entity_type {
+            EntityType::Gravestone => manager
+                .get::<logic::Owner>(spawned_id)
+                .and_then(|owner| ctx.db.entity().id().find(owner.entity_id))
+                .and_then(|owner| owner.owner_identity),
+            _ => None,
+        };
+        let entity = ctx.db.entity().insert(Entity {
+            id: 0, // auto_inc will handle this
+            entity_type,
+            position: Vec2 {
+                x: transform.position.x,
+                y: transform.position.y,
+            },
+            direction: transform.direction,
+            owner_identity,
+            created_at: ctx.timestamp,
+        });
+        if manager.rekey_entity(spawned_id, entity.id).is_err() {
+            ctx.db.entity().id().delete(entity.id);
+            manager.remove_entity(spawned_id);
+            continue;
+        }
+        store_game_entity(ctx, manager, entity.id);
+        map.entity_ids.push(entity.id);
+        stored_ids.push(entity.id);
+    }
+    stored_ids
+}
+
+/// Insert an entity along with the default stats and state of its type
+pub fn spawn_entity(ctx: &ReducerContext, entity: Entity) -> u64 {
+    let entity = ctx.db.entity().insert(entity);
+    let mut manager = EntityManager::new();
+    // A player's new entity starts at the level they already reached, with their quests and
+    // gold
+    let rows = EntityRows {
+        progress: find_progress(ctx, &entity),
+        quests: player_identity(&entity)
+            .and_then(|identity| ctx.db.player_quests().identity().find(identity)),
+        wallet: player_identity(&entity)
+            .and_then(|identity| ctx.db.player_wallet().identity().find(identity)),
+        ..EntityRows::default()
+    };
+    spawn_game_entity(&mut manager, &entity, &rows);
+    store_game_entity(ctx, &manager, entity.id);
+    entity.id
+}
+
+/// Delete an entity and all of its rows
+pub fn delete_entity(ctx: &ReducerContext, entity_id: u64) {
+    ctx.db.entity().id().delete(entity_id);
+    ctx.db.entity_stats().entity_id().delete(entity_id);
+    ctx.db.entity_state().entity_id().delete(entity_id);
+    ctx.db.entity_ai().entity_id().delete(entity_id);
+    ctx.db.entity_effects().entity_id().delete(entity_id);
+    ctx.db.entity_abilities().entity_id().delete(entity_id);
+    ctx.db.entity_projectile().entity_id().delete(entity_id);
+    ctx.db.entity_inventory().entity_id().delete(entity_id);
+    ctx.db.entity_item().entity_id().delete(entity_id);
+    ctx.db.entity_damage_taken().entity_id().delete(entity_id);
+    ctx.db.entity_death().entity_id().delete(entity_id);
+    ctx.db.entity_archetype().entity_id().delete(entity_id);
+    ctx.db.entity_dialogue().entity_id().delete(entity_id);
+    ctx.db.entity_vendor().entity_id().delete(entity_id);
+}
+
+/// Perception of an entity, or the default for entities without stats
+pub fn entity_perception(ctx: &ReducerContext, entity_id: u64) -> f64 {
+    ctx.db
+        .entity_stats()
+        .entity_id()
+        .find(entity_id)
+        .map(|stats| stats.perception)
+        .unwrap_or_else(|| logic::Senses::default().perception)
+}
 
 #[reducer]
 pub fn create_player_entity(ctx: &ReducerContext) -> Result<(), String> {
@@ -23,7 +1304,7 @@ pub fn create_player_entity(ctx: &ReducerContext) -> Result<(), String> {
         created_at: ctx.timestamp,
     };
 
-    let entity_id = ctx.db.entity().insert(entity).id;
+    let entity_id = spawn_entity(ctx, entity);
 
     // Update player with entity_id
     if let Some(mut player) = ctx.db.player().identity().find(ctx.sender) {
@@ -34,24 +1315,61 @@ pub fn create_player_entity(ctx: &ReducerContext) -> Result<(), String> {
     Ok(())
 }
 
-pub fn move_entity(ctx: &ReducerContext, entity_id: u64, x: f64, y: f64) -> Result<(), String> {
-    if let Some(mut entity) = ctx.db.entity().id().find(entity_id) {
-        // Verify the entity belongs to the calling player
-        if entity.owner_identity != Some(ctx.sender) {
-            return Err("You don't own this entity".to_string());
-        }
-
-        // Position validation would need to be handled differently
-        // Since entities no longer store location references, validation logic
-        // should be implemented at a higher level or through other means
+/// Walk an entity towards a position in its map, stopping at walls and other bodies and
+/// sliding along them. Returns where the entity ended up.
+pub fn move_entity(
+    ctx: &ReducerContext,
+    entity_id: u64,
+    map_id: Option<u64>,
+    x: f64,
+    y: f64,
+) -> Result<Vec2, String> {
+    let entity = ctx
+        .db
+        .entity()
+        .id()
+        .find(entity_id)
+        .ok_or("Entity not found")?;
 
-        // Update entity position
-        entity.position.x = x;
-        entity.position.y = y;
-        ctx.db.entity().id().update(entity);
+    // Verify the entity belongs to the calling player
+    if entity.owner_identity != Some(ctx.sender) {
+        return Err("You don't own this entity".to_string());
+    }
 
-        Ok(())
-    } else {
-        Err("Entity not found".to_string())
+    // Only bodies near the path can block the way: check the positions of the other entities
+    // of the map first and load just those
+    let map = map_id.and_then(|id| ctx.db.map().id().find(id));
+    let reach = 2.0 * logic::MAX_COLLIDER_RADIUS;
+    let near_path = |position: &Vec2| {
+        position.x >= entity.position.x.min(x) - reach
+            && position.x <= entity.position.x.max(x) + reach
+            && position.y >= entity.position.y.min(y) - reach
+            && position.y <= entity.position.y.max(y) + reach
+    };
+    let mut manager = EntityManager::new();
+    load_game_entity(ctx, &mut manager, entity_id);
+    for &id in map.iter().flat_map(|map| &map.entity_ids) {
+        let nearby = ctx
+            .db
+            .entity()
+            .id()
+            .find(id)
+            .is_some_and(|other| id != entity_id && near_path(&other.position));
+        if nearby {
+            load_game_entity(ctx, &mut manager, id);
+        }
     }
+
+    let game_map = map.as_ref().map(to_game_map);
+    let position = manager.move_entity(
+        entity_id,
+        game_module::map::Vec2 { x, y },
+        game_map.as_ref(),
+    )?;
+    store_game_entity(ctx, &manager, entity_id);
+
+    Ok(Vec2 {
+        x: position.x,
+        y: position.y,
+    })
 }
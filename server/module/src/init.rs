@@ -1,6 +1,13 @@
+use crate::entity::{store_spawned_entities, FIRST_SPAWNED_ID};
+use crate::quests::QUEST_GIVERS;
+use crate::shop::VENDORS;
 use crate::tables::{game_info, map, GameInfo, Map, MapType};
-use crate::types::Vec2;
-use game_module::map_generator;
+use crate::types::{MapProp, Vec2};
+use game_module::combat::Dice;
+use game_module::entity::loot::{find_loot_table, roll_loot};
+use game_module::entity::shops::find_shop;
+use game_module::entity::{prefabs, EntityManager, Talkable};
+use game_module::map_generator::{self, Entrance, LayerProp, LootSpot, MapGenerationResult};
 use spacetimedb::{reducer, ReducerContext, Table};
 
 #[reducer(init)]
@@ -17,8 +24,17 @@ pub fn init(ctx: &ReducerContext) {
         }
     };
 
-    // Generate exploration dungeon
-    generate_exploration_dungeon(ctx);
+    // Generate exploration dungeon and connect it to the town through their portals
+    if let Some(dungeon_id) = generate_exploration_dungeon(ctx) {
+        for (from, to) in [
+            (starting_town_id, dungeon_id),
+            (dungeon_id, starting_town_id),
+        ] {
+            if let Err(e) = crate::travel::link_maps(ctx, from, to) {
+                log::error!("Failed to link maps: {}", e);
+            }
+        }
+    }
 
     // Initialize game info with the starting town
     if let Err(e) = initialize_game_info(ctx, starting_town_id) {
@@ -98,6 +114,9 @@ fn generate_starting_town(ctx: &ReducerContext) -> Option<u64> {
         width: town_result.width as u64,
         height: town_result.height as u64,
         tiles: town_result.tiles,
+        terrain: town_result.layers.terrain,
+        decorations: to_map_props(&town_result.layers.decorations),
+        objects: to_map_props(&town_result.layers.objects),
         spawn_position,
         spawn_points,
         is_starting_town: town_result.is_starting_town,
@@ -117,6 +136,15 @@ fn generate_starting_town(ctx: &ReducerContext) -> Option<u64> {
         .max_by_key(|t| t.created_at)
         .map(|t| t.id);
 
+    if let Some(town_id) = town_id {
+        generate_building_interiors(ctx, town_id, &town_result.entrances);
+        if let Some(mut town) = ctx.db.map().id().find(town_id) {
+            if place_town_npcs(ctx, &mut town) > 0 {
+                ctx.db.map().id().update(town);
+            }
+        }
+    }
+
     log::info!(
         "Starting town generated: {} areas, size {}x{}, {} spawn points (seed: {}, generation time: {}ms)",
         town_result.metadata.room_count,
@@ -130,8 +158,83 @@ fn generate_starting_town(ctx: &ReducerContext) -> Option<u64> {
     town_id
 }
 
-/// Generate exploration dungeon
-fn generate_exploration_dungeon(ctx: &ReducerContext) {
+/// Generate the interior behind each town entrance and link it to the town both ways
+fn generate_building_interiors(ctx: &ReducerContext, town_id: u64, entrances: &[Entrance]) {
+    for (i, entrance) in entrances.iter().enumerate() {
+        let Some(template_name) = entrance.interior else {
+            continue;
+        };
+
+        let interior_result = match map_generator::Generator::generate_interior(
+            template_name.replace('_', " "),
+            town_id * 100 + i as u64, // Stable seed per building
+            template_name,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                log::error!("Failed to generate interior '{}': {}", template_name, e);
+                continue;
+            }
+        };
+
+        let interior = Map {
+            id: 0, // auto_inc will handle this
+            name: interior_result.name,
+            map_type: MapType::Interior,
+            width: interior_result.width as u64,
+            height: interior_result.height as u64,
+            tiles: interior_result.tiles,
+            terrain: interior_result.layers.terrain,
+            decorations: to_map_props(&interior_result.layers.decorations),
+            objects: to_map_props(&interior_result.layers.objects),
+            spawn_position: Vec2 {
+                x: interior_result.spawn_position.x as f64,
+                y: interior_result.spawn_position.y as f64,
+            },
+            spawn_points: interior_result
+                .spawn_points
+                .iter()
+                .map(|pos| Vec2 {
+                    x: pos.x as f64,
+                    y: pos.y as f64,
+                })
+                .collect(),
+            is_starting_town: false,
+            entity_ids: Vec::new(),
+            created_at: ctx.timestamp,
+        };
+        let interior_id = ctx.db.map().insert(interior).id;
+
+        // Town door -> interior spawn point, interior exit -> street outside the door
+        crate::travel::add_transition(
+            ctx,
+            town_id,
+            &entrance.position,
+            interior_id,
+            &interior_result.spawn_position,
+        );
+        for exit in &interior_result.entrances {
+            crate::travel::add_transition(
+                ctx,
+                interior_id,
+                &exit.position,
+                town_id,
+                &entrance.arrival,
+            );
+        }
+
+        log::info!(
+            "Building interior '{}' generated as map {} ({}x{})",
+            template_name,
+            interior_id,
+            interior_result.width,
+            interior_result.height
+        );
+    }
+}
+
+/// Generate exploration dungeon and return its ID
+fn generate_exploration_dungeon(ctx: &ReducerContext) -> Option<u64> {
     let dungeon_result = map_generator::Generator::generate_dungeon(
         "Exploration Dungeon".to_string(),
         123, // Different seed for dungeon
@@ -146,10 +249,28 @@ fn generate_exploration_dungeon(ctx: &ReducerContext) {
         Ok(result) => result,
         Err(e) => {
             log::error!("{}", e);
-            return;
+            return None;
         }
     };
 
+    let dungeon_id = store_dungeon(ctx, &dungeon_result);
+
+    log::info!(
+        "Exploration dungeon generated: {} rooms, {} secret passages, size {}x{} (seed: {}, generation time: {}ms)",
+        dungeon_result.metadata.room_count,
+        dungeon_result.secret_passages.len(),
+        dungeon_result.width,
+        dungeon_result.height,
+        dungeon_result.metadata.seed,
+        dungeon_result.metadata.generation_time_ms.unwrap_or(0)
+    );
+
+    Some(dungeon_id)
+}
+
+/// Store a generated dungeon with its secret passages, monster spawners and chest loot,
+/// returning the new map ID
+pub fn store_dungeon(ctx: &ReducerContext, dungeon_result: &MapGenerationResult) -> u64 {
     // Convert spawn position and points to Vec2
     let dungeon_spawn_position = Vec2 {
         x: dungeon_result.spawn_position.x as f64,
@@ -165,13 +286,29 @@ fn generate_exploration_dungeon(ctx: &ReducerContext) {
         })
         .collect();
 
+    // Props behind secret passages are published once the passages are found
+    let is_visible = |prop: &&LayerProp| {
+        !dungeon_result
+            .hidden_areas
+            .iter()
+            .any(|area| area.contains(&prop.position))
+    };
+
     let dungeon = Map {
         id: 0,
-        name: dungeon_result.name,
+        name: dungeon_result.name.clone(),
         map_type: crate::tables::MapType::Dungeon,
         width: dungeon_result.width as u64,
         height: dungeon_result.height as u64,
-        tiles: dungeon_result.tiles,
+        // Never publish secret doors or what lies behind them
+        tiles: crate::secret::disguise_tiles(
+            &dungeon_result.tiles,
+            dungeon_result.width,
+            &dungeon_result.hidden_areas,
+        ),
+        terrain: dungeon_result.layers.terrain.clone(),
+        decorations: to_map_props(dungeon_result.layers.decorations.iter().filter(is_visible)),
+        objects: to_map_props(dungeon_result.layers.objects.iter().filter(is_visible)),
         spawn_position: dungeon_spawn_position,
         spawn_points: dungeon_spawn_points,
         is_starting_town: false,
@@ -179,16 +316,147 @@ fn generate_exploration_dungeon(ctx: &ReducerContext) {
         created_at: ctx.timestamp,
     };
 
-    ctx.db.map().insert(dungeon);
-
-    log::info!(
-        "Exploration dungeon generated: {} rooms, size {}x{} (seed: {}, generation time: {}ms)",
-        dungeon_result.metadata.room_count,
-        dungeon_result.width,
-        dungeon_result.height,
+    let mut dungeon = ctx.db.map().insert(dungeon);
+    let dungeon_id = dungeon.id;
+    crate::secret::store_secret_passages(ctx, dungeon_id, dungeon_result);
+    crate::respawn::place_spawners(ctx, dungeon_id, &dungeon_result.monster_spots);
+    if fill_chests(
+        ctx,
+        &mut dungeon,
+        &dungeon_result.loot_spots,
         dungeon_result.metadata.seed,
-        dungeon_result.metadata.generation_time_ms.unwrap_or(0)
-    );
+    ) > 0
+    {
+        ctx.db.map().id().update(dungeon);
+    }
+    dungeon_id
+}
+
+/// Put the quest givers and vendors around the spawn of the starting town, two tiles away so
+/// they do not block arriving players. Returns how many were placed; the caller saves the map
+/// row.
+fn place_town_npcs(ctx: &ReducerContext, town: &mut Map) -> usize {
+    let game_map = to_game_map(town);
+    let spawn_x = town.spawn_position.x.round() as i64;
+    let spawn_y = town.spawn_position.y.round() as i64;
+    let spots = [
+        (2, 0),
+        (-2, 0),
+        (0, 2),
+        (0, -2),
+        (2, 2),
+        (-2, -2),
+        (2, -2),
+        (-2, 2),
+    ]
+    .into_iter()
+    .map(|(dx, dy)| (spawn_x + dx, spawn_y + dy))
+    .filter(|&(x, y)| x >= 0 && y >= 0 && game_map.is_walkable(x as usize, y as usize))
+    .filter(|&(x, y)| {
+        !town
+            .spawn_points
+            .iter()
+            .any(|point| point.x.round() as i64 == x && point.y.round() as i64 == y)
+    });
+
+    let quest_givers = QUEST_GIVERS.iter().map(|&dialogue| (dialogue, None));
+    let vendors = VENDORS
+        .iter()
+        .map(|&(dialogue, shop)| (dialogue, find_shop(shop)));
+
+    let mut manager = EntityManager::new();
+    manager.set_next_id(FIRST_SPAWNED_ID);
+    for ((dialogue, shop), (x, y)) in quest_givers.chain(vendors).zip(spots) {
+        let position = game_module::map::Vec2 {
+            x: x as f64,
+            y: y as f64,
+        };
+        manager.spawn(match shop {
+            Some(shop) => prefabs::vendor(position, dialogue, shop),
+            None => prefabs::npc(position).with(Talkable { dialogue }),
+        });
+    }
+    let placed = store_spawned_entities(ctx, &mut manager, town).len();
+    log::info!("Placed {} NPCs in {}", placed, town.name);
+    placed
+}
+
+/// Put rolled loot on the chests of a new map, as item entities that stay until picked up.
+/// Loot behind secret passages waits in the private area until they are found. Returns how
+/// many stacks were placed; the caller saves the map row.
+fn fill_chests(ctx: &ReducerContext, map: &mut Map, spots: &[LootSpot], seed: u64) -> usize {
+    let mut dice = Dice::seeded(seed);
+    let mut manager = EntityManager::new();
+    manager.set_next_id(FIRST_SPAWNED_ID);
+    for spot in spots {
+        let Some(table) = find_loot_table(spot.table) else {
+            continue;
+        };
+        let position = game_module::map::Vec2 {
+            x: spot.position.x as f64,
+            y: spot.position.y as f64,
+        };
+        let stacks = roll_loot(table, &mut dice);
+        if crate::secret::hide_loot(ctx, map.id, &spot.position, &stacks) {
+            continue;
+        }
+        for stack in stacks {
+            manager.spawn(prefabs::item(position.clone(), stack));
+        }
+    }
+    store_spawned_entities(ctx, &mut manager, map).len()
+}
+
+/// Convert generated layer props into their compact table representation
+pub fn to_map_props<'a>(props: impl IntoIterator<Item = &'a LayerProp>) -> Vec<MapProp> {
+    props
+        .into_iter()
+        .map(|prop| MapProp {
+            x: prop.position.x as u16,
+            y: prop.position.y as u16,
+            kind: prop.kind,
+        })
+        .collect()
+}
+
+/// Convert table props back into generator layer props
+fn from_map_props(props: &[MapProp]) -> Vec<LayerProp> {
+    props
+        .iter()
+        .map(|prop| LayerProp {
+            position: map_generator::Position {
+                x: prop.x as usize,
+                y: prop.y as usize,
+            },
+            kind: prop.kind,
+        })
+        .collect()
+}
+
+/// Build the game logic view of a stored map, as players see it (secrets stay walls)
+pub fn to_game_map(map: &Map) -> game_module::map::Map {
+    let to_vec2 = |v: &Vec2| game_module::map::Vec2 { x: v.x, y: v.y };
+    game_module::map::Map {
+        id: map.id,
+        name: map.name.clone(),
+        map_type: match map.map_type {
+            MapType::Dungeon => game_module::map::MapType::Dungeon,
+            MapType::Town => game_module::map::MapType::Town,
+            MapType::Wilderness => game_module::map::MapType::Wilderness,
+            MapType::Instance => game_module::map::MapType::Instance,
+            MapType::Interior => game_module::map::MapType::Interior,
+        },
+        width: map.width,
+        height: map.height,
+        tiles: map.tiles.clone(),
+        terrain: map.terrain.clone(),
+        decorations: from_map_props(&map.decorations),
+        objects: from_map_props(&map.objects),
+        spawn_position: to_vec2(&map.spawn_position),
+        spawn_points: map.spawn_points.iter().map(to_vec2).collect(),
+        is_starting_town: map.is_starting_town,
+        entity_ids: map.entity_ids.clone(),
+    }
 }
 
 /// Initialize game systems
@@ -198,6 +466,12 @@ fn initialize_game_systems(ctx: &ReducerContext) {
         Ok(()) => log::info!("Tick system initialized successfully"),
         Err(e) => log::error!("Failed to initialize tick system: {}", e),
     }
+
+    // Start generating the daily and weekly dungeons
+    match crate::rotation::initialize_rotation_system(ctx) {
+        Ok(()) => log::info!("Dungeon rotation system initialized successfully"),
+        Err(e) => log::error!("Failed to initialize dungeon rotation system: {}", e),
+    }
 }
 
 #[reducer]
@@ -0,0 +1,116 @@
+use crate::dialogue::load_player_and_target;
+use crate::entity::{delete_entity, load_game_entity, store_game_entity};
+use crate::tables::{map, player, EquipSlotKind};
+use game_module::entity::{EntityManager, InteractionResult, Inventory, Pickup};
+use spacetimedb::{reducer, ReducerContext};
+
+/// Change the calling player's entity and store the result
+fn update_player_entity<T>(
+    ctx: &ReducerContext,
+    change: impl FnOnce(&mut EntityManager, u64) -> Result<T, String>,
+) -> Result<T, String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+
+    let mut manager = EntityManager::new();
+    load_game_entity(ctx, &mut manager, entity_id);
+    if !manager.contains(entity_id) {
+        return Err("Entity not found".to_string());
+    }
+    let result = change(&mut manager, entity_id)?;
+    store_game_entity(ctx, &manager, entity_id);
+    Ok(result)
+}
+
+/// Change the calling player's inventory
+fn update_player_inventory(
+    ctx: &ReducerContext,
+    change: impl FnOnce(&mut Inventory) -> Result<(), String>,
+) -> Result<(), String> {
+    update_player_entity(ctx, |manager, entity_id| {
+        let inventory = manager
+            .get_mut::<Inventory>(entity_id)
+            .ok_or("Entity has no inventory")?;
+        change(inventory)
+    })
+}
+
+#[reducer]
+/// Wear the item in an inventory slot, swapping out whatever it replaces
+pub fn equip_item(ctx: &ReducerContext, inventory_slot: u32) -> Result<(), String> {
+    let slot = update_player_entity(ctx, |manager, entity_id| {
+        manager.equip_item(entity_id, inventory_slot as usize)
+    })?;
+    log::info!("{:?} equipped an item in {:?}", ctx.sender, slot);
+    Ok(())
+}
+
+#[reducer]
+/// Take off the item in an equipment slot and put it in the inventory
+pub fn unequip_item(ctx: &ReducerContext, slot: EquipSlotKind) -> Result<(), String> {
+    update_player_entity(ctx, |manager, entity_id| {
+        manager.unequip_item(entity_id, slot.into())
+    })?;
+    log::info!("{:?} unequipped {:?}", ctx.sender, slot);
+    Ok(())
+}
+
+#[reducer]
+/// Move a stack to another inventory slot, merging or swapping with what is there
+pub fn move_item(ctx: &ReducerContext, from_slot: u32, to_slot: u32) -> Result<(), String> {
+    update_player_inventory(ctx, |inventory| {
+        inventory.move_stack(from_slot as usize, to_slot as usize)
+    })
+}
+
+#[reducer]
+/// Move part of a stack into an empty inventory slot
+pub fn split_stack(
+    ctx: &ReducerContext,
+    from_slot: u32,
+    to_slot: u32,
+    count: u32,
+) -> Result<(), String> {
+    update_player_inventory(ctx, |inventory| {
+        inventory.split_stack(from_slot as usize, to_slot as usize, count)
+    })
+}
+
+#[reducer]
+/// Clients invoke this reducer to pick up an item lying next to their entity. Whatever does
+/// not fit in the inventory stays on the ground.
+pub fn pick_up_item(ctx: &ReducerContext, item_entity_id: u64) -> Result<(), String> {
+    let (mut manager, entity_id) = load_player_and_target(ctx, item_entity_id)?;
+    if !manager.has::<Pickup>(item_entity_id) {
+        return Err("Cannot pick that up".to_string());
+    }
+    let InteractionResult::ItemPickedUp = manager.interact_entities(entity_id, item_entity_id)?
+    else {
+        return Err("Invalid interaction".to_string());
+    };
+    store_game_entity(ctx, &manager, entity_id);
+
+    if manager.contains(item_entity_id) {
+        store_game_entity(ctx, &manager, item_entity_id);
+    } else {
+        delete_entity(ctx, item_entity_id);
+        let map_id = ctx
+            .db
+            .player()
+            .identity()
+            .find(ctx.sender)
+            .and_then(|player| player.current_map_id);
+        if let Some(mut map) = map_id.and_then(|id| ctx.db.map().id().find(id)) {
+            map.entity_ids.retain(|&id| id != item_entity_id);
+            ctx.db.map().id().update(map);
+        }
+    }
+
+    log::info!("{:?} picked up item {}", ctx.sender, item_entity_id);
+    Ok(())
+}
@@ -1,9 +1,19 @@
 // Module declarations
+pub mod ability;
+pub mod dialogue;
 pub mod entity;
 pub mod init;
+pub mod inventory;
 pub mod message;
 pub mod player;
+pub mod quests;
+pub mod respawn;
+pub mod rotation;
+pub mod secret;
+pub mod shop;
 pub mod tables;
 pub mod tick;
+pub mod travel;
 pub mod types;
 pub mod user;
+pub mod vision;
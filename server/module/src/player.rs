@@ -1,14 +1,33 @@
 use spacetimedb::{reducer, ReducerContext};
 
 use crate::entity::move_entity;
+use crate::quests::reach_objects;
+use crate::secret::reveal_secrets_passively;
 use crate::tables::player;
+use crate::travel::take_transition_at;
+use crate::vision::update_player_vision_in;
 
 #[reducer]
 pub fn move_player(ctx: &ReducerContext, x: f64, y: f64) -> Result<(), String> {
     if let Some(player) = ctx.db.player().identity().find(ctx.sender) {
         // Move the player's entity
         if let Some(entity_id) = player.entity_id {
-            move_entity(ctx, entity_id, x, y)
+            // Walls and other bodies may stop the entity short of the requested position
+            let position = move_entity(ctx, entity_id, player.current_map_id, x, y)?;
+
+            // Walking past a secret passage may reveal it, and objects may be quest goals
+            if let Some(map_id) = player.current_map_id {
+                reveal_secrets_passively(ctx, map_id, entity_id, &position);
+                reach_objects(ctx, map_id, entity_id, &position);
+            }
+
+            // Stepping onto a building entrance moves the player to the linked map
+            let map_id = player.current_map_id;
+            let travelled = take_transition_at(ctx, player, &position)?;
+            if let (false, Some(map_id)) = (travelled, map_id) {
+                update_player_vision_in(ctx, ctx.sender, map_id, &position);
+            }
+            Ok(())
         } else {
             Err("Player has no associated entity".to_string())
         }
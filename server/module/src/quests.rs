@@ -0,0 +1,42 @@
+use crate::entity::{load_game_entity, store_game_entity};
+use crate::tables::map;
+use crate::types::Vec2;
+use game_module::entity::quests::REACH_RANGE;
+use game_module::entity::{EntityManager, QuestEvent};
+use game_module::map_generator::ObjectType;
+use spacetimedb::ReducerContext;
+
+/// Dialogues of the NPCs handing out quests in the starting town
+pub const QUEST_GIVERS: &[&str] = &["elder", "villager"];
+
+/// Count the map objects a player's entity came within reach of after it moved toward their
+/// quests
+pub fn reach_objects(ctx: &ReducerContext, map_id: u64, entity_id: u64, position: &Vec2) {
+    let Some(map) = ctx.db.map().id().find(map_id) else {
+        return;
+    };
+    let reached: Vec<ObjectType> = map
+        .objects
+        .iter()
+        .filter(|object| {
+            let dx = object.x as f64 - position.x;
+            let dy = object.y as f64 - position.y;
+            dx * dx + dy * dy <= REACH_RANGE * REACH_RANGE
+        })
+        .filter_map(|object| ObjectType::from_u8(object.kind))
+        .collect();
+    if reached.is_empty() {
+        return;
+    }
+
+    let mut manager = EntityManager::new();
+    load_game_entity(ctx, &mut manager, entity_id);
+    let mut finished = Vec::new();
+    for object in reached {
+        finished.extend(manager.record_quest_event(entity_id, QuestEvent::Reached(object)));
+    }
+    store_game_entity(ctx, &manager, entity_id);
+    for quest in finished {
+        log::info!("{:?} met every objective of {}", ctx.sender, quest);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::entity::{
+    delete_entity, load_game_entity, store_game_entity, store_spawned_entities, FIRST_SPAWNED_ID,
+};
+use crate::tables::{
+    entity, game_info, map, monster_spawner, player, EntityType, Map, MonsterSpawner,
+};
+use crate::travel::transfer_player;
+use crate::types::Vec2;
+use game_module::entity::prefabs::{self, find_archetype};
+use game_module::entity::EntityManager;
+use game_module::map_generator::MonsterSpot;
+use spacetimedb::{reducer, Identity, ReducerContext, Table};
+
+/// Seconds a killed monster stays away from its post
+pub const MONSTER_RESPAWN_SECONDS: f64 = 90.0;
+
+#[reducer]
+/// Clients invoke this reducer to bring their dead entity back at the starting town's spawn.
+/// The gravestone they left behind is removed.
+pub fn respawn(ctx: &ReducerContext) -> Result<(), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+    let game_info = ctx
+        .db
+        .game_info()
+        .id()
+        .find(1)
+        .ok_or("Game info not initialized")?;
+    let town = ctx
+        .db
+        .map()
+        .id()
+        .find(game_info.starting_town_map_id)
+        .ok_or("Starting town map not found")?;
+    let spawn_position = town
+        .spawn_points
+        .first()
+        .cloned()
+        .unwrap_or(town.spawn_position);
+
+    let mut manager = EntityManager::new();
+    load_game_entity(ctx, &mut manager, entity_id);
+    if !manager.contains(entity_id) {
+        return Err("Entity not found".to_string());
+    }
+    manager.respawn(
+        entity_id,
+        game_module::map::Vec2 {
+            x: spawn_position.x,
+            y: spawn_position.y,
+        },
+    )?;
+    store_game_entity(ctx, &manager, entity_id);
+
+    if let Some(map_id) = player.current_map_id {
+        remove_gravestones(ctx, map_id, ctx.sender);
+    }
+    transfer_player(ctx, player, town.id, spawn_position)?;
+
+    log::info!("{:?} respawned in {}", ctx.sender, town.name);
+    Ok(())
+}
+
+/// Delete the gravestones a player left in a map
+fn remove_gravestones(ctx: &ReducerContext, map_id: u64, identity: Identity) {
+    let Some(mut map) = ctx.db.map().id().find(map_id) else {
+        return;
+    };
+    let gravestone_ids: Vec<u64> = map
+        .entity_ids
+        .iter()
+        .copied()
+        .filter(|&id| {
+            ctx.db.entity().id().find(id).is_some_and(|entity| {
+                entity.entity_type == EntityType::Gravestone
+                    && entity.owner_identity == Some(identity)
+            })
+        })
+        .collect();
+    if gravestone_ids.is_empty() {
+        return;
+    }
+    for &id in &gravestone_ids {
+        delete_entity(ctx, id);
+    }
+    map.entity_ids.retain(|id| !gravestone_ids.contains(id));
+    ctx.db.map().id().update(map);
+}
+
+/// Add a spawner for every monster post of a new map. The monsters appear on the first tick
+/// the map is simulated.
+pub fn place_spawners(ctx: &ReducerContext, map_id: u64, spots: &[MonsterSpot]) {
+    for spot in spots {
+        ctx.db.monster_spawner().insert(MonsterSpawner {
+            id: 0, // auto_inc will handle this
+            map_id,
+            archetype: spot.archetype.to_string(),
+            position: Vec2 {
+                x: spot.position.x as f64,
+                y: spot.position.y as f64,
+            },
+            respawn_delay: MONSTER_RESPAWN_SECONDS,
+            entity_id: None,
+            respawn_at: 0.0,
+        });
+    }
+}
+
+/// Start the timers of spawners whose monster is gone and bring back the monsters whose
+/// timer ran out. Returns the new entity IDs; the caller saves the map row.
+pub fn respawn_monsters(ctx: &ReducerContext, map: &mut Map, now: f64) -> Vec<u64> {
+    let spawners: Vec<MonsterSpawner> = ctx
+        .db
+        .monster_spawner()
+        .iter()
+        .filter(|spawner| spawner.map_id == map.id)
+        .collect();
+    let mut spawned_ids = Vec::new();
+    for spawner in spawners {
+        match spawner.entity_id {
+            Some(entity_id) if ctx.db.entity().id().find(entity_id).is_some() => {}
+            Some(_) => {
+                let respawn_at = now + spawner.respawn_delay;
+                ctx.db.monster_spawner().id().update(MonsterSpawner {
+                    entity_id: None,
+                    respawn_at,
+                    ..spawner
+                });
+            }
+            None if spawner.respawn_at <= now => {
+                let Some(archetype) = find_archetype(&spawner.archetype) else {
+                    continue;
+                };
+                let mut manager = EntityManager::new();
+                manager.set_next_id(FIRST_SPAWNED_ID);
+                manager.spawn(prefabs::archetype(
+                    archetype,
+                    game_module::map::Vec2 {
+                        x: spawner.position.x,
+                        y: spawner.position.y,
+                    },
+                ));
+                let Some(&entity_id) = store_spawned_entities(ctx, &mut manager, map).first()
+                else {
+                    continue;
+                };
+                ctx.db.monster_spawner().id().update(MonsterSpawner {
+                    entity_id: Some(entity_id),
+                    ..spawner
+                });
+                spawned_ids.push(entity_id);
+            }
+            None => {}
+        }
+    }
+    spawned_ids
+}
+
+/// Delete the spawners of a map
+pub fn delete_spawners(ctx: &ReducerContext, map_id: u64) {
+    let spawner_ids: Vec<u64> = ctx
+        .db
+        .monster_spawner()
+        .iter()
+        .filter(|spawner| spawner.map_id == map_id)
+        .map(|spawner| spawner.id)
+        .collect();
+    for id in spawner_ids {
+        ctx.db.monster_spawner().id().delete(id);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::entity::delete_entity;
+use crate::init::store_dungeon;
+use crate::respawn::delete_spawners;
+use crate::tables::{
+    dungeon_rotation, entity, game_info, map, map_transition, player, secret_area, secret_passage,
+    world_link, DungeonRotation, EntityType, Map, MapType, RotationKind,
+};
+use game_module::map_generator::{self, GenerationParams};
+use game_module::rotation::{Rotation, RotationPeriod};
+use spacetimedb::{reducer, table, ReducerContext, ScheduleAt, Table, TimeDuration};
+
+#[table(name = rotation_schedule, scheduled(refresh_rotations))]
+pub struct RotationSchedule {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub scheduled_at: ScheduleAt,
+}
+
+// New days start at midnight UTC, so checking once a minute is plenty
+const ROTATION_CHECK_INTERVAL_MICROS: i64 = 60_000_000;
+
+const ROTATION_KINDS: [RotationKind; 2] = [RotationKind::Daily, RotationKind::Weekly];
+
+fn period_of(kind: RotationKind) -> RotationPeriod {
+    match kind {
+        RotationKind::Daily => RotationPeriod::Daily,
+        RotationKind::Weekly => RotationPeriod::Weekly,
+    }
+}
+
+fn unix_seconds(ctx: &ReducerContext) -> u64 {
+    ctx.timestamp.to_micros_since_unix_epoch().max(0) as u64 / 1_000_000
+}
+
+#[reducer]
+pub fn refresh_rotations(ctx: &ReducerContext, _schedule: RotationSchedule) -> Result<(), String> {
+    // Only allow the module to call this reducer (security check)
+    if ctx.sender != ctx.identity() {
+        return Err("Rotation reducer can only be called by the module itself".to_string());
+    }
+
+    update_rotations(ctx)
+}
+
+/// Start the rotation schedule and generate the current rotations (called from init reducer)
+pub fn initialize_rotation_system(ctx: &ReducerContext) -> Result<(), String> {
+    let interval = TimeDuration::from_micros(ROTATION_CHECK_INTERVAL_MICROS);
+    ctx.db.rotation_schedule().insert(RotationSchedule {
+        id: 0,                         // auto_inc will assign this
+        scheduled_at: interval.into(), // Convert to ScheduleAt::Interval
+    });
+
+    update_rotations(ctx)
+}
+
+/// Generate any rotation that has started since the last check and retire empty old ones
+fn update_rotations(ctx: &ReducerContext) -> Result<(), String> {
+    let now = unix_seconds(ctx);
+    for kind in ROTATION_KINDS {
+        let rotation = Rotation::at(period_of(kind), now);
+        let exists = ctx
+            .db
+            .dungeon_rotation()
+            .iter()
+            .any(|r| r.kind == kind && r.period_index == rotation.index);
+        if !exists {
+            start_rotation(ctx, kind, rotation)?;
+        }
+    }
+
+    retire_empty_rotations(ctx, now);
+    Ok(())
+}
+
+/// Generate the dungeon for a rotation and connect it back to the starting town
+fn start_rotation(
+    ctx: &ReducerContext,
+    kind: RotationKind,
+    rotation: Rotation,
+) -> Result<(), String> {
+    let params = GenerationParams {
+        dungeon: rotation.params,
+        ..Default::default()
+    };
+    let dungeon_result = map_generator::Generator::generate_map(
+        map_generator::MapType::Dungeon,
+        rotation.name,
+        rotation.seed,
+        params,
+    )?;
+    let map_id = store_dungeon(ctx, &dungeon_result);
+
+    // The dungeon's portal leads back to town; players enter with enter_rotation_dungeon
+    if let Some(game_info) = ctx.db.game_info().id().find(1) {
+        crate::travel::link_maps(ctx, map_id, game_info.starting_town_map_id)?;
+    }
+
+    let modifiers: Vec<String> = rotation
+        .modifiers
+        .iter()
+        .map(|m| m.name().to_string())
+        .collect();
+    log::info!(
+        "Started {:?} rotation #{} as map {} (seed: {}, modifiers: {})",
+        kind,
+        rotation.index,
+        map_id,
+        rotation.seed,
+        modifiers.join(", ")
+    );
+
+    ctx.db.dungeon_rotation().insert(DungeonRotation {
+        id: 0, // auto_inc will handle this
+        map_id,
+        kind,
+        period_index: rotation.index,
+        seed: rotation.seed,
+        modifiers,
+        started_at: ctx.timestamp,
+    });
+    Ok(())
+}
+
+/// Delete past rotations, and everything attached to their maps, once nobody is inside
+fn retire_empty_rotations(ctx: &ReducerContext, now: u64) {
+    let retired: Vec<DungeonRotation> = ctx
+        .db
+        .dungeon_rotation()
+        .iter()
+        .filter(|r| {
+            let players = ctx
+                .db
+                .map()
+                .id()
+                .find(r.map_id)
+                .map_or(0, |m| players_inside(ctx, &m));
+            period_of(r.kind).can_retire(r.period_index, now, players)
+        })
+        .collect();
+
+    for rotation in retired {
+        let map_id = rotation.map_id;
+        delete_map(ctx, map_id);
+        ctx.db.dungeon_rotation().id().delete(rotation.id);
+
+        log::info!(
+            "Retired {:?} rotation #{} (map {})",
+            rotation.kind,
+            rotation.period_index,
+            map_id
+        );
+    }
+}
+
+/// How many player entities are in a map. Monsters and loot never leave on their own, so they
+/// do not keep a rotation around.
+fn players_inside(ctx: &ReducerContext, map: &Map) -> usize {
+    map.entity_ids
+        .iter()
+        .filter_map(|&id| ctx.db.entity().id().find(id))
+        .filter(|entity| entity.entity_type == EntityType::Player)
+        .count()
+}
+
+/// Delete a map together with its secret passages and areas, spawners, transitions, world
+/// links and the entities left in it
+fn delete_map(ctx: &ReducerContext, map_id: u64) {
+    let passages: Vec<u64> = ctx
+        .db
+        .secret_passage()
+        .iter()
+        .filter(|s| s.map_id == map_id)
+        .map(|s| s.id)
+        .collect();
+    for id in passages {
+        ctx.db.secret_passage().id().delete(id);
+    }
+    let areas: Vec<u64> = ctx
+        .db
+        .secret_area()
+        .iter()
+        .filter(|a| a.map_id == map_id)
+        .map(|a| a.id)
+        .collect();
+    for id in areas {
+        ctx.db.secret_area().id().delete(id);
+    }
+    delete_spawners(ctx, map_id);
+
+    let links: Vec<u64> = ctx
+        .db
+        .world_link()
+        .iter()
+        .filter(|l| l.from_map_id == map_id || l.to_map_id == map_id)
+        .map(|l| l.id)
+        .collect();
+    for id in links {
+        ctx.db.world_link().id().delete(id);
+    }
+
+    let transitions: Vec<u64> = ctx
+        .db
+        .map_transition()
+        .iter()
+        .filter(|t| t.map_id == map_id || t.destination_map_id == map_id)
+        .map(|t| t.id)
+        .collect();
+    for id in transitions {
+        ctx.db.map_transition().id().delete(id);
+    }
+
+    // Only rotations without players are retired, so the monsters and loot left in the map
+    // go with it
+    if let Some(map) = ctx.db.map().id().find(map_id) {
+        for &entity_id in &map.entity_ids {
+            delete_entity(ctx, entity_id);
+        }
+    }
+    ctx.db.map().id().delete(map_id);
+}
+
+#[reducer]
+/// Clients invoke this reducer to enter the current daily or weekly dungeon from a town.
+pub fn enter_rotation_dungeon(ctx: &ReducerContext, kind: RotationKind) -> Result<(), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+
+    let current_map = player
+        .current_map_id
+        .and_then(|id| ctx.db.map().id().find(id))
+        .ok_or("Player is not in a map")?;
+    if current_map.map_type != MapType::Town {
+        return Err("Rotating dungeons can only be entered from a town".to_string());
+    }
+
+    let rotation = ctx
+        .db
+        .dungeon_rotation()
+        .iter()
+        .filter(|r| r.kind == kind)
+        .max_by_key(|r| r.period_index)
+        .ok_or("No rotation is running")?;
+    let dungeon = ctx
+        .db
+        .map()
+        .id()
+        .find(rotation.map_id)
+        .ok_or("Rotation dungeon not found")?;
+
+    crate::travel::transfer_player(ctx, player, dungeon.id, dungeon.spawn_position)?;
+
+    log::info!(
+        "{:?} entered {:?} rotation #{}",
+        ctx.sender,
+        kind,
+        rotation.period_index
+    );
+    Ok(())
+}
@@ -0,0 +1,257 @@
+use crate::entity::{entity_perception, store_spawned_entities, FIRST_SPAWNED_ID};
+use crate::init::to_map_props;
+use crate::tables::{entity, map, player, secret_area, secret_passage, SecretArea, SecretPassage};
+use crate::types::{HiddenItem, MapProp, Vec2};
+use game_module::entity::items::find_item;
+use game_module::entity::{prefabs, EntityManager, ItemStack};
+use game_module::map_generator::{HiddenArea, MapGenerationResult, Position, TileType};
+use game_module::secrets;
+use spacetimedb::{reducer, ReducerContext, Table};
+
+/// Hide the secret doors of a generated map, and the floor behind them, so only walls get
+/// published
+pub fn disguise_tiles(tiles: &[u8], width: usize, hidden_areas: &[HiddenArea]) -> Vec<u8> {
+    let mut disguised: Vec<u8> = tiles
+        .iter()
+        .map(|&tile| TileType::from(tile).disguised() as u8)
+        .collect();
+    for tile in hidden_areas.iter().flat_map(|area| &area.tiles) {
+        if let Some(published) = disguised.get_mut(tile.y * width + tile.x) {
+            *published = TileType::Wall as u8;
+        }
+    }
+    disguised
+}
+
+/// Store the real location of every secret passage of a map, and what lies behind them, in
+/// the private tables
+pub fn store_secret_passages(ctx: &ReducerContext, map_id: u64, dungeon: &MapGenerationResult) {
+    let passages = &dungeon.secret_passages;
+    let mut passage_ids = Vec::new();
+    for passage in passages {
+        let passage = ctx.db.secret_passage().insert(SecretPassage {
+            id: 0, // auto_inc will handle this
+            map_id,
+            x: passage.x as u64,
+            y: passage.y as u64,
+            discovered: false,
+            discovered_by: None,
+            discovered_at: None,
+        });
+        passage_ids.push(passage.id);
+    }
+
+    for area in &dungeon.hidden_areas {
+        ctx.db.secret_area().insert(SecretArea {
+            id: 0, // auto_inc will handle this
+            map_id,
+            passage_ids: passages
+                .iter()
+                .zip(&passage_ids)
+                .filter(|(passage, _)| area.passages.contains(passage))
+                .map(|(_, &id)| id)
+                .collect(),
+            tiles: area
+                .tiles
+                .iter()
+                .map(|tile| MapProp {
+                    x: tile.x as u16,
+                    y: tile.y as u16,
+                    kind: dungeon.tiles[tile.y * dungeon.width + tile.x],
+                })
+                .collect(),
+            decorations: to_map_props(
+                dungeon
+                    .layers
+                    .decorations
+                    .iter()
+                    .filter(|prop| area.contains(&prop.position)),
+            ),
+            objects: to_map_props(
+                dungeon
+                    .layers
+                    .objects
+                    .iter()
+                    .filter(|prop| area.contains(&prop.position)),
+            ),
+            loot: Vec::new(),
+        });
+    }
+}
+
+/// Keep loot rolled for a tile behind an undiscovered secret passage in the private area
+/// instead of the map. Returns false if the tile is not hidden.
+pub fn hide_loot(
+    ctx: &ReducerContext,
+    map_id: u64,
+    position: &Position,
+    stacks: &[ItemStack],
+) -> bool {
+    let (x, y) = (position.x as u16, position.y as u16);
+    let Some(mut area) = ctx
+        .db
+        .secret_area()
+        .iter()
+        .filter(|area| area.map_id == map_id)
+        .find(|area| area.tiles.iter().any(|tile| tile.x == x && tile.y == y))
+    else {
+        return false;
+    };
+    area.loot.extend(stacks.iter().map(|stack| HiddenItem {
+        x,
+        y,
+        item_id: stack.item.to_string(),
+        count: stack.count,
+    }));
+    ctx.db.secret_area().id().update(area);
+    true
+}
+
+/// Reveal undiscovered secret passages within `radius` of a position.
+/// Returns the number of passages that were revealed.
+pub fn reveal_secrets_near(
+    ctx: &ReducerContext,
+    map_id: u64,
+    position: &Vec2,
+    radius: f64,
+) -> usize {
+    let position = game_module::map::Vec2 {
+        x: position.x,
+        y: position.y,
+    };
+
+    let found: Vec<SecretPassage> = ctx
+        .db
+        .secret_passage()
+        .iter()
+        .filter(|s| s.map_id == map_id && !s.discovered)
+        .filter(|s| {
+            let tile = Position {
+                x: s.x as usize,
+                y: s.y as usize,
+            };
+            secrets::is_within_radius(&tile, &position, radius)
+        })
+        .collect();
+
+    if found.is_empty() {
+        return 0;
+    }
+
+    let Some(mut map) = ctx.db.map().id().find(map_id) else {
+        return 0;
+    };
+
+    let revealed = found.len();
+    let found_ids: Vec<u64> = found.iter().map(|passage| passage.id).collect();
+    for passage in found {
+        // Publish the real tile: the passage becomes a regular door
+        let index = (passage.y * map.width + passage.x) as usize;
+        if let Some(tile) = map.tiles.get_mut(index) {
+            *tile = TileType::Door as u8;
+        }
+
+        ctx.db.secret_passage().id().update(SecretPassage {
+            discovered: true,
+            discovered_by: Some(ctx.sender),
+            discovered_at: Some(ctx.timestamp),
+            ..passage
+        });
+    }
+
+    // Publish the floor behind the passages and what is on it, now that there is a way in
+    let mut loot = EntityManager::new();
+    loot.set_next_id(FIRST_SPAWNED_ID);
+    let areas: Vec<SecretArea> = ctx
+        .db
+        .secret_area()
+        .iter()
+        .filter(|area| area.map_id == map_id)
+        .filter(|area| area.passage_ids.iter().any(|id| found_ids.contains(id)))
+        .collect();
+    for area in areas {
+        for tile in &area.tiles {
+            let index = tile.y as usize * map.width as usize + tile.x as usize;
+            if let Some(published) = map.tiles.get_mut(index) {
+                *published = tile.kind;
+            }
+        }
+        map.decorations.extend(area.decorations.iter().copied());
+        map.objects.extend(area.objects.iter().copied());
+        for item in &area.loot {
+            let Some(definition) = find_item(&item.item_id) else {
+                continue;
+            };
+            let position = game_module::map::Vec2 {
+                x: item.x as f64,
+                y: item.y as f64,
+            };
+            loot.spawn(prefabs::item(
+                position,
+                ItemStack {
+                    item: definition.id,
+                    count: item.count,
+                },
+            ));
+        }
+        ctx.db.secret_area().id().delete(area.id);
+    }
+    store_spawned_entities(ctx, &mut loot, &mut map);
+
+    ctx.db.map().id().update(map);
+    revealed
+}
+
+/// Passively reveal secret passages near a player's entity after it moved
+pub fn reveal_secrets_passively(
+    ctx: &ReducerContext,
+    map_id: u64,
+    entity_id: u64,
+    position: &Vec2,
+) {
+    let radius = secrets::passive_detection_radius(entity_perception(ctx, entity_id));
+    let revealed = reveal_secrets_near(ctx, map_id, position, radius);
+    if revealed > 0 {
+        log::info!(
+            "{:?} noticed {} secret passage(s) in map {}",
+            ctx.sender,
+            revealed,
+            map_id
+        );
+    }
+}
+
+#[reducer]
+/// Clients invoke this reducer to search their surroundings for secret passages.
+pub fn search_for_secrets(ctx: &ReducerContext) -> Result<(), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+
+    let entity_id = player
+        .entity_id
+        .ok_or("Player has no associated entity")?;
+    let map_id = player.current_map_id.ok_or("Player is not in a map")?;
+
+    let entity = ctx
+        .db
+        .entity()
+        .id()
+        .find(entity_id)
+        .ok_or("Entity not found")?;
+
+    let radius = secrets::search_radius(entity_perception(ctx, entity_id));
+    let revealed = reveal_secrets_near(ctx, map_id, &entity.position, radius);
+
+    log::info!(
+        "{:?} searched map {} and found {} secret passage(s)",
+        ctx.sender,
+        map_id,
+        revealed
+    );
+
+    Ok(())
+}
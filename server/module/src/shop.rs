@@ -0,0 +1,47 @@
+use crate::dialogue::load_player_and_target;
+use crate::entity::store_game_entity;
+use spacetimedb::{reducer, ReducerContext};
+
+/// Dialogue and shop of the vendors in the starting town
+pub const VENDORS: &[(&str, &str)] = &[("merchant", "general_store")];
+
+#[reducer]
+/// Clients invoke this reducer to buy wares from a vendor next to their entity, once the
+/// vendor showed them in a conversation
+pub fn buy_item(
+    ctx: &ReducerContext,
+    vendor_entity_id: u64,
+    item_id: String,
+    count: u32,
+) -> Result<(), String> {
+    let (mut manager, entity_id) = load_player_and_target(ctx, vendor_entity_id)?;
+    let price = manager.buy_item(entity_id, vendor_entity_id, &item_id, count)?;
+    store_game_entity(ctx, &manager, entity_id);
+    store_game_entity(ctx, &manager, vendor_entity_id);
+
+    log::info!(
+        "{:?} bought {} {} for {} gold",
+        ctx.sender,
+        count,
+        item_id,
+        price
+    );
+    Ok(())
+}
+
+#[reducer]
+/// Clients invoke this reducer to sell items from an inventory slot to a vendor next to their
+/// entity, once the vendor showed its wares in a conversation
+pub fn sell_item(
+    ctx: &ReducerContext,
+    vendor_entity_id: u64,
+    inventory_slot: u32,
+    count: u32,
+) -> Result<(), String> {
+    let (mut manager, entity_id) = load_player_and_target(ctx, vendor_entity_id)?;
+    let gold = manager.sell_item(entity_id, vendor_entity_id, inventory_slot as usize, count)?;
+    store_game_entity(ctx, &manager, entity_id);
+
+    log::info!("{:?} sold {} items for {} gold", ctx.sender, count, gold);
+    Ok(())
+}
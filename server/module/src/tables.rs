@@ -1,4 +1,4 @@
-use crate::types::Vec2;
+use crate::types::{HiddenItem, MapProp, Vec2};
 use spacetimedb::{table, Identity, Timestamp};
 
 #[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq, Eq)]
@@ -8,6 +8,8 @@ pub enum EntityType {
     Monster,
     Summoned,
     Item,
+    Projectile,
+    Gravestone,
 }
 
 #[table(name = entity, public)]
@@ -18,10 +20,376 @@ pub struct Entity {
     pub entity_type: EntityType,
     pub position: Vec2,
     pub direction: f64, // Direction in radians (0 = east, π/2 = north, π = west, 3π/2 = south)
-    pub owner_identity: Option<Identity>, // For player entities, this is the player's identity
+    pub owner_identity: Option<Identity>, // The player, for player entities and their gravestones
     pub created_at: Timestamp,
 }
 
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityStateKind {
+    Idle,
+    Moving,
+    Attacking,
+    Dead,
+    Interacting,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageTypeKind {
+    Physical,
+    Fire,
+    Ice,
+    Poison,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitOutcomeKind {
+    Miss,
+    Hit,
+    Critical,
+}
+
+// Combat stats of entities that can fight; entities without a row cannot be attacked
+#[table(name = entity_stats, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityStats {
+    #[primary_key]
+    pub entity_id: u64,
+    pub health: u32,
+    pub max_health: u32,
+    pub attack_damage: u32,
+    pub damage_type: DamageTypeKind,
+    pub accuracy: f64,
+    pub crit_chance: f64,
+    pub crit_multiplier: f64,
+    pub evasion: f64,
+    pub physical_resistance: i32,
+    pub fire_resistance: i32,
+    pub ice_resistance: i32,
+    pub poison_resistance: i32,
+    pub attack_range: f64,
+    pub attack_cooldown: f64,
+    pub last_attack_time: f64, // Seconds since the Unix epoch
+    pub perception: f64,
+}
+
+// Behaviour state of an entity, one row per entity
+#[table(name = entity_state, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityState {
+    #[primary_key]
+    pub entity_id: u64,
+    pub state: EntityStateKind,
+    pub target_entity_id: Option<u64>,
+    pub base_move_speed: f64,
+    pub move_speed: f64, // After equipment, slows, hastes, stuns and roots
+}
+
+// Attacks resolved in the last few seconds, so clients can show hits, misses and damage
+#[table(name = combat_event, public)]
+pub struct CombatEvent {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64,
+    pub attacker_entity_id: u64,
+    pub target_entity_id: u64,
+    pub outcome: HitOutcomeKind,
+    pub damage_type: DamageTypeKind,
+    pub raw_damage: u32,
+    pub mitigated_damage: i64, // Negative when a weakness added damage
+    pub damage_dealt: u32,
+    pub target_died: bool,
+    pub occurred_at: Timestamp,
+}
+
+// Level ups in the last few seconds, so clients can celebrate them
+#[table(name = level_up_event, public)]
+pub struct LevelUpEvent {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64,
+    pub entity_id: u64,
+    pub level: u32,
+    pub occurred_at: Timestamp,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AiModeKind {
+    Idle,
+    Patrol,
+    Chase,
+    Attack,
+    Flee,
+    Return,
+}
+
+// What an AI-driven entity is up to, one row per entity with a behaviour
+#[table(name = entity_ai, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityAi {
+    #[primary_key]
+    pub entity_id: u64,
+    pub behavior: String, // Name of a behaviour in `game_module::entity::behavior`
+    pub mode: AiModeKind,
+    pub home: Vec2,
+    pub patrol_route: Vec<Vec2>,
+    pub patrol_index: u32,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusEffectKind {
+    Poison,
+    Burn,
+    Bleed,
+    Stun,
+    Slow,
+    Root,
+    Regeneration,
+    Haste,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct ActiveEffect {
+    pub kind: StatusEffectKind,
+    pub source_entity_id: Option<u64>, // Entity credited with the effect's damage
+    pub magnitude: f64,
+    pub stacks: u32,
+    pub expires_at: f64,   // Seconds since the Unix epoch
+    pub next_tick_at: f64, // Seconds since the Unix epoch
+}
+
+// Status effects on an entity, one row per entity with at least one active effect
+#[table(name = entity_effects, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityEffects {
+    #[primary_key]
+    pub entity_id: u64,
+    pub effects: Vec<ActiveEffect>,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct AbilityCooldown {
+    pub ability: String,
+    pub ready_at: f64, // Seconds since the Unix epoch
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct AbilityCast {
+    pub ability: String,
+    pub target_entity_id: Option<u64>, // Neither target set: cast on the caster
+    pub target_position: Option<Vec2>,
+    pub completes_at: f64, // Seconds since the Unix epoch
+}
+
+// Abilities an entity knows, what it has left to pay for them and what it is casting
+#[table(name = entity_abilities, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityAbilities {
+    #[primary_key]
+    pub entity_id: u64,
+    pub known: Vec<String>, // Names of abilities in `game_module::entity::abilities`
+    pub cooldowns: Vec<AbilityCooldown>,
+    pub casting: Option<AbilityCast>,
+    pub mana: f64,
+    pub max_mana: f64,
+    pub energy: f64,
+    pub max_energy: f64,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct InventoryStack {
+    pub slot: u32,
+    pub item_id: String, // ID of an item in `game_module::entity::items`
+    pub count: u32,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EquipSlotKind {
+    Weapon,
+    Armor,
+    Trinket1,
+    Trinket2,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct EquippedItem {
+    pub slot: EquipSlotKind,
+    pub item_id: String,
+}
+
+// Carried and worn items, one row per entity with an inventory
+#[table(name = entity_inventory, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityInventory {
+    #[primary_key]
+    pub entity_id: u64,
+    pub slot_count: u32,
+    pub stacks: Vec<InventoryStack>, // Only the filled slots
+    pub equipment: Vec<EquippedItem>,
+}
+
+// The stack an item entity on the ground stands for
+#[table(name = entity_item, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityItem {
+    #[primary_key]
+    pub entity_id: u64,
+    pub item_id: String,
+    pub count: u32,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FactionKind {
+    Players,
+    Monsters,
+    Neutral,
+}
+
+// Flight of a projectile entity, removed together with the entity when it stops
+#[table(name = entity_projectile, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityProjectile {
+    #[primary_key]
+    pub entity_id: u64,
+    pub ability: String, // Name of the ability in `game_module::entity::abilities` it carries
+    pub owner_entity_id: Option<u64>,
+    pub faction: FactionKind,
+    pub velocity: Vec2, // Tiles per second
+    pub radius: f64,
+    pub pierce: u32,
+    pub hit_entity_ids: Vec<u64>,
+    pub expires_at: f64, // Seconds since the Unix epoch
+}
+
+// A player entity that died and waits for `respawn`, one row per dead player
+#[table(name = entity_death, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityDeath {
+    #[primary_key]
+    pub entity_id: u64,
+    pub died_at: f64, // Seconds since the Unix epoch
+}
+
+// The archetype a monster entity was spawned from
+#[table(name = entity_archetype, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityArchetype {
+    #[primary_key]
+    pub entity_id: u64,
+    pub archetype: String, // Name of an archetype in `game_module::entity::prefabs`
+}
+
+// Wares a vendor entity has left and when they are stocked up next
+#[table(name = entity_vendor, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityVendor {
+    #[primary_key]
+    pub entity_id: u64,
+    pub shop: String,    // Name of a shop in `game_module::entity::shops`
+    pub stock: Vec<u32>, // Left of each of the shop's wares, in the shop's order
+    pub restock_at: f64, // Seconds since the Unix epoch
+}
+
+// The dialogue an NPC entity talks with
+#[table(name = entity_dialogue, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityDialogue {
+    #[primary_key]
+    pub entity_id: u64,
+    pub dialogue: String, // Name of a dialogue in `game_module::entity::dialogue`
+}
+
+// A post in a map that a monster guards and comes back to some time after it was killed
+#[table(name = monster_spawner)]
+pub struct MonsterSpawner {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64,
+    pub archetype: String, // Name of an archetype in `game_module::entity::prefabs`
+    pub position: Vec2,
+    pub respawn_delay: f64, // Seconds from the monster's death to its return
+    pub entity_id: Option<u64>, // The monster while it lives
+    pub respawn_at: f64,    // Seconds since the Unix epoch; only used without a monster
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct DamageContribution {
+    pub attacker_entity_id: u64,
+    pub damage: u32,
+}
+
+// Damage a monster has taken from each attacker, to split its experience when it dies
+#[table(name = entity_damage_taken)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityDamageTaken {
+    #[primary_key]
+    pub entity_id: u64,
+    pub contributions: Vec<DamageContribution>,
+}
+
+// Level and experience of a player, kept by identity so they outlive the player's entity
+#[table(name = player_progress, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerProgress {
+    #[primary_key]
+    pub identity: Identity,
+    pub level: u32,
+    pub xp: u64, // Total gathered
+}
+
+// Gold a player carries, kept by identity like their progress
+#[table(name = player_wallet, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerWallet {
+    #[primary_key]
+    pub identity: Identity,
+    pub gold: u64,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuestStatusKind {
+    Active,
+    Completed,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct QuestEntry {
+    pub quest_id: String, // ID of a quest in `game_module::entity::quests`
+    pub status: QuestStatusKind,
+    pub progress: Vec<u32>, // Progress on each objective of the quest
+}
+
+// Quests a player took on, kept by identity like their progress
+#[table(name = player_quests, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerQuests {
+    #[primary_key]
+    pub identity: Identity,
+    pub quests: Vec<QuestEntry>,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Debug, PartialEq)]
+pub struct DialogueChoice {
+    pub option: u32, // Index to pass to `choose_dialogue_option`
+    pub text: String,
+}
+
+// The conversation a player is having with an NPC, removed when it ends
+#[table(name = player_conversation, public)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerConversation {
+    #[primary_key]
+    pub identity: Identity,
+    pub npc_entity_id: u64,
+    pub dialogue: String,
+    pub node: String,
+    pub text: String,                 // What the NPC says
+    pub choices: Vec<DialogueChoice>, // Only the options the player may pick
+    pub shop_open: bool,
+}
+
 #[table(name = player, public)]
 pub struct Player {
     #[primary_key]
@@ -31,6 +399,16 @@ pub struct Player {
     pub current_map_id: Option<u64>, // The map the player is currently in
 }
 
+// Tiles each online player currently sees, for fog of war
+#[table(name = player_vision, public)]
+pub struct PlayerVision {
+    #[primary_key]
+    pub identity: Identity,
+    pub map_id: u64,
+    pub visible: Vec<u32>, // Tile indices (y * width + x), sorted
+    pub updated_at: Timestamp,
+}
+
 #[table(name = player_offline, public)]
 pub struct PlayerOffline {
     #[primary_key]
@@ -73,6 +451,7 @@ pub enum MapType {
     Town,
     Wilderness,
     Instance,
+    Interior, // Inside a town building
 }
 
 #[table(name = map, public)]
@@ -84,10 +463,86 @@ pub struct Map {
     pub map_type: MapType,
     pub width: u64,
     pub height: u64,
-    pub tiles: Vec<u8>, // Flattened 2D array: tiles[y * width + x] = tile_type (0=Wall, 1=Floor, 2=Door)
+    pub tiles: Vec<u8>, // Collision layer, flattened 2D array: tiles[y * width + x] = tile_type (0=Wall, 1=Floor, 2=Door), secrets are published as walls
+    pub terrain: Vec<u8>, // Terrain layer, same layout as tiles (0=Stone, 1=Dirt, 2=Grass, 3=Cobblestone, 4=Wood)
+    pub decorations: Vec<MapProp>, // Sparse decoration layer (torches, rubble, rugs, ...)
+    pub objects: Vec<MapProp>, // Sparse interactable object layer (chests, levers, ...)
     pub spawn_position: Vec2, // Primary spawn position
     pub spawn_points: Vec<Vec2>, // All possible spawn points
     pub is_starting_town: bool, // Whether this is the main starting town (only relevant for towns)
     pub entity_ids: Vec<u64>, // List of entity IDs in this map
     pub created_at: Timestamp,
 }
+
+// Private: clients only learn about a secret passage once its tile is revealed in `map`
+#[table(name = secret_passage)]
+pub struct SecretPassage {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64,
+    pub x: u64,
+    pub y: u64,
+    pub discovered: bool,
+    pub discovered_by: Option<Identity>,
+    pub discovered_at: Option<Timestamp>,
+}
+
+// Private: floor only secret passages lead to, published as walls in `map` (and without its
+// props) until one of its passages is discovered
+#[table(name = secret_area)]
+pub struct SecretArea {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64,
+    pub passage_ids: Vec<u64>, // Discovering any of these reveals the area
+    pub tiles: Vec<MapProp>,   // Real tiles of the area
+    pub decorations: Vec<MapProp>, // Props in the area, left out of the map's layers until then
+    pub objects: Vec<MapProp>,
+    pub loot: Vec<HiddenItem>, // Chest loot in the area, spawned as item entities once revealed
+}
+
+#[table(name = map_transition, public)]
+pub struct MapTransition {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64, // The map containing the transition tile
+    pub x: u64,
+    pub y: u64,
+    pub destination_map_id: u64,
+    pub arrival_position: Vec2, // Where travellers appear in the destination map
+}
+
+// Edges of the world graph: a portal in one map leads to the spawn of another
+#[table(name = world_link, public)]
+pub struct WorldLink {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub from_map_id: u64,
+    pub to_map_id: u64,
+    pub portal_x: u64, // Portal tile in the source map
+    pub portal_y: u64,
+}
+
+#[derive(spacetimedb::SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationKind {
+    Daily,
+    Weekly,
+}
+
+// A dungeon that is regenerated from a date-derived seed every day or week
+#[table(name = dungeon_rotation, public)]
+pub struct DungeonRotation {
+    #[primary_key]
+    #[auto_inc]
+    pub id: u64,
+    pub map_id: u64,
+    pub kind: RotationKind,
+    pub period_index: u64, // Days or weeks since the Unix epoch
+    pub seed: u64,
+    pub modifiers: Vec<String>,
+    pub started_at: Timestamp,
+}
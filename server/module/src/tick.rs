@@ -1,4 +1,17 @@
+use crate::entity::{
+    delete_entity, load_game_entity, store_game_entity, store_spawned_entities, FIRST_SPAWNED_ID,
+};
+use crate::init::to_game_map;
+use crate::respawn::respawn_monsters;
+use crate::tables::{
+    combat_event, level_up_event, map, player, CombatEvent, LevelUpEvent, MapType,
+};
+use game_module::entity::{self as logic, EntityManager, QuestEvent, Transform};
+use game_module::pathfinding::{PathCache, PathParams};
 use spacetimedb::{reducer, table, ReducerContext, ScheduleAt, Table, TimeDuration, Timestamp};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::TAU;
 
 #[table(name = game_tick, scheduled(tick))]
 pub struct GameTick {
@@ -12,6 +25,15 @@ pub struct GameTick {
 // Tick rate: 20 times per second = 50ms interval
 const TICK_INTERVAL_MICROS: i64 = 50_000; // 50ms in microseconds
 
+// How long attacks and level ups stay in their event tables for clients to pick up
+const COMBAT_EVENT_LIFETIME_MICROS: i64 = 5_000_000;
+
+thread_local! {
+    // Monster paths per map, kept between ticks. This is only a cache: if the module is
+    // reloaded the paths are simply computed again.
+    static PATH_CACHES: RefCell<HashMap<u64, PathCache>> = RefCell::new(HashMap::new());
+}
+
 #[reducer]
 pub fn tick(ctx: &ReducerContext, schedule: GameTick) -> Result<(), String> {
     // Only allow the module to call this reducer (security check)
@@ -20,11 +42,12 @@ pub fn tick(ctx: &ReducerContext, schedule: GameTick) -> Result<(), String> {
     }
 
     let current_time = ctx.timestamp;
-    //let last_tick_time = schedule.last_tick_time;
+    let last_tick_time = schedule.last_tick_time;
 
     // Calculate deltaTime in seconds
-    // let delta_time_micros =
-    //     current_time.to_micros_since_unix_epoch() - last_tick_time.to_micros_since_unix_epoch();
+    let delta_time_micros =
+        current_time.to_micros_since_unix_epoch() - last_tick_time.to_micros_since_unix_epoch();
+    let delta_time = delta_time_micros.max(0) as f64 / 1_000_000.0;
 
     // Update the tick schedule with the new time
     let updated_schedule = GameTick {
@@ -35,17 +58,178 @@ pub fn tick(ctx: &ReducerContext, schedule: GameTick) -> Result<(), String> {
 
     ctx.db.game_tick().id().update(updated_schedule);
 
-    // Here you can add your game logic that should run every tick
-    // For example:
-    // - Update entity positions
-    // - Process physics
-    // - Handle AI
-    // - Check for collisions
-    // etc.
+    let now = current_time.to_micros_since_unix_epoch() as f64 / 1_000_000.0;
+    for map_id in active_map_ids(ctx) {
+        simulate_map(ctx, map_id, delta_time, now);
+    }
+    prune_events(ctx);
 
     Ok(())
 }
 
+/// Forget attacks and level ups that clients have had time to show
+fn prune_events(ctx: &ReducerContext) {
+    let cutoff = ctx.timestamp.to_micros_since_unix_epoch() - COMBAT_EVENT_LIFETIME_MICROS;
+    let expired: Vec<u64> = ctx
+        .db
+        .combat_event()
+        .iter()
+        .filter(|event| event.occurred_at.to_micros_since_unix_epoch() < cutoff)
+        .map(|event| event.id)
+        .collect();
+    for id in expired {
+        ctx.db.combat_event().id().delete(id);
+    }
+    let expired: Vec<u64> = ctx
+        .db
+        .level_up_event()
+        .iter()
+        .filter(|event| event.occurred_at.to_micros_since_unix_epoch() < cutoff)
+        .map(|event| event.id)
+        .collect();
+    for id in expired {
+        ctx.db.level_up_event().id().delete(id);
+    }
+}
+
+/// Publish attacks that happened in a map for clients to show
+pub fn record_combat_events(ctx: &ReducerContext, map_id: u64, events: Vec<logic::CombatEvent>) {
+    for event in events {
+        let breakdown = event.result.breakdown;
+        ctx.db.combat_event().insert(CombatEvent {
+            id: 0, // auto_inc will handle this
+            map_id,
+            attacker_entity_id: event.attacker_id,
+            target_entity_id: event.target_id,
+            outcome: breakdown.outcome.into(),
+            damage_type: breakdown.damage_type.into(),
+            raw_damage: breakdown.raw_damage,
+            mitigated_damage: breakdown.mitigated_damage,
+            damage_dealt: breakdown.damage_dealt,
+            target_died: event.result.target_died,
+            occurred_at: ctx.timestamp,
+        });
+    }
+}
+
+/// Publish levels reached in a map for clients to show
+pub fn record_level_ups(ctx: &ReducerContext, map_id: u64, level_ups: Vec<logic::LevelUp>) {
+    for level_up in level_ups {
+        log::info!(
+            "Entity {} reached level {}",
+            level_up.entity_id,
+            level_up.level
+        );
+        ctx.db.level_up_event().insert(LevelUpEvent {
+            id: 0, // auto_inc will handle this
+            map_id,
+            entity_id: level_up.entity_id,
+            level: level_up.level,
+            occurred_at: ctx.timestamp,
+        });
+    }
+}
+
+/// Maps with at least one player in them; empty maps are left frozen
+fn active_map_ids(ctx: &ReducerContext) -> HashSet<u64> {
+    ctx.db
+        .player()
+        .iter()
+        .filter_map(|player| player.current_map_id)
+        .collect()
+}
+
+/// Run one step of the game logic for the entities of a map and store the result
+fn simulate_map(ctx: &ReducerContext, map_id: u64, delta_time: f64, now: f64) {
+    let Some(mut map) = ctx.db.map().id().find(map_id) else {
+        return;
+    };
+
+    let mut manager = EntityManager::new();
+    for &entity_id in &map.entity_ids {
+        load_game_entity(ctx, &mut manager, entity_id);
+    }
+    manager.set_next_id(FIRST_SPAWNED_ID);
+    let paths = PATH_CACHES.with(|caches| caches.borrow_mut().remove(&map_id));
+    manager.set_navigation(
+        to_game_map(&map),
+        PathParams::default(),
+        paths.unwrap_or_default(),
+    );
+    // Different rolls every tick and map, but the same ones when a tick is replayed
+    manager.set_seed(ctx.timestamp.to_micros_since_unix_epoch() as u64 ^ map_id.rotate_left(32));
+    let previous_positions: Vec<(u64, f64, f64)> = manager
+        .store::<Transform>()
+        .iter()
+        .map(|(id, transform)| (id, transform.position.x, transform.position.y))
+        .collect();
+
+    manager.update_entities(delta_time, now);
+    if let Some(paths) = manager.take_path_cache() {
+        PATH_CACHES.with(|caches| caches.borrow_mut().insert(map_id, paths));
+    }
+    record_combat_events(ctx, map_id, manager.take_combat_events());
+
+    for (entity_id, x, y) in previous_positions {
+        let Some(transform) = manager.get_mut::<Transform>(entity_id) else {
+            continue;
+        };
+
+        // Face the way the entity moved; y grows downwards, so north is -y
+        let dx = transform.position.x - x;
+        let dy = transform.position.y - y;
+        if dx != 0.0 || dy != 0.0 {
+            transform.direction = (-dy).atan2(dx).rem_euclid(TAU);
+        }
+        store_game_entity(ctx, &manager, entity_id);
+    }
+
+    // Dead players stay in the map until they respawn
+    let monsters_before = manager.store::<logic::MonsterKind>().len();
+    let mut dead_ids = manager.cleanup_expired_entities(now);
+    dead_ids.extend(manager.cleanup_dead_entities(now));
+
+    // Whoever is in a dungeon when its last monster falls cleared it
+    if map.map_type == MapType::Dungeon
+        && monsters_before > 0
+        && manager.store::<logic::MonsterKind>().is_empty()
+    {
+        log::info!("Map {} was cleared of monsters", map_id);
+        for entity_id in manager.store::<logic::QuestLog>().ids() {
+            manager.record_quest_event(entity_id, QuestEvent::ClearedDungeon);
+        }
+    }
+
+    // Killers got their share of experience and quest kills and the fallen their death
+    // penalty as the dead were cleaned up
+    for entity_id in manager.store::<logic::Experience>().ids() {
+        store_game_entity(ctx, &manager, entity_id);
+    }
+    record_level_ups(ctx, map_id, manager.take_level_ups());
+
+    // Projectiles launched, loot dropped and gravestones left this tick
+    let mut spawned_ids = store_spawned_entities(ctx, &mut manager, &mut map);
+    for &entity_id in &dead_ids {
+        delete_entity(ctx, entity_id);
+    }
+    map.entity_ids.retain(|id| !dead_ids.contains(id));
+
+    // Monsters return to their posts once their killers have had some time
+    spawned_ids.extend(respawn_monsters(ctx, &mut map, now));
+    if dead_ids.is_empty() && spawned_ids.is_empty() {
+        return;
+    }
+
+    ctx.db.map().id().update(map);
+    if !dead_ids.is_empty() {
+        log::info!(
+            "Removed {} dead or expired entities from map {}",
+            dead_ids.len(),
+            map_id
+        );
+    }
+}
+
 // Helper function to initialize the tick system (called from init reducer)
 pub fn initialize_tick_system(ctx: &ReducerContext) -> Result<(), String> {
     let current_time = ctx.timestamp;
@@ -0,0 +1,213 @@
+use crate::tables::{
+    entity, entity_death, map, map_transition, player, world_link, MapTransition, Player, WorldLink,
+};
+use crate::types::Vec2;
+use crate::vision::update_player_vision;
+use game_module::map_generator::{ObjectType, Position};
+use spacetimedb::{reducer, ReducerContext, Table};
+
+/// How close a player's entity must be to a portal to use it
+pub const PORTAL_RANGE: f64 = 1.5;
+
+/// Add a transition tile to a map that moves whoever steps on it to `arrival` in another map
+pub fn add_transition(
+    ctx: &ReducerContext,
+    map_id: u64,
+    tile: &Position,
+    destination_map_id: u64,
+    arrival: &Position,
+) {
+    ctx.db.map_transition().insert(MapTransition {
+        id: 0, // auto_inc will handle this
+        map_id,
+        x: tile.x as u64,
+        y: tile.y as u64,
+        destination_map_id,
+        arrival_position: Vec2 {
+            x: arrival.x as f64,
+            y: arrival.y as f64,
+        },
+    });
+}
+
+/// Move a player's entity into another map at the given position
+pub fn transfer_player(
+    ctx: &ReducerContext,
+    player: Player,
+    destination_map_id: u64,
+    arrival: Vec2,
+) -> Result<(), String> {
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+    if ctx.db.entity_death().entity_id().find(entity_id).is_some() {
+        return Err("Dead players cannot travel".to_string());
+    }
+
+    let mut destination = ctx
+        .db
+        .map()
+        .id()
+        .find(destination_map_id)
+        .ok_or("Destination map not found")?;
+    let mut entity = ctx
+        .db
+        .entity()
+        .id()
+        .find(entity_id)
+        .ok_or("Entity not found")?;
+
+    // Leave the current map
+    if let Some(mut current) = player
+        .current_map_id
+        .and_then(|id| ctx.db.map().id().find(id))
+    {
+        current.entity_ids.retain(|&id| id != entity_id);
+        ctx.db.map().id().update(current);
+    }
+
+    // Enter the destination map
+    if !destination.entity_ids.contains(&entity_id) {
+        destination.entity_ids.push(entity_id);
+    }
+    update_player_vision(ctx, player.identity, &destination, &arrival);
+    ctx.db.map().id().update(destination);
+
+    entity.position = arrival;
+    ctx.db.entity().id().update(entity);
+
+    ctx.db.player().identity().update(Player {
+        current_map_id: Some(destination_map_id),
+        ..player
+    });
+
+    Ok(())
+}
+
+/// Send a player through the transition tile at their position, if there is one.
+/// Returns whether the player changed maps.
+pub fn take_transition_at(
+    ctx: &ReducerContext,
+    player: Player,
+    position: &Vec2,
+) -> Result<bool, String> {
+    let Some(map_id) = player.current_map_id else {
+        return Ok(false);
+    };
+    if position.x < 0.0 || position.y < 0.0 {
+        return Ok(false);
+    }
+
+    // Entity positions are tile centres
+    let x = position.x.round() as u64;
+    let y = position.y.round() as u64;
+
+    let Some(transition) = ctx
+        .db
+        .map_transition()
+        .iter()
+        .find(|t| t.map_id == map_id && t.x == x && t.y == y)
+    else {
+        return Ok(false);
+    };
+
+    log::info!(
+        "{:?} travelled from map {} to map {}",
+        ctx.sender,
+        map_id,
+        transition.destination_map_id
+    );
+    transfer_player(
+        ctx,
+        player,
+        transition.destination_map_id,
+        transition.arrival_position,
+    )?;
+    Ok(true)
+}
+
+/// Add an edge to the world graph from the portal of one map to the spawn of another
+pub fn link_maps(ctx: &ReducerContext, from_map_id: u64, to_map_id: u64) -> Result<(), String> {
+    let from_map = ctx
+        .db
+        .map()
+        .id()
+        .find(from_map_id)
+        .ok_or("Source map not found")?;
+    if ctx.db.map().id().find(to_map_id).is_none() {
+        return Err("Destination map not found".to_string());
+    }
+
+    let portal = from_map
+        .objects
+        .iter()
+        .find(|o| ObjectType::from_u8(o.kind) == Some(ObjectType::Portal))
+        .ok_or_else(|| format!("Map {} has no portal", from_map_id))?;
+
+    ctx.db.world_link().insert(WorldLink {
+        id: 0, // auto_inc will handle this
+        from_map_id,
+        to_map_id,
+        portal_x: portal.x as u64,
+        portal_y: portal.y as u64,
+    });
+
+    log::info!("Linked map {} to map {}", from_map_id, to_map_id);
+    Ok(())
+}
+
+#[reducer]
+/// Clients invoke this reducer to travel through a portal next to their entity. A map can be
+/// linked to several others through the same portal, so clients pick the destination from
+/// the `world_link` rows of their map.
+pub fn use_portal(ctx: &ReducerContext, destination_map_id: u64) -> Result<(), String> {
+    let player = ctx
+        .db
+        .player()
+        .identity()
+        .find(ctx.sender)
+        .ok_or("Player not found")?;
+
+    let entity_id = player.entity_id.ok_or("Player has no associated entity")?;
+    let map_id = player.current_map_id.ok_or("Player is not in a map")?;
+
+    let entity = ctx
+        .db
+        .entity()
+        .id()
+        .find(entity_id)
+        .ok_or("Entity not found")?;
+    let nearby: Vec<WorldLink> = ctx
+        .db
+        .world_link()
+        .iter()
+        .filter(|l| l.from_map_id == map_id)
+        .filter(|l| {
+            let dx = l.portal_x as f64 - entity.position.x;
+            let dy = l.portal_y as f64 - entity.position.y;
+            dx * dx + dy * dy <= PORTAL_RANGE * PORTAL_RANGE
+        })
+        .collect();
+    if nearby.is_empty() {
+        return Err("There is no portal nearby".to_string());
+    }
+    let link = nearby
+        .into_iter()
+        .find(|l| l.to_map_id == destination_map_id)
+        .ok_or("No portal nearby leads there")?;
+
+    let destination = ctx
+        .db
+        .map()
+        .id()
+        .find(link.to_map_id)
+        .ok_or("Destination map not found")?;
+
+    transfer_player(ctx, player, destination.id, destination.spawn_position)?;
+
+    log::info!(
+        "{:?} used a portal from map {} to map {}",
+        ctx.sender,
+        map_id,
+        destination.id
+    );
+    Ok(())
+}
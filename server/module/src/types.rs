@@ -1,7 +1,25 @@
 use spacetimedb::SpacetimeType;
 
-#[derive(SpacetimeType, Clone, Copy, Debug)]
+#[derive(SpacetimeType, Clone, Copy, Debug, PartialEq)]
 pub struct Vec2 {
     pub x: f64,
     pub y: f64,
 }
+
+/// A stack of items waiting on a map tile until it is revealed
+#[derive(SpacetimeType, Clone, Debug, PartialEq, Eq)]
+pub struct HiddenItem {
+    pub x: u16,
+    pub y: u16,
+    pub item_id: String,
+    pub count: u32,
+}
+
+/// A prop on one of the sparse map layers (decorations or interactable objects), or a single
+/// tile kept outside of the flattened tile array
+#[derive(SpacetimeType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapProp {
+    pub x: u16,
+    pub y: u16,
+    pub kind: u8, // DecorationType, ObjectType or TileType value, depending on the layer
+}
@@ -1,7 +1,9 @@
+use crate::entity::spawn_entity;
 use crate::tables::{
-    entity, game_info, map, player, player_offline, user, Entity, EntityType, GameInfo, Player,
-    PlayerOffline, User,
+    game_info, map, player, player_offline, player_vision, user, Entity, EntityType, GameInfo,
+    Player, PlayerOffline, User,
 };
+use crate::vision::update_player_vision;
 use spacetimedb::{reducer, ReducerContext, Table};
 
 #[reducer]
@@ -135,6 +137,7 @@ pub fn identity_disconnected(ctx: &ReducerContext) {
         });
         // Remove from Player table
         ctx.db.player().identity().delete(ctx.sender);
+        ctx.db.player_vision().identity().delete(ctx.sender);
     }
 }
 
@@ -225,20 +228,11 @@ pub fn spawn_player_entity(ctx: &ReducerContext) -> Result<(), String> {
         created_at: ctx.timestamp,
     };
 
-    ctx.db.entity().insert(new_entity);
-
-    // Find the inserted entity to get its actual ID
-    let entity_id = ctx
-        .db
-        .entity()
-        .iter()
-        .filter(|e| e.owner_identity == Some(ctx.sender) && e.entity_type == EntityType::Player)
-        .last()
-        .map(|e| e.id)
-        .ok_or("Failed to retrieve created entity")?;
+    let entity_id = spawn_entity(ctx, new_entity);
 
     // Add entity to the starting town's entity list
     starting_town.entity_ids.push(entity_id);
+    update_player_vision(ctx, ctx.sender, &starting_town, &spawn_position);
     ctx.db.map().id().update(starting_town);
 
     // Update player with entity ID and current map
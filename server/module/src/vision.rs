@@ -0,0 +1,50 @@
+use crate::init::to_game_map;
+use crate::tables::{map, player_vision, Map, PlayerVision};
+use crate::types::Vec2;
+use game_module::fov::compute_fov;
+use game_module::pathfinding::tile_of;
+use spacetimedb::{Identity, ReducerContext, Table};
+
+/// How many tiles far players can see in a lit map
+pub const VISION_RADIUS: u32 = 10;
+
+/// Recompute the tiles a player sees from `position` in `map` for their fog of war
+pub fn update_player_vision(ctx: &ReducerContext, identity: Identity, map: &Map, position: &Vec2) {
+    let origin = game_module::map::Vec2 {
+        x: position.x,
+        y: position.y,
+    };
+    let mut visible: Vec<u32> = match tile_of(&origin) {
+        Some(origin) => compute_fov(&to_game_map(map), origin, VISION_RADIUS)
+            .into_iter()
+            .filter(|&(x, y)| (x as u64) < map.width && (y as u64) < map.height)
+            .map(|(x, y)| (y as u64 * map.width + x as u64) as u32)
+            .collect(),
+        None => Vec::new(),
+    };
+    visible.sort_unstable();
+
+    let vision = PlayerVision {
+        identity,
+        map_id: map.id,
+        visible,
+        updated_at: ctx.timestamp,
+    };
+    if ctx.db.player_vision().identity().find(identity).is_some() {
+        ctx.db.player_vision().identity().update(vision);
+    } else {
+        ctx.db.player_vision().insert(vision);
+    }
+}
+
+/// Recompute a player's vision in the map with the given ID
+pub fn update_player_vision_in(
+    ctx: &ReducerContext,
+    identity: Identity,
+    map_id: u64,
+    position: &Vec2,
+) {
+    if let Some(map) = ctx.db.map().id().find(map_id) {
+        update_player_vision(ctx, identity, &map, position);
+    }
+}